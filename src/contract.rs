@@ -1,10 +1,29 @@
-#[cfg(not(feature = "library"))]
-use crate::helpers::asset_info_from_string;
+use crate::helpers::{asset_info_from_string, asset_info_to_string, MsgTransfer};
 use crate::msg::{
-    CollectFeeRequirement, ConfigResponse, DistributeTargetsResponse, ExecuteMsg, InstantiateMsg,
-    MigrateMsg, QueryMsg,
+    AccountBalance, AllocationEntry, AssetValue, CollectFeeRequirement, CollectPreview,
+    CollectionWorthwhileResponse, ConfigResponse, CurrentEpochResponse, DistributeTargetsResponse,
+    DistributionShare, EffectiveDistributionResponse, ExecuteMsg, FullCyclePlanResponse,
+    InstantiateMsg, MigrateMsg, NextScheduledDistributionResponse, OraclePriceResponse,
+    OracleQueryMsg, QueryMsg, Reconciliation, RegistryWeightsResponse, RouterTwapQueryMsg,
+    SimulateBalancesAfterResponse, SnapshotQueryMsg, StateSnapshot, TargetProfileResponse,
+    TargetRatio, TvlAsset, TvlResponse, TwapResponse, ValidatorWeight, WeightRegistryQueryMsg,
+    SUPPORTED_ACTIONS,
+};
+use crate::state::{
+    AllowedMsg, CollectGrant, CollectSnapshot, Config, ConfigHistoryEntry, DistributeTarget,
+    DistributionHistoryEntry, DistributionMode, EpochConfig, GenesisConfig, HookAttempt,
+    HookRetryConfig, IbcTarget, OracleConfig, PacketMemo, PauseFlags, PendingDistribution,
+    PendingRemoval, TargetKindRestriction, VaultConfig, AGGREGATE_EVENTS, CLAIMABLE,
+    CLAIM_EXPIRY_SECONDS, COLLECT_COOLDOWN_SECONDS, COLLECT_GRANTS, COLLECT_SNAPSHOT, CONFIG,
+    CONFIG_HISTORY, DISTRIBUTION_HISTORY, DISTRIBUTION_INTERVAL, DISTRIBUTION_MODE,
+    DISTRIBUTION_NONCE, DISTRIBUTION_TARGETS, EPOCH, EXECUTE_ALLOWLIST, EXECUTORS, FAILED_HOOKS,
+    GENESIS_CONFIG, HISTORY_MAX_AGE_SECONDS, HOOK_ATTEMPTS, HOOK_RETRY, LAST_COLLECTED,
+    LAST_CREDITED, LAST_DISTRIBUTION, LEGACY_CONFIG, MAX_ASSETS_PER_COLLECT,
+    MAX_HOOKS_PER_DISTRIBUTION, MAX_TARGET_SHARE_BPS, ORACLE, PAUSE_FLAGS, PENDING_COLLECT,
+    PENDING_COLLECT_GUARD, PENDING_DISTRIBUTION, PENDING_REMOVALS, REMAINDER_TARGET,
+    REMOVAL_GRACE_SECONDS, SUNSET, SUNSET_RECIPIENT, TARGET_KIND_RESTRICTION, TARGET_RECEIVED,
+    TOKEN_DISTRIBUTION_TARGETS, TOTAL_CLAIMABLE, VAULT, VAULT_HELD, WEIGHT_REGISTRY,
 };
-use crate::state::{Config, DistributeTarget, CONFIG, DISTRIBUTION_TARGETS, EXECUTORS};
 use crate::ContractError;
 use cosmos_sdk_proto::cosmos::authz::v1beta1::MsgExec;
 use cosmos_sdk_proto::cosmos::bank::v1beta1::MsgSend;
@@ -12,19 +31,52 @@ use cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
 use cosmos_sdk_proto::traits::{Message, MessageExt};
 
 use cosmwasm_std::{
-    entry_point, to_json_binary, Addr, CosmosMsg, Decimal, Storage, Uint128, WasmMsg,
+    coin, coins, entry_point, from_json, to_json_binary, to_json_string, Addr, BankMsg, CosmosMsg,
+    Decimal, Event, Fraction, Order, QuerierWrapper, Reply, StakingMsg, StdError, Storage, SubMsg,
+    SubMsgResult, Timestamp, Uint128, WasmMsg,
 };
 use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
-use cw2::set_contract_version;
-use cw20::{BalanceResponse, Cw20ExecuteMsg};
-use oraiswap::asset::AssetInfo;
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{AllowanceResponse, BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, TokenInfoResponse};
+use cw_ownable::OwnershipError;
+use cw_storage_plus::{Bound, Item, Map};
+use oraiswap::asset::{Asset, AssetInfo};
 use oraiswap::mixed_router::{
-    Cw20HookMsg as Cw20RouterHookMsg, ExecuteMsg as RouterExecuteMsg, SwapOperation,
+    Cw20HookMsg as Cw20RouterHookMsg, ExecuteMsg as RouterExecuteMsg, QueryMsg as RouterQueryMsg,
+    SwapOperation,
 };
+use oraiswap::router::SimulateSwapOperationsResponse;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::Serialize;
+use std::collections::BTreeMap;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:tresury";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+// maximum size in bytes of a target msg_hook
+pub const MAX_HOOK_SIZE: usize = 1024;
+// target weights are percentages of the distributed amount
+pub const MAX_TOTAL_WEIGHT: u64 = 100;
+// packet forward middleware hops an IBC target may route through
+pub const MAX_FORWARD_HOPS: usize = 4;
+
+const COLLECT_FEES_REPLY_ID: u64 = 1;
+// hook submessages get consecutive reply ids from here
+const HOOK_REPLY_ID_START: u64 = 100;
+/// Window of the router TWAP `DistributeNormalized` values preferred assets at
+const NORMALIZATION_TWAP_WINDOW_SECONDS: u64 = 1800;
+// oldest entries are evicted once the config history is full
+pub const MAX_CONFIG_HISTORY: usize = 20;
+// oldest entries are evicted once the distribution history is full
+pub const MAX_DISTRIBUTION_HISTORY: usize = 20;
+// claimable entries exported per state snapshot page
+pub const MAX_SNAPSHOT_ENTRIES: usize = 100;
+// virtual shares and vault denom priced in on every deposit and redeem, an empty
+// vault can not be inflated to round the next deposit down to nothing
+const VAULT_VIRTUAL_OFFSET: Uint128 = Uint128::new(1);
+// slack on the quoted output of a preferred asset swap, covers the rounding of
+// consecutive swaps through the same route
+const PREFERRED_ASSET_SLIPPAGE: Decimal = Decimal::permille(5);
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -35,8 +87,9 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
+    cw_ownable::initialize_owner(deps.storage, deps.api, Some(msg.owner.as_str()))?;
+
     let config = Config {
-        owner: deps.api.addr_validate(msg.owner.as_str())?,
         distribute_token: msg.distribute_token,
         router: match msg.router {
             Some(addr) => Some(deps.api.addr_validate(addr.as_str())?),
@@ -46,19 +99,21 @@ pub fn instantiate(
 
     CONFIG.save(deps.storage, &config)?;
 
-    let valid_distribute_targets = msg
-        .init_distribution_targets
-        .iter()
-        .map(|target| {
-            Ok(DistributeTarget {
-                weight: target.weight,
-                addr: deps.api.addr_validate(target.addr.as_str())?,
-                msg_hook: target.msg_hook.clone(),
-            })
-        })
-        .collect::<Result<Vec<DistributeTarget>, ContractError>>()?;
+    let valid_distribute_targets =
+        validate_distribute_targets(deps.as_ref(), &msg.init_distribution_targets)?;
 
     DISTRIBUTION_TARGETS.save(deps.storage, &valid_distribute_targets)?;
+    DISTRIBUTION_MODE.save(deps.storage, &DistributionMode::Pull)?;
+
+    GENESIS_CONFIG.save(
+        deps.storage,
+        &GenesisConfig {
+            owner: deps.api.addr_validate(msg.owner.as_str())?,
+            distribute_token: config.distribute_token,
+            router: config.router,
+            init_distribution_targets: valid_distribute_targets,
+        },
+    )?;
 
     let valid_executors = msg
         .executors
@@ -81,26 +136,195 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::UpdateConfig {
-            owner,
-            distribute_token,
-        } => execute_update_config(deps, env, info, owner, distribute_token),
+        ExecuteMsg::UpdateOwnership(action) => {
+            assert_not_paused(deps.storage, Operation::Admin)?;
+            let ownership = cw_ownable::update_ownership(deps, &env.block, &info.sender, action)?;
+            Ok(Response::new()
+                .add_attribute("action", "update_ownership")
+                .add_attributes(ownership.into_attributes()))
+        }
+        ExecuteMsg::UpdateConfig { distribute_token } => {
+            execute_update_config(deps, env, info, distribute_token)
+        }
         ExecuteMsg::UpdateDistributeTarget { distribute_targets } => {
             execute_update_distribute_target(deps, env, info, distribute_targets)
         }
-        ExecuteMsg::Distribute { amount_distribute } => {
-            execute_distribute(deps, env, info, amount_distribute)
+        ExecuteMsg::UpdateTokenDistributeTargets {
+            token,
+            distribute_targets,
+        } => execute_update_token_distribute_targets(deps, env, info, token, distribute_targets),
+        ExecuteMsg::Distribute {
+            amount_distribute,
+            token,
+            nonce,
+        } => execute_distribute(deps, env, info, amount_distribute, token, nonce),
+        ExecuteMsg::UpdateWeights { updates } => execute_update_weights(deps, env, info, updates),
+        ExecuteMsg::UpdateTargetKindRestriction { restriction } => {
+            execute_update_target_kind_restriction(deps, env, info, restriction)
+        }
+        ExecuteMsg::DistributeFromAllowance {
+            token,
+            owner,
+            amount,
+        } => execute_distribute_from_allowance(deps, env, info, token, owner, amount),
+        ExecuteMsg::DistributeByVotingPower {
+            token,
+            height,
+            holders,
+            amount,
+        } => execute_distribute_by_voting_power(deps, env, info, token, height, holders, amount),
+        ExecuteMsg::UpdateWeightRegistry { registry } => {
+            execute_update_weight_registry(deps, env, info, registry)
+        }
+        ExecuteMsg::DistributeFromRegistry { amount } => {
+            execute_distribute_from_registry(deps, env, info, amount)
+        }
+        ExecuteMsg::UpdateMaxHooksPerDistribution { max_hooks } => {
+            execute_update_max_hooks_per_distribution(deps, env, info, max_hooks)
+        }
+        ExecuteMsg::ContinueDistribution {} => execute_continue_distribution(deps, env, info),
+        ExecuteMsg::UpdateRemovalGrace {
+            removal_grace_seconds,
+        } => execute_update_removal_grace(deps, env, info, removal_grace_seconds),
+        ExecuteMsg::RemoveDistributeTarget { addr } => {
+            execute_remove_distribute_target(deps, env, info, addr)
+        }
+        ExecuteMsg::UpdateDistributionInterval {
+            distribution_interval,
+        } => execute_update_distribution_interval(deps, env, info, distribution_interval),
+        ExecuteMsg::UpdateEpoch { epoch } => execute_update_epoch(deps, env, info, epoch),
+        ExecuteMsg::UpdateAggregateEvents { aggregate_events } => {
+            execute_update_aggregate_events(deps, env, info, aggregate_events)
+        }
+        ExecuteMsg::UpdateHistoryMaxAge {
+            history_max_age_seconds,
+        } => execute_update_history_max_age(deps, env, info, history_max_age_seconds),
+        ExecuteMsg::UpdateCollectCooldown {
+            collect_cooldown_seconds,
+        } => execute_update_collect_cooldown(deps, env, info, collect_cooldown_seconds),
+        ExecuteMsg::RegisterGrant { granter, grant } => {
+            execute_register_grant(deps, env, info, granter, grant)
+        }
+        ExecuteMsg::UpdateMaxAssetsPerCollect { max_assets } => {
+            execute_update_max_assets_per_collect(deps, env, info, max_assets)
+        }
+        ExecuteMsg::UpdateOracle { oracle } => execute_update_oracle(deps, env, info, oracle),
+        ExecuteMsg::UpdateHookRetry { hook_retry } => {
+            execute_update_hook_retry(deps, env, info, hook_retry)
+        }
+        ExecuteMsg::RetryHook { target, token } => {
+            execute_retry_hook(deps, env, info, target, token)
+        }
+        ExecuteMsg::Rebalance {
+            quote_denom,
+            target_ratios,
+            tolerance,
+            max_slippage,
+        } => execute_rebalance(
+            deps,
+            env,
+            info,
+            quote_denom,
+            target_ratios,
+            tolerance,
+            max_slippage,
+        ),
+        ExecuteMsg::UpdateRemainderTarget { remainder_target } => {
+            execute_update_remainder_target(deps, env, info, remainder_target)
+        }
+        ExecuteMsg::UpdateExecuteAllowlist { allowlist } => {
+            execute_update_execute_allowlist(deps, env, info, allowlist)
+        }
+        ExecuteMsg::Execute { msgs } => execute_dispatch_msgs(deps, env, info, msgs),
+        ExecuteMsg::UpdateVault { vault } => execute_update_vault(deps, env, info, vault),
+        ExecuteMsg::UpdateDistributionMode { mode } => {
+            execute_update_distribution_mode(deps, env, info, mode)
+        }
+        ExecuteMsg::UpdateClaimExpiry {
+            claim_expiry_seconds,
+        } => execute_update_claim_expiry(deps, env, info, claim_expiry_seconds),
+        ExecuteMsg::ReclaimUnclaimed { addr } => execute_reclaim_unclaimed(deps, env, info, addr),
+        ExecuteMsg::Claim {} => execute_claim(deps, env, info),
+        ExecuteMsg::DistributeNormalized { amount, nonce } => {
+            execute_distribute_normalized(deps, env, info, amount, nonce)
+        }
+        ExecuteMsg::DistributeDiversified {
+            amount,
+            allocation,
+            nonce,
+        } => execute_distribute_diversified(deps, env, info, amount, allocation, nonce),
+        ExecuteMsg::DistributeAsStake { amount, validators } => {
+            execute_distribute_as_stake(deps, env, info, amount, validators)
+        }
+        ExecuteMsg::UpdateSunsetRecipient { recipient } => {
+            execute_update_sunset_recipient(deps, env, info, recipient)
+        }
+        ExecuteMsg::Sunset {} => execute_sunset(deps, env, info),
+        ExecuteMsg::EmergencyWithdraw { assets } => {
+            execute_emergency_withdraw(deps, env, info, assets)
+        }
+        ExecuteMsg::UpdateMaxTargetShare {
+            max_target_share_bps,
+        } => execute_update_max_target_share(deps, env, info, max_target_share_bps),
+        ExecuteMsg::Deposit {} => execute_deposit(deps, env, info),
+        ExecuteMsg::Redeem { shares } => execute_redeem(deps, env, info, shares),
+        ExecuteMsg::SetPauseFlags { pause_flags } => {
+            execute_set_pause_flags(deps, env, info, pause_flags)
         }
         ExecuteMsg::CollectFees {
             collect_fee_requirements,
         } => execute_collect_fees(deps, env, info, collect_fee_requirements),
+        ExecuteMsg::CollectFeesGuarded {
+            collect_fee_requirements,
+            max_total_offer,
+        } => _execute_collect_fees(
+            deps,
+            env,
+            info,
+            collect_fee_requirements,
+            Some(max_total_offer),
+            None,
+        ),
+        ExecuteMsg::ContinueCollect {} => {
+            let pending = PENDING_COLLECT
+                .may_load(deps.storage)?
+                .ok_or(ContractError::NoPendingCollect {})?;
+            let max_total_offer = PENDING_COLLECT_GUARD.may_load(deps.storage)?;
+            PENDING_COLLECT.remove(deps.storage);
+            PENDING_COLLECT_GUARD.remove(deps.storage);
+            _execute_collect_fees(deps, env, info, pending, max_total_offer, None)
+        }
+        ExecuteMsg::CollectBurnDistribute {
+            collect_fee_requirements,
+            burn_ratio,
+            nonce,
+        } => {
+            if burn_ratio > Decimal::one() {
+                return Err(ContractError::InvalidBurnRatio { burn_ratio });
+            }
+            // the rest is distributed from the reply, it must not slip past the pause,
+            // the nonce, the throttle or the epoch, nor start while another
+            // distribution is pending
+            assert_not_paused(deps.storage, Operation::Distribute)?;
+            if PENDING_DISTRIBUTION.may_load(deps.storage)?.is_some() {
+                return Err(ContractError::DistributionInProgress {});
+            }
+            _begin_distribution(deps.storage, &env, nonce)?;
+            _execute_collect_fees(
+                deps,
+                env,
+                info,
+                collect_fee_requirements,
+                None,
+                Some(burn_ratio),
+            )
+        }
         ExecuteMsg::UpdateExecutors {
             executors,
             permission,
         } => {
-            if info.sender != CONFIG.load(deps.storage)?.owner {
-                return Err(ContractError::Unauthorized {});
-            }
+            assert_owner(deps.storage, &info.sender)?;
+            assert_not_paused(deps.storage, Operation::Admin)?;
             let valid_executors = executors
                 .iter()
                 .map(|executor| deps.api.addr_validate(executor.as_str()))
@@ -119,488 +343,5518 @@ pub fn execute(
 
 fn execute_update_config(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
-    owner: Option<Addr>,
     distribute_token: Option<Addr>,
 ) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
     let config = CONFIG.load(deps.storage)?;
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
-    }
+    assert_not_paused(deps.storage, Operation::Admin)?;
 
     let new_config = Config {
-        owner: owner.unwrap_or(config.owner),
         distribute_token: distribute_token.unwrap_or(config.distribute_token),
         router: config.router,
     };
 
-    CONFIG.save(deps.storage, &new_config)?;
+    _save_config(deps.storage, &env, &new_config)?;
 
     Ok(Response::new()
         .add_attribute("action", "update_config")
-        .add_attribute("owner", new_config.owner.as_str())
         .add_attribute("distribute_token", new_config.distribute_token.as_str()))
 }
 
+/// Save the config and append it to the bounded config history
+fn _save_config(storage: &mut dyn Storage, env: &Env, config: &Config) -> StdResult<()> {
+    CONFIG.save(storage, config)?;
+
+    let mut history = CONFIG_HISTORY.may_load(storage)?.unwrap_or_default();
+    history.push(ConfigHistoryEntry {
+        config: config.clone(),
+        updated_at: env.block.time,
+    });
+    if history.len() > MAX_CONFIG_HISTORY {
+        history.drain(..history.len() - MAX_CONFIG_HISTORY);
+    }
+    CONFIG_HISTORY.save(storage, &history)
+}
+
 fn execute_update_distribute_target(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
     distribute_targets: Vec<DistributeTarget>,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
 
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
-    }
-
-    let valid_distribute_targets = distribute_targets
-        .iter()
-        .map(|target| {
-            Ok(DistributeTarget {
-                weight: target.weight,
-                addr: deps.api.addr_validate(target.addr.as_str())?,
-                msg_hook: target.msg_hook.clone(),
-            })
-        })
-        .collect::<Result<Vec<DistributeTarget>, ContractError>>()?;
+    let valid_distribute_targets = validate_distribute_targets(deps.as_ref(), &distribute_targets)?;
 
     DISTRIBUTION_TARGETS.save(deps.storage, &valid_distribute_targets)?;
+    // the new target list supersedes any scheduled removal
+    PENDING_REMOVALS.clear(deps.storage);
 
     Ok(Response::new().add_attribute("action", "update_distribute_target"))
 }
 
-fn execute_distribute(
+fn execute_update_token_distribute_targets(
     deps: DepsMut,
-    env: Env,
+    _env: Env,
     info: MessageInfo,
-    amount_distribute: Uint128,
+    token: AssetInfo,
+    distribute_targets: Vec<DistributeTarget>,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
 
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    let token_key = asset_info_to_string(&token);
+
+    if distribute_targets.is_empty() {
+        TOKEN_DISTRIBUTION_TARGETS.remove(deps.storage, &token_key);
+    } else {
+        let valid_distribute_targets =
+            _validate_token_targets(deps.as_ref(), &token, &distribute_targets)?;
+        TOKEN_DISTRIBUTION_TARGETS.save(deps.storage, &token_key, &valid_distribute_targets)?;
     }
 
-    let balance: BalanceResponse = deps.querier.query_wasm_smart(
-        config.distribute_token.clone(),
-        &cw20_base::msg::QueryMsg::Balance {
-            address: env.contract.address.into(),
-        },
-    )?;
+    Ok(Response::new()
+        .add_attribute("action", "update_token_distribute_targets")
+        .add_attribute("token", token_key))
+}
 
-    balance
-        .balance
-        .checked_sub(amount_distribute)
-        .map_err(|_| ContractError::ExceedContractBalance {})?;
+/// `validate_distribute_targets` plus what the token allows, IBC transfers need a
+/// native token and hooks a cw20 one
+fn _validate_token_targets(
+    deps: Deps,
+    token: &AssetInfo,
+    distribute_targets: &[DistributeTarget],
+) -> Result<Vec<DistributeTarget>, ContractError> {
+    match token {
+        AssetInfo::Token { contract_addr } => {
+            deps.api.addr_validate(contract_addr.as_str())?;
+            if let Some(target) = distribute_targets
+                .iter()
+                .find(|target| target.ibc.is_some())
+            {
+                return Err(ContractError::IbcTargetRequiresNativeToken {
+                    addr: target.addr.to_string(),
+                });
+            }
+        }
+        AssetInfo::NativeToken { .. } => _assert_hooks_supported(token, distribute_targets)?,
+    }
+    validate_distribute_targets(deps, distribute_targets)
+}
+
+fn execute_update_removal_grace(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    removal_grace_seconds: u64,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    REMOVAL_GRACE_SECONDS.save(deps.storage, &removal_grace_seconds)?;
 
-    let messages = _load_target_messages(deps.storage, amount_distribute, config.distribute_token)?;
     Ok(Response::new()
-        .add_messages(messages)
-        .add_attribute("action", "distribute")
-        .add_attribute("amount_distribute", amount_distribute.to_string()))
+        .add_attribute("action", "update_removal_grace")
+        .add_attribute("removal_grace_seconds", removal_grace_seconds.to_string()))
 }
 
-pub fn execute_collect_fees(
+fn execute_remove_distribute_target(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    collect_fee_requirements: Vec<CollectFeeRequirement>,
+    addr: Addr,
 ) -> Result<Response, ContractError> {
-    if !EXECUTORS
-        .load(deps.storage, &info.sender)
-        .unwrap_or_default()
-    {
-        return Err(ContractError::Unauthorized {});
-    }
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
 
-    let config = CONFIG.load(deps.storage)?;
+    // the schedule holds on every list the target is on
+    let addr = deps.api.addr_validate(addr.as_str())?;
+    let mut listed = DISTRIBUTION_TARGETS
+        .load(deps.storage)?
+        .iter()
+        .any(|target| target.addr == addr);
+    for item in TOKEN_DISTRIBUTION_TARGETS.range(deps.storage, None, None, Order::Ascending) {
+        listed = listed || item?.1.iter().any(|target| target.addr == addr);
+    }
+    if !listed {
+        return Err(ContractError::TargetNotFound {
+            addr: addr.to_string(),
+        });
+    }
 
-    let mut messages: Vec<CosmosMsg> = vec![];
+    // removing again keeps the original schedule
+    let effective_at = match PENDING_REMOVALS.may_load(deps.storage, &addr)? {
+        Some(effective_at) => effective_at,
+        None => {
+            let effective_at = env.block.time.plus_seconds(
+                REMOVAL_GRACE_SECONDS
+                    .may_load(deps.storage)?
+                    .unwrap_or_default(),
+            );
+            PENDING_REMOVALS.save(deps.storage, &addr, &effective_at)?;
+            effective_at
+        }
+    };
 
-    if config.router.is_none() {
-        return Err(ContractError::RouterAndApproverNotSet {});
-    }
-    let router_unwrap = config.router.unwrap();
-    // create a new variable for better code readability
-    let fees_receiver = env.contract.address;
-    // convert 1 times to asset info
-    let distribute_asset_info =
-        asset_info_from_string(deps.api, config.distribute_token.clone().into());
-    // build swap operations
-    let approver_messages = collect_fee_requirements
-        .iter()
-        .map(|requirement| -> StdResult<Option<Vec<CosmosMsg>>> {
-            let operations = requirement.clone().swap_operations;
-
-            let offer_asset = if let SwapOperation::SwapV3 { pool_key, x_to_y } = &operations[0] {
-                if *x_to_y {
-                    asset_info_from_string(deps.api, pool_key.token_x.clone())
-                } else {
-                    asset_info_from_string(deps.api, pool_key.token_y.clone())
-                }
-            } else {
-                return Ok(None);
-            };
+    Ok(Response::new()
+        .add_attribute("action", "remove_distribute_target")
+        .add_attribute("addr", addr.as_str())
+        .add_attribute("effective_at", effective_at.seconds().to_string()))
+}
 
-            let final_ask_asset = if let SwapOperation::SwapV3 { pool_key, x_to_y } =
-                &operations[operations.len() - 1]
-            {
-                if *x_to_y {
-                    asset_info_from_string(deps.api, pool_key.token_y.clone())
-                } else {
-                    asset_info_from_string(deps.api, pool_key.token_x.clone())
-                }
-            } else {
-                return Ok(None);
-            };
+fn execute_update_distribution_interval(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    distribution_interval: u64,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
 
-            // final ask asset should be distribute token
-            if distribute_asset_info != final_ask_asset.clone() {
-                return Ok(None);
-            }
+    DISTRIBUTION_INTERVAL.save(deps.storage, &distribution_interval)?;
 
-            let balance = offer_asset
-                .query_pool(&deps.querier, requirement.approver.clone())
-                .unwrap_or_default();
+    Ok(Response::new()
+        .add_attribute("action", "update_distribution_interval")
+        .add_attribute("distribution_interval", distribution_interval.to_string()))
+}
 
-            if balance.is_zero() {
-                return Ok(None);
-            }
-            // Assume that the owner approve infinite allowance to the contract
-            match &offer_asset {
-                AssetInfo::Token { contract_addr } => {
-                    // transfer from only if distribute asset equals to offer_asset
-                    if distribute_asset_info == offer_asset.clone() {
-                        return Ok(Some(vec![CosmosMsg::Wasm(WasmMsg::Execute {
-                            contract_addr: contract_addr.clone().into(),
-                            msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
-                                owner: requirement.approver.to_string(),
-                                recipient: fees_receiver.to_string(),
-                                amount: balance,
-                            })?,
-                            funds: vec![],
-                        })]));
-                    }
+fn execute_update_epoch(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    epoch: Option<EpochConfig>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
 
-                    Ok(Some(vec![CosmosMsg::Wasm(WasmMsg::Execute {
-                        contract_addr: contract_addr.clone().into(),
-                        msg: to_json_binary(&Cw20ExecuteMsg::SendFrom {
-                            owner: requirement.approver.to_string(),
-                            contract: router_unwrap.to_string(),
-                            amount: balance,
-                            msg: to_json_binary(&Cw20RouterHookMsg::ExecuteSwapOperations {
-                                operations,
-                                minimum_receive: requirement.minimum_receive,
-                                to: Some(fees_receiver.to_string()),
-                                affiliates: None,
-                            })?,
-                        })?,
-                        funds: vec![],
-                    })]))
-                }
-                // handle native token
-                AssetInfo::NativeToken { denom } => {
-                    let mut swap_amount = balance;
-                    if denom == "orai" {
-                        // Left 1 orai for transaction fee
-                        swap_amount = swap_amount
-                            .checked_sub(Uint128::from(1000000u128))
-                            .unwrap_or_default();
-                    }
+    if epoch
+        .as_ref()
+        .is_some_and(|epoch| epoch.epoch_length_seconds == 0)
+    {
+        return Err(ContractError::InvalidEpochLength {});
+    }
+    save_or_remove(deps.storage, &EPOCH, epoch.as_ref())?;
 
-                    if swap_amount.is_zero() {
-                        return Ok(None);
-                    }
+    Ok(Response::new()
+        .add_attribute("action", "update_epoch")
+        .add_attributes(epoch.iter().flat_map(|epoch| {
+            [
+                (
+                    "epoch_length_seconds",
+                    epoch.epoch_length_seconds.to_string(),
+                ),
+                ("one_per_epoch", epoch.one_per_epoch.to_string()),
+            ]
+        })))
+}
 
-                    let send = MsgSend {
-                        from_address: requirement.approver.to_string(),
-                        to_address: fees_receiver.to_string(),
-                        amount: vec![Coin {
-                            denom: denom.clone(),
-                            amount: swap_amount.to_string(),
-                        }],
-                    };
-                    let send_any_result = send.to_any();
-                    if send_any_result.is_err() {
-                        return Ok(None);
-                    }
+fn _current_epoch(epoch: &EpochConfig, time: Timestamp) -> CurrentEpochResponse {
+    CurrentEpochResponse {
+        epoch: time.seconds() / epoch.epoch_length_seconds,
+        seconds_remaining: epoch.epoch_length_seconds - time.seconds() % epoch.epoch_length_seconds,
+    }
+}
 
-                    let stargate_value = Binary::from(
-                        MsgExec {
-                            grantee: fees_receiver.to_string(),
-                            msgs: vec![send_any_result.unwrap()],
-                        }
-                        .encode_to_vec(),
-                    );
-                    // transfer_from native token
-                    let stargate = CosmosMsg::Stargate {
-                        type_url: "/cosmos.authz.v1beta1.MsgExec".to_string(),
-                        value: stargate_value,
-                    };
-
-                    let wasm_swap = CosmosMsg::Wasm(WasmMsg::Execute {
-                        contract_addr: router_unwrap.to_string(),
-                        msg: to_json_binary(&RouterExecuteMsg::ExecuteSwapOperations {
-                            operations: operations.clone(),
-                            to: Some(fees_receiver.clone()),
-                            minimum_receive: requirement.minimum_receive,
-                            affiliates: None,
-                        })?,
-                        funds: vec![cosmwasm_std::Coin {
-                            denom: denom.clone(),
-                            amount: swap_amount,
-                        }],
-                    });
+fn execute_update_aggregate_events(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    aggregate_events: bool,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
 
-                    Ok(Some(vec![stargate, wasm_swap]))
-                }
-            }
-        })
-        .filter_map(|msgs| match msgs {
-            Ok(Some(cosmos_msgs)) => Some(cosmos_msgs),
-            _ => None,
-        })
-        .flatten()
-        .collect::<Vec<CosmosMsg>>();
+    AGGREGATE_EVENTS.save(deps.storage, &aggregate_events)?;
 
-    messages.extend(approver_messages);
+    Ok(Response::new()
+        .add_attribute("action", "update_aggregate_events")
+        .add_attribute("aggregate_events", aggregate_events.to_string()))
+}
 
-    let mut response = Response::new();
+fn execute_update_history_max_age(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    history_max_age_seconds: Option<u64>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
 
-    if !messages.is_empty() {
-        response = response.add_messages(messages);
-    }
+    save_or_remove(
+        deps.storage,
+        &HISTORY_MAX_AGE_SECONDS,
+        history_max_age_seconds.as_ref(),
+    )?;
 
-    Ok(response)
+    Ok(Response::new()
+        .add_attribute("action", "update_history_max_age")
+        .add_attributes(
+            history_max_age_seconds.map(|max_age| ("history_max_age_seconds", max_age.to_string())),
+        ))
 }
 
-fn _load_target_messages(
+/// Appends a distribution to the history, dropping the entries past the max age
+/// and the oldest ones once it is full
+fn _record_distribution(
     storage: &mut dyn Storage,
-    amount_distribute: Uint128,
-    distribute_token: Addr,
-) -> Result<Vec<WasmMsg>, ContractError> {
-    DISTRIBUTION_TARGETS
-        .load(storage)?
-        .iter()
-        .map(|target| -> Result<WasmMsg, ContractError> {
-            let transfer_amount = amount_distribute * Decimal::percent(target.weight as u64);
+    env: &Env,
+    token: &AssetInfo,
+    amount: Uint128,
+) -> StdResult<()> {
+    let mut history = DISTRIBUTION_HISTORY.may_load(storage)?.unwrap_or_default();
+    if let Some(max_age) = HISTORY_MAX_AGE_SECONDS.may_load(storage)? {
+        history.retain(|entry| entry.distributed_at.plus_seconds(max_age) >= env.block.time);
+    }
+    history.push(DistributionHistoryEntry {
+        token: asset_info_to_string(token),
+        amount,
+        distributed_at: env.block.time,
+    });
+    if history.len() > MAX_DISTRIBUTION_HISTORY {
+        history.drain(..history.len() - MAX_DISTRIBUTION_HISTORY);
+    }
+    DISTRIBUTION_HISTORY.save(storage, &history)
+}
 
-            let msg = match target.clone().msg_hook {
-                None => WasmMsg::Execute {
-                    contract_addr: distribute_token.clone().into(),
-                    msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-                        recipient: target.clone().addr.into(),
-                        amount: transfer_amount,
-                    })?,
-                    funds: vec![],
-                },
-                Some(msg_hook) => WasmMsg::Execute {
-                    contract_addr: distribute_token.clone().into(),
-                    msg: to_json_binary(&Cw20ExecuteMsg::Send {
-                        contract: target.clone().addr.into(),
-                        amount: transfer_amount,
-                        msg: msg_hook,
-                    })?,
-                    funds: vec![],
-                },
-            };
-            Ok(msg)
+/// Summary event of the non-zero payouts of a distribution when events are aggregated,
+/// otherwise a distribution only carries its action attributes
+fn _distribution_events(
+    storage: &dyn Storage,
+    payouts: &[(Uint128, DistributeTarget)],
+) -> StdResult<Vec<Event>> {
+    let shares = payouts
+        .iter()
+        .filter(|(amount, _)| !amount.is_zero())
+        .map(|(amount, target)| DistributionShare {
+            addr: target.addr.clone(),
+            amount: *amount,
         })
-        .collect::<Result<Vec<WasmMsg>, ContractError>>()
+        .collect::<Vec<DistributionShare>>();
+    _summary_events(storage, shares)
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Config {} => to_json_binary(&ConfigResponse(CONFIG.load(deps.storage)?)),
-        QueryMsg::DistributeTargets {} => to_json_binary(&DistributeTargetsResponse(
-            DISTRIBUTION_TARGETS.load(deps.storage)?,
-        )),
-        QueryMsg::IsExecutor { addr } => {
-            to_json_binary(&EXECUTORS.load(deps.storage, &addr).unwrap_or_default())
-        }
+/// Summary event of the shares of a distribution, staked distributions list their
+/// validators
+fn _summary_events(storage: &dyn Storage, shares: Vec<DistributionShare>) -> StdResult<Vec<Event>> {
+    if !AGGREGATE_EVENTS.may_load(storage)?.unwrap_or_default() {
+        return Ok(vec![]);
     }
-}
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
-    let mut config = CONFIG.load(deps.storage)?;
-    config.router = Some(msg.new_router);
-    CONFIG.save(deps.storage, &config)?;
-    Ok(Response::default())
+    Ok(vec![Event::new("distribute-summary")
+        .add_attribute("targets", shares.len().to_string())
+        .add_attribute("payouts", to_json_string(&shares)?)])
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::msg::{ConfigResponse, DistributeTargetsResponse, InstantiateMsg, QueryMsg};
-    use crate::state::{Config, DistributeTarget};
-    use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
-    use cosmwasm_std::{from_json, OwnedDeps, Uint128};
-    use cosmwasm_std::{
-        testing::{mock_dependencies, mock_env, mock_info},
-        Addr,
-    };
+fn execute_update_collect_cooldown(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    collect_cooldown_seconds: u64,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
 
-    use super::*;
+    COLLECT_COOLDOWN_SECONDS.save(deps.storage, &collect_cooldown_seconds)?;
 
-    fn _instantiate_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
-        let mut deps = mock_dependencies();
-        let init_distribution_targets = vec![
-            DistributeTarget {
-                weight: 40,
-                addr: Addr::unchecked("target1"),
-                msg_hook: Some(to_json_binary(&"hook1").unwrap()),
-            },
-            DistributeTarget {
-                weight: 60,
-                addr: Addr::unchecked("target2"),
-                msg_hook: None,
-            },
-        ];
+    Ok(Response::new()
+        .add_attribute("action", "update_collect_cooldown")
+        .add_attribute(
+            "collect_cooldown_seconds",
+            collect_cooldown_seconds.to_string(),
+        ))
+}
 
-        let msg = InstantiateMsg {
-            owner: Addr::unchecked("owner"),
-            distribute_token: Addr::unchecked("distribute_token"),
-            init_distribution_targets: init_distribution_targets.clone(),
-            router: Some(Addr::unchecked("router")),
+fn execute_register_grant(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    granter: String,
+    grant: Option<CollectGrant>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    let granter = deps.api.addr_validate(&granter)?;
+    match &grant {
+        Some(grant) => COLLECT_GRANTS.save(deps.storage, &granter, grant)?,
+        None => COLLECT_GRANTS.remove(deps.storage, &granter),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "register_grant")
+        .add_attribute("granter", granter)
+        .add_attribute("registered", grant.is_some().to_string()))
+}
+
+/// Earliest time `Distribute` is allowed, now when it is not throttled
+fn _next_distribution_at(storage: &dyn Storage, env: &Env) -> StdResult<Timestamp> {
+    let interval = DISTRIBUTION_INTERVAL.may_load(storage)?.unwrap_or_default();
+    Ok(match LAST_DISTRIBUTION.may_load(storage)? {
+        Some(last) if interval > 0 => last.plus_seconds(interval).max(env.block.time),
+        _ => env.block.time,
+    })
+}
+
+fn execute_update_max_assets_per_collect(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    max_assets: Option<u32>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+    if max_assets == Some(0) {
+        return Err(ContractError::InvalidMaxAssets {});
+    }
+
+    save_or_remove(deps.storage, &MAX_ASSETS_PER_COLLECT, max_assets.as_ref())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_max_assets_per_collect")
+        .add_attribute(
+            "max_assets",
+            max_assets.map_or("none".to_string(), |max_assets| max_assets.to_string()),
+        ))
+}
+
+fn execute_update_oracle(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    oracle: Option<OracleConfig>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    if let Some(oracle) = &oracle {
+        deps.api.addr_validate(oracle.oracle.as_str())?;
+    }
+    save_or_remove(deps.storage, &ORACLE, oracle.as_ref())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_oracle")
+        .add_attributes(oracle.iter().flat_map(|oracle| {
+            [
+                ("oracle", oracle.oracle.to_string()),
+                (
+                    "max_oracle_staleness_seconds",
+                    oracle.max_oracle_staleness_seconds.to_string(),
+                ),
+            ]
+        })))
+}
+
+/// Fails when an oracle is configured and its price of `denom` is too old
+fn _assert_oracle_fresh(deps: Deps, env: &Env, denom: String) -> Result<(), ContractError> {
+    let Some(oracle) = ORACLE.may_load(deps.storage)? else {
+        return Ok(());
+    };
+    _fresh_oracle_price(deps, env, &oracle, denom)?;
+    Ok(())
+}
+
+fn _fresh_oracle_price(
+    deps: Deps,
+    env: &Env,
+    oracle: &OracleConfig,
+    denom: String,
+) -> Result<Decimal, ContractError> {
+    let price: OraclePriceResponse = deps
+        .querier
+        .query_wasm_smart(oracle.oracle.clone(), &OracleQueryMsg::Price { denom })?;
+    if price
+        .updated_at
+        .plus_seconds(oracle.max_oracle_staleness_seconds)
+        < env.block.time
+    {
+        return Err(ContractError::StaleOracle {
+            updated_at: price.updated_at,
+        });
+    }
+    Ok(price.price)
+}
+
+fn execute_update_hook_retry(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    hook_retry: Option<HookRetryConfig>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    if let Some(hook_retry) = &hook_retry {
+        deps.api.addr_validate(hook_retry.fallback.as_str())?;
+    }
+    save_or_remove(deps.storage, &HOOK_RETRY, hook_retry.as_ref())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_hook_retry")
+        .add_attributes(hook_retry.iter().flat_map(|hook_retry| {
+            [
+                ("max_retries", hook_retry.max_retries.to_string()),
+                ("fallback", hook_retry.fallback.to_string()),
+            ]
+        })))
+}
+
+fn execute_retry_hook(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    target: Addr,
+    token: Option<Addr>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Distribute)?;
+
+    // without a retry config the hook is only resent, there is no fallback to sweep to
+    let hook_retry = HOOK_RETRY.may_load(deps.storage)?;
+    let token = match token {
+        Some(token) => deps.api.addr_validate(token.as_str())?,
+        None => CONFIG.load(deps.storage)?.distribute_token,
+    };
+    let failed = FAILED_HOOKS
+        .may_load(deps.storage, (&target, &token))?
+        .ok_or_else(|| ContractError::NoFailedHook {
+            addr: target.to_string(),
+            token: token.to_string(),
+        })?;
+    if env.block.height < failed.retry_at_height {
+        return Err(ContractError::RetryTooEarly {
+            height: failed.retry_at_height,
+        });
+    }
+    FAILED_HOOKS.remove(deps.storage, (&target, &token));
+
+    let response = Response::new()
+        .add_attribute("action", "retry_hook")
+        .add_attribute("target", target.as_str());
+
+    if let Some(hook_retry) =
+        hook_retry.filter(|hook_retry| failed.retries >= hook_retry.max_retries)
+    {
+        // give up on the hook, the payout goes to the fallback as a plain transfer
+        return Ok(response
+            .add_message(WasmMsg::Execute {
+                contract_addr: failed.token.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: hook_retry.fallback.to_string(),
+                    amount: failed.amount,
+                })?,
+                funds: vec![],
+            })
+            .add_attribute("swept_to", hook_retry.fallback.as_str()));
+    }
+
+    let msg = WasmMsg::Execute {
+        contract_addr: failed.token.to_string(),
+        msg: to_json_binary(&Cw20ExecuteMsg::Send {
+            contract: target.to_string(),
+            amount: failed.amount,
+            msg: failed.msg_hook.clone(),
+        })?,
+        funds: vec![],
+    };
+    let retries = failed.retries + 1;
+    let id = _next_hook_reply_id(deps.storage)?;
+    let mut submsg = SubMsg::reply_always(msg, id);
+    submsg.gas_limit = failed.gas_limit;
+    HOOK_ATTEMPTS.save(deps.storage, id, &HookAttempt { retries, ..failed })?;
+
+    Ok(response
+        .add_submessage(submsg)
+        .add_attribute("retries", retries.to_string()))
+}
+
+fn execute_rebalance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    quote_denom: String,
+    target_ratios: Vec<TargetRatio>,
+    tolerance: Decimal,
+    max_slippage: Decimal,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+    let total_ratio = target_ratios
+        .iter()
+        .try_fold(Decimal::zero(), |total, target_ratio| {
+            total.checked_add(target_ratio.ratio)
+        });
+    if total_ratio != Ok(Decimal::one()) {
+        return Err(ContractError::InvalidTargetRatios {});
+    }
+    let router = CONFIG
+        .load(deps.storage)?
+        .router
+        .ok_or(ContractError::RouterAndApproverNotSet {})?;
+
+    // funds held on behalf of others are not the treasury's to rebalance
+    let mut valued = vec![];
+    for target_ratio in target_ratios {
+        let (balance, value) = _value_asset(
+            deps.as_ref(),
+            &env,
+            Some(&router),
+            &quote_denom,
+            &target_ratio.asset,
+        )?;
+        let value = value.ok_or_else(|| ContractError::UnpricedAsset {
+            asset: asset_info_to_string(&target_ratio.asset.info),
+        })?;
+        let free_balance = balance.saturating_sub(_reserved_asset_amount(
+            deps.as_ref(),
+            &target_ratio.asset.info,
+        )?);
+        let value = value.multiply_ratio(free_balance, balance.max(Uint128::one()));
+        valued.push((target_ratio, free_balance, value));
+    }
+    let total = valued.iter().map(|(_, _, value)| *value).sum::<Uint128>();
+    if total.is_zero() {
+        return Ok(Response::new().add_attribute("action", "rebalance"));
+    }
+
+    // over weight assets are sold into the quote first, the proceeds then buy
+    // the under weight ones
+    let quote_info = asset_info_from_string(deps.api, quote_denom.clone());
+    let mut available = quote_info
+        .query_pool(&deps.querier, env.contract.address.clone())?
+        .saturating_sub(_reserved_asset_amount(deps.as_ref(), &quote_info)?);
+    let mut sells = vec![];
+    let mut buys = vec![];
+    for (target_ratio, balance, value) in &valued {
+        if asset_info_to_string(&target_ratio.asset.info) == quote_denom {
+            continue;
+        }
+        let target_value = total * target_ratio.ratio;
+        let current_ratio = Decimal::from_ratio(*value, total);
+        if current_ratio > target_ratio.ratio + tolerance {
+            let excess = *value - target_value;
+            let (message, minimum_receive) = _quoted_swap_message(
+                deps.as_ref(),
+                &router,
+                &target_ratio.asset.info,
+                balance.multiply_ratio(excess, *value),
+                target_ratio.asset.operations.clone(),
+                &env.contract.address,
+                max_slippage,
+            )?;
+            sells.push(message);
+            // only the guaranteed proceeds are spent on the buys
+            available += minimum_receive;
+        } else if current_ratio + tolerance < target_ratio.ratio {
+            buys.push((target_ratio, target_value - *value));
+        }
+    }
+
+    let mut messages = sells;
+    for (target_ratio, deficit) in buys {
+        let amount = deficit.min(available);
+        if amount.is_zero() {
+            break;
+        }
+        available -= amount;
+        let (message, _) = _quoted_swap_message(
+            deps.as_ref(),
+            &router,
+            &quote_info,
+            amount,
+            target_ratio.buy_operations.clone(),
+            &env.contract.address,
+            max_slippage,
+        )?;
+        messages.push(message);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "rebalance")
+        .add_attribute("swaps", messages.len().to_string())
+        .add_messages(messages))
+}
+
+/// Router swap that fails unless it receives at least its quote less `slippage`,
+/// returned with that minimum
+fn _quoted_swap_message(
+    deps: Deps,
+    router: &Addr,
+    offer_asset: &AssetInfo,
+    amount: Uint128,
+    operations: Vec<SwapOperation>,
+    to: &Addr,
+    slippage: Decimal,
+) -> Result<(CosmosMsg, Uint128), ContractError> {
+    let quote: SimulateSwapOperationsResponse = deps.querier.query_wasm_smart(
+        router,
+        &RouterQueryMsg::SimulateSwapOperations {
+            offer_amount: amount,
+            operations: operations.clone(),
+        },
+    )?;
+    let minimum_receive = quote.amount * Decimal::one().saturating_sub(slippage);
+    let message = _build_swap_message(
+        router,
+        offer_asset,
+        amount,
+        operations,
+        to,
+        Some(minimum_receive),
+    )?;
+    Ok((message, minimum_receive))
+}
+
+/// Plain transfer of `amount` of `asset` held by the contract
+fn _build_transfer_message(
+    asset: &AssetInfo,
+    amount: Uint128,
+    recipient: &Addr,
+) -> StdResult<CosmosMsg> {
+    Ok(match asset {
+        AssetInfo::NativeToken { denom } => CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(amount.u128(), denom),
+        }),
+        AssetInfo::Token { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }),
+    })
+}
+
+/// Router swap of `amount` of `offer_asset` sending the output to `to`
+fn _build_swap_message(
+    router: &Addr,
+    offer_asset: &AssetInfo,
+    amount: Uint128,
+    operations: Vec<SwapOperation>,
+    to: &Addr,
+    minimum_receive: Option<Uint128>,
+) -> StdResult<CosmosMsg> {
+    Ok(match offer_asset {
+        AssetInfo::Token { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                contract: router.to_string(),
+                amount,
+                msg: to_json_binary(&Cw20RouterHookMsg::ExecuteSwapOperations {
+                    operations,
+                    minimum_receive,
+                    to: Some(to.to_string()),
+                    affiliates: None,
+                })?,
+            })?,
+            funds: vec![],
+        }),
+        AssetInfo::NativeToken { denom } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: router.to_string(),
+            msg: to_json_binary(&RouterExecuteMsg::ExecuteSwapOperations {
+                operations,
+                minimum_receive,
+                to: Some(to.clone()),
+                affiliates: None,
+            })?,
+            funds: coins(amount.u128(), denom),
+        }),
+    })
+}
+
+fn execute_update_remainder_target(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    remainder_target: Option<Addr>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    let remainder_target = remainder_target
+        .map(|remainder_target| deps.api.addr_validate(remainder_target.as_str()))
+        .transpose()?;
+    save_or_remove(deps.storage, &REMAINDER_TARGET, remainder_target.as_ref())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_remainder_target")
+        .add_attributes(
+            remainder_target.map(|remainder_target| ("remainder_target", remainder_target)),
+        ))
+}
+
+fn execute_update_execute_allowlist(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    allowlist: Vec<AllowedMsg>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    for allowed in &allowlist {
+        let addr = match allowed {
+            AllowedMsg::BankSend { to_address, .. } => to_address,
+            AllowedMsg::WasmExecute { contract_addr, .. } => contract_addr,
+        };
+        deps.api.addr_validate(addr.as_str())?;
+    }
+    EXECUTE_ALLOWLIST.save(deps.storage, &allowlist)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_execute_allowlist")
+        .add_attribute("allowed", allowlist.len().to_string()))
+}
+
+fn execute_dispatch_msgs(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msgs: Vec<CosmosMsg>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    let allowlist = EXECUTE_ALLOWLIST
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    for (index, msg) in msgs.iter().enumerate() {
+        if !allowlist
+            .iter()
+            .any(|allowed| _is_allowed_msg(allowed, msg))
+        {
+            return Err(ContractError::MsgNotAllowed {
+                index: index as u32,
+            });
+        }
+    }
+    _assert_dispatch_unreserved(deps.as_ref(), &env, &msgs)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "execute")
+        .add_attribute("msgs", msgs.len().to_string())
+        .add_messages(msgs))
+}
+
+fn execute_update_vault(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    vault: Option<VaultConfig>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    // depositors must be able to redeem what they were minted for
+    if let Some(current) = VAULT.may_load(deps.storage)? {
+        let (supply, _) = _vault_state(deps.as_ref(), &current)?;
+        if !supply.is_zero() {
+            return Err(ContractError::VaultInUse {});
+        }
+    }
+    if let Some(vault) = &vault {
+        deps.api.addr_validate(vault.receipt_token.as_str())?;
+    }
+    save_or_remove(deps.storage, &VAULT, vault.as_ref())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_vault")
+        .add_attributes(vault.into_iter().flat_map(|vault| {
+            [
+                ("receipt_token", vault.receipt_token.to_string()),
+                ("denom", vault.denom),
+            ]
+        })))
+}
+
+fn execute_update_max_target_share(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    max_target_share_bps: Option<u32>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    if let Some(max_bps) = max_target_share_bps {
+        if !(1..=10_000).contains(&max_bps) {
+            return Err(ContractError::InvalidMaxTargetShare { max_bps });
+        }
+        // the cap can not leave the current target lists in breach
+        _assert_target_shares(&DISTRIBUTION_TARGETS.load(deps.storage)?, max_bps)?;
+        for item in TOKEN_DISTRIBUTION_TARGETS.range(deps.storage, None, None, Order::Ascending) {
+            _assert_target_shares(&item?.1, max_bps)?;
+        }
+    }
+    save_or_remove(
+        deps.storage,
+        &MAX_TARGET_SHARE_BPS,
+        max_target_share_bps.as_ref(),
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_max_target_share")
+        .add_attributes(
+            max_target_share_bps.map(|max_bps| ("max_target_share_bps", max_bps.to_string())),
+        ))
+}
+
+/// Receipt token supply and vault denom deposited. Only deposits and redeems move
+/// the deposited amount, funds sent to the contract otherwise do not price the shares
+fn _vault_state(deps: Deps, vault: &VaultConfig) -> StdResult<(Uint128, Uint128)> {
+    let token_info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(&vault.receipt_token, &Cw20QueryMsg::TokenInfo {})?;
+    let held = VAULT_HELD.may_load(deps.storage)?.unwrap_or_default();
+    Ok((token_info.total_supply, held))
+}
+
+fn execute_deposit(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    // deposits bring funds in like collections do, and stop with them
+    assert_not_paused(deps.storage, Operation::Collect)?;
+    let vault = VAULT
+        .may_load(deps.storage)?
+        .ok_or(ContractError::VaultNotSet {})?;
+    let deposit = match info.funds.as_slice() {
+        [coin] if coin.denom == vault.denom && !coin.amount.is_zero() => coin.amount,
+        _ => return Err(ContractError::InvalidDeposit { denom: vault.denom }),
+    };
+
+    let (supply, held) = _vault_state(deps.as_ref(), &vault)?;
+    let shares = deposit.multiply_ratio(supply + VAULT_VIRTUAL_OFFSET, held + VAULT_VIRTUAL_OFFSET);
+    if shares.is_zero() {
+        return Err(ContractError::ZeroVaultAmount {});
+    }
+    VAULT_HELD.save(deps.storage, &(held + deposit))?;
+
+    Ok(Response::new()
+        .add_message(WasmMsg::Execute {
+            contract_addr: vault.receipt_token.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Mint {
+                recipient: info.sender.to_string(),
+                amount: shares,
+            })?,
+            funds: vec![],
+        })
+        .add_attribute("action", "deposit")
+        .add_attribute("depositor", info.sender.as_str())
+        .add_attribute("amount", deposit.to_string())
+        .add_attribute("shares", shares.to_string()))
+}
+
+fn execute_redeem(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    shares: Uint128,
+) -> Result<Response, ContractError> {
+    let vault = VAULT
+        .may_load(deps.storage)?
+        .ok_or(ContractError::VaultNotSet {})?;
+
+    let (supply, held) = _vault_state(deps.as_ref(), &vault)?;
+    if shares > supply {
+        return Err(ContractError::ExceedContractBalance {});
+    }
+    let amount = shares
+        .multiply_ratio(held + VAULT_VIRTUAL_OFFSET, supply + VAULT_VIRTUAL_OFFSET)
+        .min(held);
+    if amount.is_zero() {
+        return Err(ContractError::ZeroVaultAmount {});
+    }
+    VAULT_HELD.save(deps.storage, &(held - amount))?;
+
+    Ok(Response::new()
+        .add_message(WasmMsg::Execute {
+            contract_addr: vault.receipt_token.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::BurnFrom {
+                owner: info.sender.to_string(),
+                amount: shares,
+            })?,
+            funds: vec![],
+        })
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(amount.u128(), vault.denom),
+        })
+        .add_attribute("action", "redeem")
+        .add_attribute("redeemer", info.sender.as_str())
+        .add_attribute("shares", shares.to_string())
+        .add_attribute("amount", amount.to_string()))
+}
+
+fn _is_allowed_msg(allowed: &AllowedMsg, msg: &CosmosMsg) -> bool {
+    match (allowed, msg) {
+        (
+            AllowedMsg::BankSend {
+                to_address,
+                max_amount,
+            },
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient,
+                amount,
+            }),
+        ) => to_address.as_str() == recipient.as_str() && _within_max_funds(amount, max_amount),
+        (
+            AllowedMsg::WasmExecute {
+                contract_addr,
+                max_funds,
+            },
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: contract,
+                funds,
+                ..
+            }),
+        ) => contract_addr.as_str() == contract.as_str() && _within_max_funds(funds, max_funds),
+        _ => false,
+    }
+}
+
+/// Whether every denom of `funds` is listed in `max_funds`, within its amount
+fn _within_max_funds(funds: &[cosmwasm_std::Coin], max_funds: &[cosmwasm_std::Coin]) -> bool {
+    funds.iter().all(|fund| {
+        max_funds
+            .iter()
+            .any(|max_fund| max_fund.denom == fund.denom && fund.amount <= max_fund.amount)
+    })
+}
+
+/// Dispatched messages may only spend what is left of each balance once the amounts
+/// held for others are set aside. cw20 spends are read from the execute message
+fn _assert_dispatch_unreserved(
+    deps: Deps,
+    env: &Env,
+    msgs: &[CosmosMsg],
+) -> Result<(), ContractError> {
+    let mut spent: Vec<(AssetInfo, Uint128)> = vec![];
+    let mut add_spent = |asset: AssetInfo, amount: Uint128| match spent
+        .iter_mut()
+        .find(|(spent_asset, _)| *spent_asset == asset)
+    {
+        Some((_, total)) => *total += amount,
+        None => spent.push((asset, amount)),
+    };
+    for (index, msg) in msgs.iter().enumerate() {
+        let (coins_sent, execute) = match msg {
+            CosmosMsg::Bank(BankMsg::Send { amount, .. }) => (amount, None),
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                msg,
+                funds,
+            }) => (funds, Some((contract_addr, msg))),
+            _ => continue,
+        };
+        for coin_sent in coins_sent {
+            add_spent(
+                AssetInfo::NativeToken {
+                    denom: coin_sent.denom.clone(),
+                },
+                coin_sent.amount,
+            );
+        }
+
+        let Some((contract_addr, msg)) = execute else {
+            continue;
+        };
+        let token = AssetInfo::Token {
+            contract_addr: Addr::unchecked(contract_addr),
+        };
+        match from_json::<Cw20ExecuteMsg>(msg) {
+            Ok(
+                Cw20ExecuteMsg::Transfer { amount, .. }
+                | Cw20ExecuteMsg::Send { amount, .. }
+                | Cw20ExecuteMsg::Burn { amount },
+            ) => add_spent(token, amount),
+            // an allowance would let the spender take the reserved balance later on
+            Ok(Cw20ExecuteMsg::IncreaseAllowance { .. })
+                if !_reserved_asset_amount(deps, &token)?.is_zero() =>
+            {
+                return Err(ContractError::MsgNotAllowed {
+                    index: index as u32,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    for (asset, amount) in spent {
+        asset
+            .query_pool(&deps.querier, env.contract.address.clone())?
+            .checked_sub(_reserved_asset_amount(deps, &asset)?)
+            .and_then(|available| available.checked_sub(amount))
+            .map_err(|_| ContractError::ExceedContractBalance {})?;
+    }
+    Ok(())
+}
+
+/// Targets receiving `token`, the configured distribute token when omitted. Tokens
+/// without a list of their own use the default distribute targets
+fn _token_targets(deps: Deps, token: Option<&AssetInfo>) -> StdResult<Vec<DistributeTarget>> {
+    let token_key = match token {
+        Some(token) => asset_info_to_string(token),
+        None => CONFIG.load(deps.storage)?.distribute_token.to_string(),
+    };
+    match TOKEN_DISTRIBUTION_TARGETS.may_load(deps.storage, &token_key)? {
+        Some(targets) => Ok(targets),
+        None => DISTRIBUTION_TARGETS.load(deps.storage),
+    }
+}
+
+/// Hooks go out with a cw20 `Send`, a native `token` can not pay a hook-bearing target
+fn _assert_hooks_supported(
+    token: &AssetInfo,
+    targets: &[DistributeTarget],
+) -> Result<(), ContractError> {
+    if let AssetInfo::NativeToken { .. } = token {
+        if let Some(target) = targets.iter().find(|target| target.msg_hook.is_some()) {
+            return Err(ContractError::HooksRequireCw20Token {
+                addr: target.addr.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Distribute targets of `token` whose removal, if any, has not taken effect yet
+fn _active_targets(
+    deps: Deps,
+    env: &Env,
+    token: Option<&AssetInfo>,
+) -> StdResult<Vec<DistributeTarget>> {
+    let targets = _token_targets(deps, token)?;
+    let mut active = vec![];
+    for target in targets {
+        match PENDING_REMOVALS.may_load(deps.storage, &target.addr)? {
+            Some(effective_at) if effective_at <= env.block.time => {}
+            _ => active.push(target),
+        }
+    }
+    Ok(active)
+}
+
+fn execute_set_pause_flags(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    pause_flags: PauseFlags,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    if let Some(sunset_at) = SUNSET.may_load(deps.storage)? {
+        return Err(ContractError::Sunset { sunset_at });
+    }
+
+    PAUSE_FLAGS.save(deps.storage, &pause_flags)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_pause_flags")
+        .add_attribute("distribute", pause_flags.distribute.to_string())
+        .add_attribute("collect", pause_flags.collect.to_string())
+        .add_attribute("admin", pause_flags.admin.to_string()))
+}
+
+fn assert_owner(storage: &dyn Storage, sender: &Addr) -> Result<(), ContractError> {
+    cw_ownable::assert_owner(storage, sender).map_err(|err| match err {
+        OwnershipError::NotOwner | OwnershipError::NoOwner => ContractError::Unauthorized {},
+        err => err.into(),
+    })
+}
+
+/// Operations gated by the pause flags
+#[derive(Clone, Copy)]
+enum Operation {
+    Distribute,
+    Collect,
+    Admin,
+}
+
+fn assert_not_paused(storage: &dyn Storage, operation: Operation) -> Result<(), ContractError> {
+    let pause_flags = load_pause_flags(storage)?;
+    let (paused, operation) = match operation {
+        Operation::Distribute => (pause_flags.distribute, "distribute"),
+        Operation::Collect => (pause_flags.collect, "collect"),
+        Operation::Admin => (pause_flags.admin, "admin"),
+    };
+    if paused {
+        return Err(ContractError::Paused {
+            operation: operation.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Saves an optional setting, or clears it when unset
+fn save_or_remove<T>(storage: &mut dyn Storage, item: &Item<T>, value: Option<&T>) -> StdResult<()>
+where
+    T: Serialize + DeserializeOwned,
+{
+    match value {
+        Some(value) => item.save(storage, value),
+        None => {
+            item.remove(storage);
+            Ok(())
+        }
+    }
+}
+
+fn load_pause_flags(storage: &dyn Storage) -> StdResult<PauseFlags> {
+    // a sunset contract stays paused whatever the stored flags are
+    if SUNSET.may_load(storage)?.is_some() {
+        return Ok(PauseFlags {
+            distribute: true,
+            collect: true,
+            admin: true,
+        });
+    }
+    Ok(PAUSE_FLAGS.may_load(storage)?.unwrap_or_default())
+}
+
+fn execute_update_sunset_recipient(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    recipient: Option<Addr>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    let recipient = recipient
+        .map(|recipient| deps.api.addr_validate(recipient.as_str()))
+        .transpose()?;
+    save_or_remove(deps.storage, &SUNSET_RECIPIENT, recipient.as_ref())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_sunset_recipient")
+        .add_attributes(recipient.map(|recipient| ("recipient", recipient))))
+}
+
+fn execute_sunset(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    if let Some(sunset_at) = SUNSET.may_load(deps.storage)? {
+        return Err(ContractError::Sunset { sunset_at });
+    }
+    // the funds must have somewhere to go once everything else is blocked
+    let recipient = SUNSET_RECIPIENT
+        .may_load(deps.storage)?
+        .ok_or(ContractError::SunsetRecipientNotSet {})?;
+
+    SUNSET.save(deps.storage, &env.block.time)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "sunset")
+        .add_attribute("recipient", recipient.as_str()))
+}
+
+fn execute_emergency_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    assets: Vec<AssetInfo>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    if SUNSET.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NotSunset {});
+    }
+    let recipient = SUNSET_RECIPIENT.load(deps.storage)?;
+
+    // what Pull mode targets and vault depositors can still withdraw stays behind
+    let mut messages = vec![];
+    for asset in assets {
+        let amount = asset
+            .query_pool(&deps.querier, env.contract.address.clone())?
+            .saturating_sub(_owed_amount(deps.as_ref(), &asset)?);
+        if amount.is_zero() {
+            continue;
+        }
+        messages.push(_build_transfer_message(&asset, amount, &recipient)?);
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "emergency_withdraw")
+        .add_attribute("recipient", recipient.as_str()))
+}
+
+fn execute_update_weights(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    updates: Vec<(Addr, u32)>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    let mut distribute_targets = DISTRIBUTION_TARGETS.load(deps.storage)?;
+    let mut removed = vec![];
+    for target in distribute_targets.iter() {
+        if PENDING_REMOVALS
+            .may_load(deps.storage, &target.addr)?
+            .is_some_and(|effective_at| effective_at <= env.block.time)
+        {
+            removed.push(target.addr.clone());
+        }
+    }
+    for (addr, weight) in updates.iter() {
+        let target = distribute_targets
+            .iter_mut()
+            .filter(|target| !removed.contains(&target.addr))
+            .find(|target| target.addr == *addr)
+            .ok_or_else(|| ContractError::TargetNotFound {
+                addr: addr.to_string(),
+            })?;
+        target.weight = *weight;
+    }
+
+    // targets whose removal took effect no longer hold any of the total weight, they
+    // keep their place in the list
+    let active_targets = distribute_targets
+        .iter()
+        .filter(|target| !removed.contains(&target.addr))
+        .cloned()
+        .collect::<Vec<DistributeTarget>>();
+    validate_distribute_targets(deps.as_ref(), &active_targets)?;
+
+    DISTRIBUTION_TARGETS.save(deps.storage, &distribute_targets)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_weights")
+        .add_attribute("updated", updates.len().to_string()))
+}
+
+fn execute_update_target_kind_restriction(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    restriction: TargetKindRestriction,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    // the current targets, per-token lists included, must already satisfy the new restriction
+    let mut distribute_targets = DISTRIBUTION_TARGETS.load(deps.storage)?;
+    for item in TOKEN_DISTRIBUTION_TARGETS.range(deps.storage, None, None, Order::Ascending) {
+        distribute_targets.extend(item?.1);
+    }
+    for target in distribute_targets.iter() {
+        _validate_target_kind(deps.as_ref(), &restriction, &target.addr)?;
+    }
+
+    TARGET_KIND_RESTRICTION.save(deps.storage, &restriction)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_target_kind_restriction")
+        .add_attribute("restriction", format!("{:?}", restriction)))
+}
+
+fn validate_distribute_targets(
+    deps: Deps,
+    distribute_targets: &[DistributeTarget],
+) -> Result<Vec<DistributeTarget>, ContractError> {
+    let restriction = TARGET_KIND_RESTRICTION
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    // weights are percentages of the distributed amount
+    let total_weight = distribute_targets
+        .iter()
+        .map(|target| target.weight as u64)
+        .sum::<u64>();
+    if total_weight > MAX_TOTAL_WEIGHT {
+        return Err(ContractError::InvalidTotalWeight {
+            total: total_weight,
+            max: MAX_TOTAL_WEIGHT,
+        });
+    }
+
+    if let Some(max_bps) = MAX_TARGET_SHARE_BPS.may_load(deps.storage)? {
+        _assert_target_shares(distribute_targets, max_bps)?;
+    }
+
+    distribute_targets
+        .iter()
+        .map(|target| {
+            // bound the per-target state growth and the gas spent forwarding the hook
+            if let Some(msg_hook) = &target.msg_hook {
+                if msg_hook.len() > MAX_HOOK_SIZE {
+                    return Err(ContractError::HookTooLarge {
+                        size: msg_hook.len(),
+                        max: MAX_HOOK_SIZE,
+                    });
+                }
+            }
+
+            if target.usd_target.is_some() && target.weight != 0 {
+                return Err(ContractError::UsdTargetWithWeight {
+                    addr: target.addr.to_string(),
+                });
+            }
+
+            // the swap output goes straight to the target, there is no hook to call
+            if let Some(preferred_asset) = &target.preferred_asset {
+                if target.msg_hook.is_some() {
+                    return Err(ContractError::PreferredAssetWithHook {
+                        addr: target.addr.to_string(),
+                    });
+                }
+                if let AssetInfo::Token { contract_addr } = preferred_asset {
+                    deps.api.addr_validate(contract_addr.as_str())?;
+                }
+            }
+
+            if let Some(ibc) = &target.ibc {
+                if target.msg_hook.is_some() || target.preferred_asset.is_some() {
+                    return Err(ContractError::IbcTargetConflict {
+                        addr: target.addr.to_string(),
+                    });
+                }
+                _validate_ibc_target(ibc)?;
+            }
+
+            let addr = deps.api.addr_validate(target.addr.as_str())?;
+            _validate_target_kind(deps, &restriction, &addr)?;
+
+            Ok(DistributeTarget {
+                weight: target.weight,
+                addr,
+                msg_hook: target.msg_hook.clone(),
+                preferred_asset: target.preferred_asset.clone(),
+                usd_target: target.usd_target,
+                hook_gas_limit: target.hook_gas_limit,
+                ibc: target.ibc.clone(),
+            })
+        })
+        .collect::<Result<Vec<DistributeTarget>, ContractError>>()
+}
+
+fn _validate_ibc_target(ibc: &IbcTarget) -> Result<(), ContractError> {
+    let invalid = |reason: &str| ContractError::InvalidIbcTarget {
+        reason: reason.to_string(),
+    };
+    if !_is_channel_id(&ibc.channel_id) {
+        return Err(invalid("channel_id must look like channel-<n>"));
+    }
+    if ibc.receiver.is_empty() {
+        return Err(invalid("receiver is empty"));
+    }
+    if ibc.timeout_seconds == 0 {
+        return Err(invalid("timeout_seconds must be positive"));
+    }
+
+    // walk the forward route, every hop ends up in the memo the middleware parses
+    let mut hop = ibc.forward.as_ref();
+    let mut hops = 0;
+    while let Some(forward) = hop {
+        hops += 1;
+        if hops > MAX_FORWARD_HOPS {
+            return Err(invalid("forward route is too long"));
+        }
+        if forward.receiver.is_empty() {
+            return Err(invalid("forward receiver is empty"));
+        }
+        if forward.port.is_empty() {
+            return Err(invalid("forward port is empty"));
+        }
+        if !_is_channel_id(&forward.channel) {
+            return Err(invalid("forward channel must look like channel-<n>"));
+        }
+        if let Some(timeout) = &forward.timeout {
+            if !_is_go_duration(timeout) {
+                return Err(invalid("forward timeout must be a duration such as 10m"));
+            }
+        }
+        hop = forward.next.as_ref().map(|next| &next.forward);
+    }
+    Ok(())
+}
+
+fn _is_channel_id(channel_id: &str) -> bool {
+    channel_id
+        .strip_prefix("channel-")
+        .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Accepts the `<number><unit>` sequences go's `time.ParseDuration` reads, e.g. `1h30m`
+fn _is_go_duration(duration: &str) -> bool {
+    let mut rest = duration;
+    if rest.is_empty() {
+        return false;
+    }
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return false;
+        }
+        rest = &rest[digits..];
+        match ["ns", "us", "ms", "s", "m", "h"]
+            .iter()
+            .find(|unit| rest.starts_with(*unit))
+        {
+            Some(unit) => rest = &rest[unit.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Rejects a target list where one target holds more than `max_bps` of the total weight
+fn _assert_target_shares(
+    distribute_targets: &[DistributeTarget],
+    max_bps: u32,
+) -> Result<(), ContractError> {
+    let total_weight = distribute_targets
+        .iter()
+        .map(|target| target.weight as u64)
+        .sum::<u64>();
+    for target in distribute_targets {
+        let share_bps = (target.weight as u64 * 10_000)
+            .checked_div(total_weight)
+            .unwrap_or_default();
+        if share_bps > max_bps as u64 {
+            return Err(ContractError::TargetShareTooHigh {
+                addr: target.addr.to_string(),
+                share_bps,
+                max_bps,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn _validate_target_kind(
+    deps: Deps,
+    restriction: &TargetKindRestriction,
+    addr: &Addr,
+) -> Result<(), ContractError> {
+    if *restriction == TargetKindRestriction::Any {
+        return Ok(());
+    }
+
+    // only contracts have a contract info
+    let is_contract = deps
+        .querier
+        .query_wasm_contract_info(addr.to_string())
+        .is_ok();
+
+    match restriction {
+        TargetKindRestriction::ContractsOnly if !is_contract => {
+            Err(ContractError::TargetKindMismatch {
+                addr: addr.to_string(),
+                expected: "contract".to_string(),
+            })
+        }
+        TargetKindRestriction::WalletsOnly if is_contract => {
+            Err(ContractError::TargetKindMismatch {
+                addr: addr.to_string(),
+                expected: "wallet".to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn execute_distribute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount_distribute: Uint128,
+    token: Option<AssetInfo>,
+    nonce: Option<u64>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    let config = CONFIG.load(deps.storage)?;
+    assert_not_paused(deps.storage, Operation::Distribute)?;
+    _begin_distribution(deps.storage, &env, nonce)?;
+
+    let token = token.unwrap_or(AssetInfo::Token {
+        contract_addr: config.distribute_token,
+    });
+    _assert_oracle_fresh(deps.as_ref(), &env, asset_info_to_string(&token))?;
+    _assert_hooks_supported(&token, &_active_targets(deps.as_ref(), &env, Some(&token))?)?;
+    let plan = _compute_distribution_plan(deps.as_ref(), &env, Some(&token), amount_distribute)?;
+
+    let response = match &token {
+        AssetInfo::NativeToken { denom } => {
+            _assert_native_distribute_balance(deps.as_ref(), &env, denom, amount_distribute)?;
+            let events = _distribution_events(deps.storage, &plan.payouts)?;
+            _record_distribution(deps.storage, &env, &token, amount_distribute)?;
+
+            if _is_pull_mode(deps.storage)? {
+                let credited = _credit_claimable(deps.storage, &env, &token, &plan.payouts)?;
+                Response::new()
+                    .add_events(events)
+                    .add_attribute("credited", credited.to_string())
+            } else {
+                let messages = _build_native_target_messages(
+                    &deps.querier,
+                    &env,
+                    config.router.as_ref(),
+                    &plan.payouts,
+                    denom,
+                )?;
+                _record_received(deps.storage, &token, &plan.payouts)?;
+                Response::new()
+                    .add_messages(messages)
+                    .add_events(events)
+                    .add_attribute("deferred_hooks", "0")
+            }
+        }
+        AssetInfo::Token { contract_addr } => {
+            let contract_addr = deps.api.addr_validate(contract_addr.as_str())?;
+            _assert_distribute_balance(deps.as_ref(), &env, &contract_addr, amount_distribute)?;
+
+            _settle_distribution(deps, &env, contract_addr, amount_distribute, plan.payouts)?
+        }
+    };
+
+    Ok(response
+        .add_attribute("action", "distribute")
+        .add_attribute("token", asset_info_to_string(&token))
+        .add_attribute("amount_distribute", amount_distribute.to_string()))
+}
+
+/// Nonce, throttle and epoch checks of a scheduled distribution, which then counts
+/// as the last one
+fn _begin_distribution(
+    storage: &mut dyn Storage,
+    env: &Env,
+    nonce: Option<u64>,
+) -> Result<(), ContractError> {
+    let expected = DISTRIBUTION_NONCE.may_load(storage)?.unwrap_or_default();
+    if nonce.is_some_and(|nonce| nonce != expected) {
+        return Err(ContractError::NonceMismatch { expected });
+    }
+    DISTRIBUTION_NONCE.save(storage, &(expected + 1))?;
+
+    let available_at = _next_distribution_at(storage, env)?;
+    if env.block.time < available_at {
+        return Err(ContractError::DistributionThrottled { available_at });
+    }
+    if let Some(epoch) = EPOCH.may_load(storage)?.filter(|epoch| epoch.one_per_epoch) {
+        let current = _current_epoch(&epoch, env.block.time).epoch;
+        if let Some(last_distribution) = LAST_DISTRIBUTION.may_load(storage)? {
+            if _current_epoch(&epoch, last_distribution).epoch == current {
+                return Err(ContractError::EpochAlreadyDistributed { epoch: current });
+            }
+        }
+    }
+    LAST_DISTRIBUTION.save(storage, &env.block.time)?;
+    Ok(())
+}
+
+fn _is_pull_mode(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(DISTRIBUTION_MODE.may_load(storage)?.unwrap_or_default() == DistributionMode::Pull)
+}
+
+/// Pays a cw20 distribution the way `Distribute` does: the distribution is recorded
+/// with its events, then the shares are credited in Pull mode or sent within the hook
+/// cap otherwise. The caller checks the balance and adds its own action attributes
+fn _settle_distribution(
+    deps: DepsMut,
+    env: &Env,
+    distribute_token: Addr,
+    amount_distribute: Uint128,
+    payouts: Vec<(Uint128, DistributeTarget)>,
+) -> Result<Response, ContractError> {
+    let token = AssetInfo::Token {
+        contract_addr: distribute_token.clone(),
+    };
+    let events = _distribution_events(deps.storage, &payouts)?;
+    _record_distribution(deps.storage, env, &token, amount_distribute)?;
+
+    Ok(
+        _pay_or_credit(deps.storage, &deps.querier, env, payouts, distribute_token)?
+            .add_events(events),
+    )
+}
+
+/// Pull mode distribution, the shares stay in the contract until their target claims
+/// them. Hooks and preferred assets only apply to pushed shares. Returns the amount
+/// credited
+fn _credit_claimable(
+    storage: &mut dyn Storage,
+    env: &Env,
+    token: &AssetInfo,
+    payouts: &[(Uint128, DistributeTarget)],
+) -> StdResult<Uint128> {
+    let key = asset_info_to_string(token);
+    let mut credited = Uint128::zero();
+    for (amount, target) in payouts.iter().filter(|(amount, _)| !amount.is_zero()) {
+        CLAIMABLE.update(
+            storage,
+            (&target.addr, key.as_str()),
+            |claimable| -> StdResult<Uint128> { Ok(claimable.unwrap_or_default() + amount) },
+        )?;
+        LAST_CREDITED.save(storage, &target.addr, &env.block.time)?;
+        credited += amount;
+    }
+    TOTAL_CLAIMABLE.update(storage, key.as_str(), |total| -> StdResult<Uint128> {
+        Ok(total.unwrap_or_default() + credited)
+    })?;
+    _record_received(storage, token, payouts)?;
+    Ok(credited)
+}
+
+/// Adds the non-zero payouts to what their targets received of `token`. A share
+/// swapped into a preferred asset counts at its distributed amount and a failed hook
+/// is counted once, when it was first sent
+fn _record_received(
+    storage: &mut dyn Storage,
+    token: &AssetInfo,
+    payouts: &[(Uint128, DistributeTarget)],
+) -> StdResult<()> {
+    let key = asset_info_to_string(token);
+    for (amount, target) in payouts.iter().filter(|(amount, _)| !amount.is_zero()) {
+        TARGET_RECEIVED.update(
+            storage,
+            (&target.addr, key.as_str()),
+            |received| -> StdResult<Uint128> { Ok(received.unwrap_or_default() + amount) },
+        )?;
+    }
+    Ok(())
+}
+
+fn execute_distribute_normalized(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    nonce: Option<u64>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Distribute)?;
+    _begin_distribution(deps.storage, &env, nonce)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let distribute_asset_info = AssetInfo::Token {
+        contract_addr: config.distribute_token.clone(),
+    };
+    let plan = _compute_distribution_plan(deps.as_ref(), &env, None, amount)?;
+    let events = _distribution_events(deps.storage, &plan.payouts)?;
+
+    let mut rates: Vec<(AssetInfo, Decimal)> = vec![];
+    let mut direct_payouts = vec![];
+    let mut preferred_shares = vec![];
+    let mut preferred_payouts: Vec<(AssetInfo, Uint128, DistributeTarget)> = vec![];
+    for (share, target) in plan.payouts {
+        match &target.preferred_asset {
+            Some(preferred_asset) if preferred_asset != &distribute_asset_info => {
+                // paid from the preferred asset held, worth the share at the router TWAP
+                let rate = match rates.iter().find(|(asset, _)| asset == preferred_asset) {
+                    Some((_, rate)) => *rate,
+                    None => {
+                        let rate = _normalization_rate(
+                            deps.as_ref(),
+                            &config,
+                            &distribute_asset_info,
+                            preferred_asset,
+                        )?;
+                        rates.push((preferred_asset.clone(), rate));
+                        rate
+                    }
+                };
+                let paid = share.checked_mul_floor(rate).map_err(|_| {
+                    ContractError::NormalizationUnpriced {
+                        denom: asset_info_to_string(preferred_asset),
+                    }
+                })?;
+                preferred_payouts.push((preferred_asset.clone(), paid, target.clone()));
+                preferred_shares.push((share, target));
+            }
+            _ => direct_payouts.push((share, target)),
+        }
+    }
+
+    let direct_amount = direct_payouts
+        .iter()
+        .map(|(share, _)| *share)
+        .sum::<Uint128>();
+    _assert_distribute_balance(deps.as_ref(), &env, &config.distribute_token, direct_amount)?;
+    let mut paid_assets: Vec<(AssetInfo, Uint128)> = vec![];
+    for (asset, paid, _) in preferred_payouts.iter() {
+        match paid_assets
+            .iter_mut()
+            .find(|(paid_asset, _)| paid_asset == asset)
+        {
+            Some((_, total)) => *total += *paid,
+            None => paid_assets.push((asset.clone(), *paid)),
+        }
+    }
+    for (asset, total) in paid_assets.iter() {
+        _assert_asset_balance(deps.as_ref(), &env, asset, *total)?;
+    }
+
+    _record_distribution(deps.storage, &env, &distribute_asset_info, amount)?;
+
+    // in Pull mode the preferred assets are credited, and received, as they would
+    // have been paid
+    let response = if _is_pull_mode(deps.storage)? {
+        let credited =
+            _credit_claimable(deps.storage, &env, &distribute_asset_info, &direct_payouts)?;
+        for (asset, _) in paid_assets.iter() {
+            let payouts = preferred_payouts
+                .iter()
+                .filter(|(paid_asset, _, _)| paid_asset == asset)
+                .map(|(_, paid, target)| (*paid, target.clone()))
+                .collect::<Vec<(Uint128, DistributeTarget)>>();
+            _credit_claimable(deps.storage, &env, asset, &payouts)?;
+        }
+        Response::new().add_attribute("credited", credited.to_string())
+    } else {
+        // a pushed preferred share counts at its distributed amount, like `Distribute`
+        _record_received(deps.storage, &distribute_asset_info, &preferred_shares)?;
+        // the direct shares carry the hooks, they are capped like a `Distribute`
+        let (direct_messages, deferred_hooks) = _push_capped_payouts(
+            deps.storage,
+            &deps.querier,
+            direct_payouts,
+            config.distribute_token,
+        )?;
+        let messages = preferred_payouts
+            .iter()
+            .filter(|(_, paid, _)| !paid.is_zero())
+            .map(|(asset, paid, target)| _build_transfer_message(asset, *paid, &target.addr))
+            .collect::<StdResult<Vec<CosmosMsg>>>()?;
+        Response::new()
+            .add_submessages(direct_messages)
+            .add_messages(messages)
+            .add_attribute("deferred_hooks", deferred_hooks.to_string())
+    };
+
+    Ok(response
+        .add_events(events)
+        .add_attribute("action", "distribute_normalized")
+        .add_attribute("amount_distribute", amount.to_string()))
+}
+
+/// Router TWAP of `preferred_asset` per distributed token for `DistributeNormalized`,
+/// averaged so a single block can not skew the value paid
+fn _normalization_rate(
+    deps: Deps,
+    config: &Config,
+    distribute_asset_info: &AssetInfo,
+    preferred_asset: &AssetInfo,
+) -> Result<Decimal, ContractError> {
+    let router = config
+        .router
+        .as_ref()
+        .ok_or(ContractError::RouterAndApproverNotSet {})?;
+    let twap: TwapResponse = deps.querier.query_wasm_smart(
+        router,
+        &RouterTwapQueryMsg::Twap {
+            offer_asset_info: distribute_asset_info.clone(),
+            ask_asset_info: preferred_asset.clone(),
+            window_seconds: NORMALIZATION_TWAP_WINDOW_SECONDS,
+        },
+    )?;
+    if twap.price.is_zero() {
+        return Err(ContractError::NormalizationUnpriced {
+            denom: asset_info_to_string(preferred_asset),
+        });
+    }
+    Ok(twap.price)
+}
+
+fn execute_distribute_diversified(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    allocation: Vec<AllocationEntry>,
+    nonce: Option<u64>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Distribute)?;
+
+    let duplicated = allocation.iter().enumerate().any(|(index, entry)| {
+        allocation[..index]
+            .iter()
+            .any(|other| other.asset == entry.asset)
+    });
+    let total_ratio = allocation.iter().map(|entry| entry.ratio).sum::<Decimal>();
+    if duplicated
+        || total_ratio != Decimal::one()
+        || allocation.iter().any(|entry| {
+            entry.ratio.is_zero()
+                || entry
+                    .swap_operations
+                    .as_ref()
+                    .is_some_and(|operations| operations.is_empty())
+        })
+    {
+        return Err(ContractError::InvalidAllocation {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let router = config
+        .router
+        .clone()
+        .ok_or(ContractError::RouterAndApproverNotSet {})?;
+    _assert_distribute_balance(deps.as_ref(), &env, &config.distribute_token, amount)?;
+    let distribute_asset_info = AssetInfo::Token {
+        contract_addr: config.distribute_token.clone(),
+    };
+
+    if PENDING_DISTRIBUTION.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::DistributionInProgress {});
+    }
+    _begin_distribution(deps.storage, &env, nonce)?;
+    // the hook cap holds across the whole basket
+    let mut hooks_left = MAX_HOOKS_PER_DISTRIBUTION.may_load(deps.storage)?;
+    let pull_mode = _is_pull_mode(deps.storage)?;
+    let mut credited = vec![];
+    let mut events = vec![];
+    let mut deferred = vec![];
+
+    let mut swap_messages = vec![];
+    let mut payout_messages = vec![];
+    for entry in allocation {
+        let part = amount * entry.ratio;
+        let received = if entry.asset == distribute_asset_info {
+            part
+        } else {
+            let operations = entry.swap_operations.clone().unwrap_or_else(|| {
+                vec![SwapOperation::OraiSwap {
+                    offer_asset_info: distribute_asset_info.clone(),
+                    ask_asset_info: entry.asset.clone(),
+                }]
+            });
+            let quote: SimulateSwapOperationsResponse = deps.querier.query_wasm_smart(
+                &router,
+                &RouterQueryMsg::SimulateSwapOperations {
+                    offer_amount: part,
+                    operations: operations.clone(),
+                },
+            )?;
+            // the swap fails below the quote, so the quoted amount is there to split
+            swap_messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: config.distribute_token.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                    contract: router.to_string(),
+                    amount: part,
+                    msg: to_json_binary(&Cw20RouterHookMsg::ExecuteSwapOperations {
+                        operations,
+                        minimum_receive: Some(quote.amount),
+                        to: Some(env.contract.address.to_string()),
+                        affiliates: None,
+                    })?,
+                })?,
+                funds: vec![],
+            }));
+            quote.amount
+        };
+
+        let plan = _compute_distribution_plan(deps.as_ref(), &env, Some(&entry.asset), received)?;
+        events.extend(
+            _distribution_events(deps.storage, &plan.payouts)?
+                .into_iter()
+                .map(|event| event.add_attribute("token", asset_info_to_string(&entry.asset))),
+        );
+        // the swaps settle in this transaction at no less than the quote, so the
+        // quoted amount can be credited before it arrives
+        if pull_mode {
+            let amount = _credit_claimable(deps.storage, &env, &entry.asset, &plan.payouts)?;
+            credited.push((
+                "credited",
+                format!("{}{}", amount, asset_info_to_string(&entry.asset)),
+            ));
+            continue;
+        }
+        match &entry.asset {
+            // hooks past the cap wait for `ContinueDistribution`, one asset after another
+            AssetInfo::Token { contract_addr } => {
+                let (payouts, deferred_payouts) = _cap_hooks(plan.payouts, &mut hooks_left);
+                payout_messages.extend(_build_target_submessages(
+                    deps.storage,
+                    &deps.querier,
+                    &payouts,
+                    contract_addr.clone(),
+                )?);
+                _record_received(deps.storage, &entry.asset, &payouts)?;
+                if !deferred_payouts.is_empty() {
+                    deferred.push(PendingDistribution {
+                        distribute_token: contract_addr.clone(),
+                        payouts: deferred_payouts,
+                        queued: vec![],
+                    });
+                }
+            }
+            AssetInfo::NativeToken { denom } => {
+                payout_messages.extend(
+                    _build_native_target_messages(
+                        &deps.querier,
+                        &env,
+                        Some(&router),
+                        &plan.payouts,
+                        denom,
+                    )?
+                    .into_iter()
+                    .map(SubMsg::new),
+                );
+                _record_received(deps.storage, &entry.asset, &plan.payouts)?;
+            }
+        }
+    }
+
+    let deferred_hooks = deferred
+        .iter()
+        .map(|pending| pending.payouts.len())
+        .sum::<usize>();
+    _queue_pending_distributions(deps.storage, deferred)?;
+    _record_distribution(deps.storage, &env, &distribute_asset_info, amount)?;
+
+    Ok(Response::new()
+        .add_messages(swap_messages)
+        .add_submessages(payout_messages)
+        .add_attributes(credited)
+        .add_events(events)
+        .add_attribute("action", "distribute_diversified")
+        .add_attribute("amount_distribute", amount.to_string())
+        .add_attribute("deferred_hooks", deferred_hooks.to_string()))
+}
+
+fn execute_claim(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    // claimable balances belong to their targets, a sunset does not lock them in
+    if SUNSET.may_load(deps.storage)?.is_none() {
+        assert_not_paused(deps.storage, Operation::Distribute)?;
+    }
+
+    let claimable = CLAIMABLE
+        .prefix(&info.sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, Uint128)>>>()?;
+    if claimable.is_empty() {
+        return Err(ContractError::NothingToClaim {
+            addr: info.sender.to_string(),
+        });
+    }
+
+    LAST_CREDITED.remove(deps.storage, &info.sender);
+    let mut messages = vec![];
+    for (key, amount) in claimable {
+        CLAIMABLE.remove(deps.storage, (&info.sender, key.as_str()));
+        TOTAL_CLAIMABLE.update(deps.storage, key.as_str(), |total| -> StdResult<Uint128> {
+            Ok(total.unwrap_or_default().checked_sub(amount)?)
+        })?;
+        messages.push(_build_transfer_message(
+            &asset_info_from_string(deps.api, key),
+            amount,
+            &info.sender,
+        )?);
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "claim")
+        .add_attribute("target", info.sender.as_str()))
+}
+
+fn execute_update_claim_expiry(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    claim_expiry_seconds: Option<u64>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    save_or_remove(
+        deps.storage,
+        &CLAIM_EXPIRY_SECONDS,
+        claim_expiry_seconds.as_ref(),
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_claim_expiry")
+        .add_attributes(
+            claim_expiry_seconds.map(|expiry| ("claim_expiry_seconds", expiry.to_string())),
+        ))
+}
+
+/// Drops the shares credited to `addr`, their reservation and their count as received,
+/// the tokens stay in the contract and are distributed again. A later credit restarts
+/// the expiry of all of the target's shares
+fn execute_reclaim_unclaimed(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    addr: Addr,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+    let claim_expiry_seconds = CLAIM_EXPIRY_SECONDS
+        .may_load(deps.storage)?
+        .ok_or(ContractError::ClaimExpiryNotSet {})?;
+
+    let claimable = CLAIMABLE
+        .prefix(&addr)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, Uint128)>>>()?;
+    if claimable.is_empty() {
+        return Err(ContractError::NothingToClaim {
+            addr: addr.to_string(),
+        });
+    }
+    let expires_at = LAST_CREDITED
+        .load(deps.storage, &addr)?
+        .plus_seconds(claim_expiry_seconds);
+    if env.block.time < expires_at {
+        return Err(ContractError::ClaimNotExpired {
+            addr: addr.to_string(),
+            expires_at,
+        });
+    }
+
+    LAST_CREDITED.remove(deps.storage, &addr);
+    let mut reclaimed = vec![];
+    for (key, amount) in claimable {
+        CLAIMABLE.remove(deps.storage, (&addr, key.as_str()));
+        TOTAL_CLAIMABLE.update(deps.storage, key.as_str(), |total| -> StdResult<Uint128> {
+            Ok(total.unwrap_or_default().checked_sub(amount)?)
+        })?;
+        // the target never got the share, shares imported from a snapshot were not
+        // counted as received
+        let received = TARGET_RECEIVED
+            .may_load(deps.storage, (&addr, key.as_str()))?
+            .unwrap_or_default()
+            .saturating_sub(amount);
+        if received.is_zero() {
+            TARGET_RECEIVED.remove(deps.storage, (&addr, key.as_str()));
+        } else {
+            TARGET_RECEIVED.save(deps.storage, (&addr, key.as_str()), &received)?;
+        }
+        reclaimed.push(Asset {
+            info: asset_info_from_string(deps.api, key),
+            amount,
+        });
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "reclaim_unclaimed")
+        .add_attribute("target", addr.as_str())
+        .add_attribute("reclaimed", to_json_string(&reclaimed)?))
+}
+
+fn execute_update_distribution_mode(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    mode: DistributionMode,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    DISTRIBUTION_MODE.save(deps.storage, &mode)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_distribution_mode")
+        .add_attribute(
+            "mode",
+            match mode {
+                DistributionMode::Push => "push",
+                DistributionMode::Pull => "pull",
+            },
+        ))
+}
+
+fn execute_update_max_hooks_per_distribution(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    max_hooks: Option<u32>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+    if max_hooks == Some(0) {
+        return Err(ContractError::InvalidMaxHooks {});
+    }
+
+    save_or_remove(
+        deps.storage,
+        &MAX_HOOKS_PER_DISTRIBUTION,
+        max_hooks.as_ref(),
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_max_hooks_per_distribution")
+        .add_attribute(
+            "max_hooks",
+            max_hooks.map_or("none".to_string(), |max_hooks| max_hooks.to_string()),
+        ))
+}
+
+fn execute_continue_distribution(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Distribute)?;
+
+    let pending = PENDING_DISTRIBUTION
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingDistribution {})?;
+    PENDING_DISTRIBUTION.remove(deps.storage);
+
+    // the mode may have changed since the distribution was split
+    let response = _pay_or_credit(
+        deps.storage,
+        &deps.querier,
+        &env,
+        pending.payouts,
+        pending.distribute_token,
+    )?;
+    // the other assets of a diversified distribution follow what is left of this one
+    _queue_pending_distributions(deps.storage, pending.queued)?;
+
+    Ok(response.add_attribute("action", "continue_distribution"))
+}
+
+/// Split payouts into the ones to fire now and the hook-bearing ones beyond
+/// the configured cap, which are left for a `ContinueDistribution` call
+fn _split_capped_hooks(
+    storage: &dyn Storage,
+    payouts: Vec<(Uint128, DistributeTarget)>,
+) -> Result<
+    (
+        Vec<(Uint128, DistributeTarget)>,
+        Vec<(Uint128, DistributeTarget)>,
+    ),
+    ContractError,
+> {
+    if PENDING_DISTRIBUTION.may_load(storage)?.is_some() {
+        return Err(ContractError::DistributionInProgress {});
+    }
+    let mut hooks_left = MAX_HOOKS_PER_DISTRIBUTION.may_load(storage)?;
+
+    Ok(_cap_hooks(payouts, &mut hooks_left))
+}
+
+/// Fires the hook-bearing payouts while `hooks_left` allows, all of them when it
+/// is unset. Shared by the assets of a diversified distribution
+fn _cap_hooks(
+    payouts: Vec<(Uint128, DistributeTarget)>,
+    hooks_left: &mut Option<u32>,
+) -> (
+    Vec<(Uint128, DistributeTarget)>,
+    Vec<(Uint128, DistributeTarget)>,
+) {
+    let (mut fired, mut deferred) = (vec![], vec![]);
+    for payout in payouts {
+        match hooks_left {
+            Some(0) if payout.1.msg_hook.is_some() => deferred.push(payout),
+            Some(left) if payout.1.msg_hook.is_some() => {
+                *left -= 1;
+                fired.push(payout);
+            }
+            _ => fired.push(payout),
+        }
+    }
+    (fired, deferred)
+}
+
+/// Appends `queued` to the pending distribution, the first of them becomes the
+/// pending one when nothing else is left
+fn _queue_pending_distributions(
+    storage: &mut dyn Storage,
+    mut queued: Vec<PendingDistribution>,
+) -> StdResult<()> {
+    if queued.is_empty() {
+        return Ok(());
+    }
+    let pending = match PENDING_DISTRIBUTION.may_load(storage)? {
+        Some(mut pending) => {
+            pending.queued.append(&mut queued);
+            pending
+        }
+        None => {
+            let mut pending = queued.remove(0);
+            pending.queued = queued;
+            pending
+        }
+    };
+    PENDING_DISTRIBUTION.save(storage, &pending)
+}
+
+/// Submessages of the payouts within the hook cap, the deferred ones are saved
+/// as the pending distribution. Returns the number of deferred hooks
+fn _push_capped_payouts(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    payouts: Vec<(Uint128, DistributeTarget)>,
+    distribute_token: Addr,
+) -> Result<(Vec<SubMsg>, usize), ContractError> {
+    let (payouts, deferred) = _split_capped_hooks(storage, payouts)?;
+    let messages = _build_target_submessages(storage, querier, &payouts, distribute_token.clone())?;
+    _record_received(
+        storage,
+        &AssetInfo::Token {
+            contract_addr: distribute_token.clone(),
+        },
+        &payouts,
+    )?;
+
+    let deferred_hooks = deferred.len();
+    if !deferred.is_empty() {
+        PENDING_DISTRIBUTION.save(
+            storage,
+            &PendingDistribution {
+                distribute_token,
+                payouts: deferred,
+                queued: vec![],
+            },
+        )?;
+    }
+    Ok((messages, deferred_hooks))
+}
+
+/// Credits the payouts in Pull mode, otherwise sends them within the hook cap
+fn _pay_or_credit(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    payouts: Vec<(Uint128, DistributeTarget)>,
+    distribute_token: Addr,
+) -> Result<Response, ContractError> {
+    if _is_pull_mode(storage)? {
+        let token = AssetInfo::Token {
+            contract_addr: distribute_token,
+        };
+        let credited = _credit_claimable(storage, env, &token, &payouts)?;
+        return Ok(Response::new().add_attribute("credited", credited.to_string()));
+    }
+
+    let (messages, deferred_hooks) =
+        _push_capped_payouts(storage, querier, payouts, distribute_token)?;
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("deferred_hooks", deferred_hooks.to_string()))
+}
+
+fn execute_distribute_from_allowance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token: Addr,
+    owner: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Distribute)?;
+
+    let token = deps.api.addr_validate(token.as_str())?;
+    let owner = deps.api.addr_validate(owner.as_str())?;
+    _assert_oracle_fresh(deps.as_ref(), &env, token.to_string())?;
+
+    let allowance: AllowanceResponse = deps.querier.query_wasm_smart(
+        token.clone(),
+        &cw20_base::msg::QueryMsg::Allowance {
+            owner: owner.to_string(),
+            spender: env.contract.address.to_string(),
+        },
+    )?;
+    if allowance.allowance < amount {
+        return Err(ContractError::InsufficientAllowance {
+            allowance: allowance.allowance,
+            amount,
+        });
+    }
+
+    // pull the funds first so the split below spends tokens the contract holds
+    let pull_message = WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+            owner: owner.to_string(),
+            recipient: env.contract.address.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    };
+    let plan = _compute_distribution_plan(
+        deps.as_ref(),
+        &env,
+        Some(&AssetInfo::Token {
+            contract_addr: token.clone(),
+        }),
+        amount,
+    )?;
+    let settled = _settle_distribution(deps, &env, token.clone(), amount, plan.payouts)?;
+
+    Ok(Response::new()
+        .add_message(pull_message)
+        .add_submessages(settled.messages)
+        .add_events(settled.events)
+        .add_attributes(settled.attributes)
+        .add_attribute("action", "distribute_from_allowance")
+        .add_attribute("token", token.as_str())
+        .add_attribute("owner", owner.as_str())
+        .add_attribute("amount_distribute", amount.to_string()))
+}
+
+fn execute_distribute_by_voting_power(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token: Addr,
+    height: u64,
+    holders: Vec<Addr>,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    let config = CONFIG.load(deps.storage)?;
+    assert_not_paused(deps.storage, Operation::Distribute)?;
+    // balances are final only for past blocks
+    if height >= env.block.height {
+        return Err(ContractError::InvalidSnapshotHeight { height });
+    }
+
+    _assert_distribute_balance(deps.as_ref(), &env, &config.distribute_token, amount)?;
+
+    // a holder listed twice would be weighted and paid twice
+    if let Some(holder) = holders
+        .iter()
+        .enumerate()
+        .find_map(|(index, holder)| holders[..index].contains(holder).then_some(holder))
+    {
+        return Err(ContractError::DuplicateHolder {
+            addr: holder.to_string(),
+        });
+    }
+
+    let token = deps.api.addr_validate(token.as_str())?;
+    let voting_powers = holders
+        .iter()
+        .map(|holder| {
+            let holder = deps.api.addr_validate(holder.as_str())?;
+            let balance: BalanceResponse = deps.querier.query_wasm_smart(
+                token.clone(),
+                &SnapshotQueryMsg::BalanceAt {
+                    address: holder.to_string(),
+                    height,
+                },
+            )?;
+            Ok((holder, balance.balance))
+        })
+        .collect::<StdResult<Vec<(Addr, Uint128)>>>()?;
+
+    let total_voting_power = voting_powers
+        .iter()
+        .map(|(_, voting_power)| *voting_power)
+        .sum::<Uint128>();
+    if total_voting_power.is_zero() {
+        return Err(ContractError::NoVotingPower { height });
+    }
+
+    let payouts = voting_powers
+        .into_iter()
+        .map(|(holder, voting_power)| {
+            (
+                amount.multiply_ratio(voting_power, total_voting_power),
+                DistributeTarget {
+                    addr: holder,
+                    weight: 0,
+                    msg_hook: None,
+                    preferred_asset: None,
+                    usd_target: None,
+                    hook_gas_limit: None,
+                    ibc: None,
+                },
+            )
+        })
+        .filter(|(share, _)| !share.is_zero())
+        .collect::<Vec<(Uint128, DistributeTarget)>>();
+
+    let response = _settle_distribution(deps, &env, config.distribute_token, amount, payouts)?;
+    Ok(response
+        .add_attribute("action", "distribute_by_voting_power")
+        .add_attribute("token", token.as_str())
+        .add_attribute("height", height.to_string())
+        .add_attribute("amount_distribute", amount.to_string()))
+}
+
+fn execute_update_weight_registry(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    registry: Option<Addr>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Admin)?;
+
+    let registry = registry
+        .map(|registry| deps.api.addr_validate(registry.as_str()))
+        .transpose()?;
+    save_or_remove(deps.storage, &WEIGHT_REGISTRY, registry.as_ref())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_weight_registry")
+        .add_attributes(registry.map(|registry| ("registry", registry))))
+}
+
+fn execute_distribute_from_registry(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    let config = CONFIG.load(deps.storage)?;
+    assert_not_paused(deps.storage, Operation::Distribute)?;
+
+    let registry = WEIGHT_REGISTRY
+        .may_load(deps.storage)?
+        .ok_or(ContractError::RegistryNotSet {})?;
+
+    _assert_distribute_balance(deps.as_ref(), &env, &config.distribute_token, amount)?;
+
+    let targets = _active_targets(deps.as_ref(), &env, None)?;
+    let registry_weights: RegistryWeightsResponse = deps.querier.query_wasm_smart(
+        registry.clone(),
+        &WeightRegistryQueryMsg::Weights {
+            addrs: targets
+                .iter()
+                .map(|target| target.addr.to_string())
+                .collect(),
+        },
+    )?;
+
+    // targets unknown to the registry get no weight for this distribution
+    let weights = targets
+        .iter()
+        .map(|target| {
+            registry_weights
+                .weights
+                .iter()
+                .find(|registry_weight| registry_weight.addr == target.addr)
+                .map(|registry_weight| registry_weight.weight)
+                .unwrap_or_default()
+        })
+        .collect::<Vec<u64>>();
+    let total_weight = weights.iter().sum::<u64>();
+    if total_weight == 0 {
+        return Err(ContractError::InvalidRegistryWeights {});
+    }
+
+    let payouts = targets
+        .into_iter()
+        .zip(weights)
+        .map(|(target, weight)| (amount.multiply_ratio(weight, total_weight), target))
+        .collect::<Vec<(Uint128, DistributeTarget)>>();
+
+    let response = _settle_distribution(deps, &env, config.distribute_token, amount, payouts)?;
+    Ok(response
+        .add_attribute("action", "distribute_from_registry")
+        .add_attribute("registry", registry.as_str())
+        .add_attribute("amount_distribute", amount.to_string()))
+}
+
+fn _assert_distribute_balance(
+    deps: Deps,
+    env: &Env,
+    distribute_token: &Addr,
+    amount_distribute: Uint128,
+) -> Result<(), ContractError> {
+    let balance: BalanceResponse = deps.querier.query_wasm_smart(
+        distribute_token.clone(),
+        &cw20_base::msg::QueryMsg::Balance {
+            address: env.contract.address.to_string(),
+        },
+    )?;
+
+    balance
+        .balance
+        .checked_sub(_reserved_amount(deps, distribute_token)?)
+        .and_then(|available| available.checked_sub(amount_distribute))
+        .map_err(|_| ContractError::ExceedContractBalance {})?;
+
+    Ok(())
+}
+
+fn execute_distribute_as_stake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    validators: Vec<ValidatorWeight>,
+) -> Result<Response, ContractError> {
+    assert_owner(deps.storage, &info.sender)?;
+    assert_not_paused(deps.storage, Operation::Distribute)?;
+
+    let duplicated = validators.iter().enumerate().any(|(index, validator)| {
+        validators[..index]
+            .iter()
+            .any(|other| other.validator == validator.validator)
+    });
+    if validators.is_empty()
+        || duplicated
+        || validators.iter().any(|validator| validator.weight == 0)
+    {
+        return Err(ContractError::InvalidValidatorWeights {});
+    }
+    for validator in &validators {
+        if deps
+            .querier
+            .query_validator(&validator.validator)?
+            .is_none()
+        {
+            return Err(ContractError::UnknownValidator {
+                validator: validator.validator.clone(),
+            });
+        }
+    }
+
+    let denom = deps.querier.query_bonded_denom()?;
+    _assert_native_distribute_balance(deps.as_ref(), &env, &denom, amount)?;
+
+    let total_weight = validators
+        .iter()
+        .map(|validator| validator.weight as u64)
+        .sum::<u64>();
+    let delegations = validators
+        .into_iter()
+        .map(|validator| DistributionShare {
+            addr: Addr::unchecked(validator.validator),
+            amount: amount.multiply_ratio(validator.weight, total_weight),
+        })
+        .filter(|delegation| !delegation.amount.is_zero())
+        .collect::<Vec<DistributionShare>>();
+    let messages = delegations
+        .iter()
+        .map(|delegation| StakingMsg::Delegate {
+            validator: delegation.addr.to_string(),
+            amount: coin(delegation.amount.u128(), &denom),
+        })
+        .collect::<Vec<StakingMsg>>();
+    let events = _summary_events(deps.storage, delegations)?;
+    _record_distribution(
+        deps.storage,
+        &env,
+        &AssetInfo::NativeToken {
+            denom: denom.clone(),
+        },
+        amount,
+    )?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_events(events)
+        .add_attribute("action", "distribute_as_stake")
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount.to_string()))
+}
+
+/// Fails unless the contract holds `amount` of `asset` on top of what it owes
+fn _assert_asset_balance(
+    deps: Deps,
+    env: &Env,
+    asset: &AssetInfo,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    asset
+        .query_pool(&deps.querier, env.contract.address.clone())?
+        .checked_sub(_reserved_asset_amount(deps, asset)?)
+        .and_then(|available| available.checked_sub(amount))
+        .map_err(|_| ContractError::ExceedContractBalance {})?;
+
+    Ok(())
+}
+
+fn _assert_native_distribute_balance(
+    deps: Deps,
+    env: &Env,
+    denom: &str,
+    amount_distribute: Uint128,
+) -> Result<(), ContractError> {
+    let balance = deps
+        .querier
+        .query_balance(env.contract.address.clone(), denom)?;
+    let reserved = _reserved_asset_amount(
+        deps,
+        &AssetInfo::NativeToken {
+            denom: denom.to_string(),
+        },
+    )?;
+
+    balance
+        .amount
+        .checked_sub(reserved)
+        .and_then(|available| available.checked_sub(amount_distribute))
+        .map_err(|_| ContractError::ExceedContractBalance {})?;
+
+    Ok(())
+}
+
+/// Balance of `asset` the contract holds on behalf of others, native claimable
+/// balances are tracked by denom and the vault denom also holds the deposits
+fn _reserved_asset_amount(deps: Deps, asset: &AssetInfo) -> StdResult<Uint128> {
+    match asset {
+        AssetInfo::Token { contract_addr } => _reserved_amount(deps, contract_addr),
+        AssetInfo::NativeToken { .. } => _owed_amount(deps, asset),
+    }
+}
+
+/// Balance of `asset` Pull mode targets can claim and vault depositors can redeem,
+/// both stay open once the contract is sunset
+fn _owed_amount(deps: Deps, asset: &AssetInfo) -> StdResult<Uint128> {
+    let claimable = TOTAL_CLAIMABLE
+        .may_load(deps.storage, &asset_info_to_string(asset))?
+        .unwrap_or_default();
+    let vault_held = match (VAULT.may_load(deps.storage)?, asset) {
+        (Some(vault), AssetInfo::NativeToken { denom }) if &vault.denom == denom => {
+            VAULT_HELD.may_load(deps.storage)?.unwrap_or_default()
+        }
+        _ => Uint128::zero(),
+    };
+    Ok(claimable + vault_held)
+}
+
+/// Balance of `distribute_token` owed to a pending distribution, to failed hooks
+/// or to the targets of a Pull mode distribution
+fn _reserved_amount(deps: Deps, distribute_token: &Addr) -> StdResult<Uint128> {
+    let pending = PENDING_DISTRIBUTION
+        .may_load(deps.storage)?
+        .map(|mut pending| {
+            let queued = std::mem::take(&mut pending.queued);
+            std::iter::once(pending)
+                .chain(queued)
+                .filter(|pending| &pending.distribute_token == distribute_token)
+                .flat_map(|pending| pending.payouts)
+                .map(|(amount, _)| amount)
+                .sum::<Uint128>()
+        })
+        .unwrap_or_default();
+    let failed_hooks = FAILED_HOOKS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, failed)| failed))
+        .collect::<StdResult<Vec<HookAttempt>>>()?
+        .into_iter()
+        .filter(|failed| &failed.token == distribute_token)
+        .map(|failed| failed.amount)
+        .sum::<Uint128>();
+    let claimable = TOTAL_CLAIMABLE
+        .may_load(deps.storage, distribute_token.as_str())?
+        .unwrap_or_default();
+
+    Ok(pending + failed_hooks + claimable)
+}
+
+pub fn execute_collect_fees(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collect_fee_requirements: Vec<CollectFeeRequirement>,
+) -> Result<Response, ContractError> {
+    _execute_collect_fees(deps, env, info, collect_fee_requirements, None, None)
+}
+
+fn _execute_collect_fees(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mut collect_fee_requirements: Vec<CollectFeeRequirement>,
+    max_total_offer: Option<Uint128>,
+    burn_ratio: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    if !EXECUTORS
+        .load(deps.storage, &info.sender)
+        .unwrap_or_default()
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+    assert_not_paused(deps.storage, Operation::Collect)?;
+    // an empty call is most likely a keeper bug, fail loudly instead of burning gas
+    if collect_fee_requirements.is_empty() {
+        return Err(ContractError::NoRequirements {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+
+    if config.router.is_none() {
+        return Err(ContractError::RouterAndApproverNotSet {});
+    }
+    let router_unwrap = config.router.unwrap();
+
+    _assert_oracle_fresh(deps.as_ref(), &env, config.distribute_token.to_string())?;
+
+    if PENDING_COLLECT.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::CollectInProgress {});
+    }
+    // requirements beyond the per call cap are left for ContinueCollect
+    let remaining = match MAX_ASSETS_PER_COLLECT.may_load(deps.storage)? {
+        Some(max_assets) if collect_fee_requirements.len() > max_assets as usize => {
+            collect_fee_requirements.split_off(max_assets as usize)
+        }
+        _ => vec![],
+    };
+    let remaining_requirements = remaining.len();
+    if !remaining.is_empty() {
+        PENDING_COLLECT.save(deps.storage, &remaining)?;
+    }
+
+    // convert 1 times to asset info
+    let distribute_asset_info =
+        asset_info_from_string(deps.api, config.distribute_token.clone().into());
+    let collect_cooldown_seconds = COLLECT_COOLDOWN_SECONDS
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    // build swap operations
+    let mut collected = vec![];
+    let mut skipped_events = vec![];
+    for requirement in collect_fee_requirements.iter() {
+        let fee = match _build_collect_fee_messages(
+            deps.as_ref(),
+            &env,
+            &router_unwrap,
+            &distribute_asset_info,
+            requirement,
+        ) {
+            Ok(Some(fee)) => fee,
+            _ => continue,
+        };
+
+        // swapping the same asset again right away only adds price impact
+        let offer_key = asset_info_to_string(&fee.offer_asset);
+        if let Some(last_collected) = LAST_COLLECTED.may_load(deps.storage, &offer_key)? {
+            let available_at = last_collected.plus_seconds(collect_cooldown_seconds);
+            if env.block.time < available_at {
+                skipped_events.push(
+                    Event::new("collect-cooldown")
+                        .add_attribute("offer_asset", offer_key)
+                        .add_attribute("available_at", available_at.to_string()),
+                );
+                continue;
+            }
+        }
+
+        // keepers may run often, the swap only goes through at a good enough price
+        if let Some(min_price) = requirement.min_price {
+            let simulation: SimulateSwapOperationsResponse = deps.querier.query_wasm_smart(
+                &router_unwrap,
+                &RouterQueryMsg::SimulateSwapOperations {
+                    offer_amount: fee.offer_amount,
+                    operations: requirement.swap_operations.clone(),
+                },
+            )?;
+            let price = Decimal::from_ratio(simulation.amount, fee.offer_amount);
+            if price < min_price {
+                skipped_events.push(
+                    Event::new("collect-skipped-price")
+                        .add_attribute("offer_asset", asset_info_to_string(&fee.offer_asset))
+                        .add_attribute("price", price.to_string())
+                        .add_attribute("min_price", min_price.to_string()),
+                );
+                continue;
+            }
+        }
+        if collect_cooldown_seconds > 0 {
+            LAST_COLLECTED.save(deps.storage, &offer_key, &env.block.time)?;
+        }
+        collected.push(fee);
+    }
+
+    if let Some(max_total_offer) = max_total_offer {
+        // the guard caps the whole collection, not each requirement on its own
+        let offer_amount = collected
+            .iter()
+            .try_fold(Uint128::zero(), |total, collected| {
+                total.checked_add(collected.offer_amount)
+            })
+            .map_err(StdError::from)?;
+        if offer_amount > max_total_offer {
+            return Err(ContractError::OfferExceedsGuard {
+                offer_amount,
+                max_total_offer,
+            });
+        }
+        // the requirements left for ContinueCollect share what is left of the guard
+        if !remaining.is_empty() {
+            PENDING_COLLECT_GUARD.save(deps.storage, &(max_total_offer - offer_amount))?;
+        }
+    }
+
+    // track every involved asset so the balances can be reconciled once the swaps settle
+    let mut assets = vec![distribute_asset_info];
+    collected.iter().for_each(|collected| {
+        if !assets.contains(&collected.offer_asset) {
+            assets.push(collected.offer_asset.clone());
+        }
+    });
+    let before = _query_asset_balances(deps.as_ref(), &env.contract.address, &assets)?;
+
+    let mut messages = collected
+        .into_iter()
+        .flat_map(|collected| collected.messages)
+        .collect::<Vec<CosmosMsg>>();
+
+    let response = Response::new()
+        .add_events(skipped_events)
+        .add_attribute("action", "collect_fees")
+        .add_attribute("remaining_requirements", remaining_requirements.to_string());
+
+    match messages.pop() {
+        None => Ok(response.set_data(to_json_binary(&Reconciliation {
+            after: before.clone(),
+            before,
+        })?)),
+        Some(last_message) => {
+            COLLECT_SNAPSHOT.save(
+                deps.storage,
+                &CollectSnapshot {
+                    assets,
+                    before,
+                    burn_ratio,
+                },
+            )?;
+            // the reply fires once every swap has been executed
+            Ok(response
+                .add_messages(messages)
+                .add_submessage(SubMsg::reply_on_success(
+                    last_message,
+                    COLLECT_FEES_REPLY_ID,
+                )))
+        }
+    }
+}
+
+struct CollectedFee {
+    offer_asset: AssetInfo,
+    offer_amount: Uint128,
+    messages: Vec<CosmosMsg>,
+}
+
+fn _build_collect_fee_messages(
+    deps: Deps,
+    env: &Env,
+    router: &Addr,
+    distribute_asset_info: &AssetInfo,
+    requirement: &CollectFeeRequirement,
+) -> StdResult<Option<CollectedFee>> {
+    // create a new variable for better code readability
+    let fees_receiver = env.contract.address.clone();
+    let operations = requirement.clone().swap_operations;
+
+    let offer_asset = if let Some(SwapOperation::SwapV3 { pool_key, x_to_y }) = operations.first() {
+        if *x_to_y {
+            asset_info_from_string(deps.api, pool_key.token_x.clone())
+        } else {
+            asset_info_from_string(deps.api, pool_key.token_y.clone())
+        }
+    } else {
+        return Ok(None);
+    };
+
+    let final_ask_asset =
+        if let Some(SwapOperation::SwapV3 { pool_key, x_to_y }) = operations.last() {
+            if *x_to_y {
+                asset_info_from_string(deps.api, pool_key.token_y.clone())
+            } else {
+                asset_info_from_string(deps.api, pool_key.token_x.clone())
+            }
+        } else {
+            return Ok(None);
+        };
+
+    // final ask asset should be distribute token
+    if *distribute_asset_info != final_ask_asset {
+        return Ok(None);
+    }
+
+    let balance = offer_asset
+        .query_pool(&deps.querier, requirement.approver.clone())
+        .unwrap_or_default();
+
+    if balance.is_zero() {
+        return Ok(None);
+    }
+    // Assume that the owner approve infinite allowance to the contract
+    let (offer_amount, messages) = match &offer_asset {
+        AssetInfo::Token { contract_addr } => {
+            // transfer from only if distribute asset equals to offer_asset
+            let messages = if *distribute_asset_info == offer_asset {
+                vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: contract_addr.clone().into(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+                        owner: requirement.approver.to_string(),
+                        recipient: fees_receiver.to_string(),
+                        amount: balance,
+                    })?,
+                    funds: vec![],
+                })]
+            } else {
+                vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: contract_addr.clone().into(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::SendFrom {
+                        owner: requirement.approver.to_string(),
+                        contract: router.to_string(),
+                        amount: balance,
+                        msg: to_json_binary(&Cw20RouterHookMsg::ExecuteSwapOperations {
+                            operations,
+                            minimum_receive: requirement.minimum_receive,
+                            to: Some(fees_receiver.to_string()),
+                            affiliates: None,
+                        })?,
+                    })?,
+                    funds: vec![],
+                })]
+            };
+            (balance, messages)
+        }
+        // handle native token
+        AssetInfo::NativeToken { denom } => {
+            let mut swap_amount = balance;
+            if denom == "orai" {
+                // Left 1 orai for transaction fee
+                swap_amount = swap_amount
+                    .checked_sub(Uint128::from(1000000u128))
+                    .unwrap_or_default();
+            }
+
+            if swap_amount.is_zero() {
+                return Ok(None);
+            }
+
+            let send = MsgSend {
+                from_address: requirement.approver.to_string(),
+                to_address: fees_receiver.to_string(),
+                amount: vec![Coin {
+                    denom: denom.clone(),
+                    amount: swap_amount.to_string(),
+                }],
+            };
+            let send_any_result = send.to_any();
+            if send_any_result.is_err() {
+                return Ok(None);
+            }
+
+            let stargate_value = Binary::from(
+                MsgExec {
+                    grantee: fees_receiver.to_string(),
+                    msgs: vec![send_any_result.unwrap()],
+                }
+                .encode_to_vec(),
+            );
+            // transfer_from native token
+            let stargate = CosmosMsg::Stargate {
+                type_url: "/cosmos.authz.v1beta1.MsgExec".to_string(),
+                value: stargate_value,
+            };
+
+            let wasm_swap = CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: router.to_string(),
+                msg: to_json_binary(&RouterExecuteMsg::ExecuteSwapOperations {
+                    operations: operations.clone(),
+                    to: Some(fees_receiver.clone()),
+                    minimum_receive: requirement.minimum_receive,
+                    affiliates: None,
+                })?,
+                funds: vec![cosmwasm_std::Coin {
+                    denom: denom.clone(),
+                    amount: swap_amount,
+                }],
+            });
+
+            (swap_amount, vec![stargate, wasm_swap])
+        }
+    };
+
+    Ok(Some(CollectedFee {
+        offer_asset,
+        offer_amount,
+        messages,
+    }))
+}
+
+fn _query_asset_balances(
+    deps: Deps,
+    account: &Addr,
+    assets: &[AssetInfo],
+) -> StdResult<BTreeMap<String, Uint128>> {
+    assets
+        .iter()
+        .map(|asset| {
+            Ok((
+                asset_info_to_string(asset),
+                asset.query_pool(&deps.querier, account.clone())?,
+            ))
+        })
+        .collect()
+}
+
+/// Split of a distribution once every enabled modifier has been applied. Both the
+/// execution and the previews go through here so they can never disagree.
+struct DistributionPlan {
+    payouts: Vec<(Uint128, DistributeTarget)>,
+    undistributed: Uint128,
+}
+
+/// Modifiers are applied in a fixed order:
+/// 1. targets past their removal grace period are skipped, their share stays undistributed
+/// 2. targets with a USD target get its value at the oracle price, pro-rated when
+///    the amount does not cover them all
+/// 3. every other active target gets its weighted share of the rest, rounded down
+/// 4. the rounding residue of step 3 goes to the remainder target, if one is set
+fn _compute_distribution_plan(
+    deps: Deps,
+    env: &Env,
+    token: Option<&AssetInfo>,
+    amount_distribute: Uint128,
+) -> Result<DistributionPlan, ContractError> {
+    let (usd_targets, weighted_targets): (Vec<DistributeTarget>, Vec<DistributeTarget>) =
+        _active_targets(deps, env, token)?
+            .into_iter()
+            .partition(|target| target.usd_target.is_some());
+    let active_weight = weighted_targets
+        .iter()
+        .map(|target| target.weight as u64)
+        .sum::<u64>();
+
+    let mut payouts = _usd_target_payouts(deps, env, token, amount_distribute, usd_targets)?;
+    let weighted_amount = amount_distribute
+        .checked_sub(payouts.iter().map(|(amount, _)| *amount).sum::<Uint128>())
+        .map_err(StdError::from)?;
+    let weighted_start = payouts.len();
+    payouts.extend(
+        weighted_targets
+            .into_iter()
+            .map(|target| (target_share(weighted_amount, &target), target)),
+    );
+
+    if let Some(remainder_target) = REMAINDER_TARGET.may_load(deps.storage)? {
+        let weighted = weighted_amount.multiply_ratio(active_weight, MAX_TOTAL_WEIGHT);
+        let residue = weighted
+            .checked_sub(
+                payouts[weighted_start..]
+                    .iter()
+                    .map(|(amount, _)| *amount)
+                    .sum::<Uint128>(),
+            )
+            .map_err(StdError::from)?;
+        if !residue.is_zero() {
+            payouts.push((
+                residue,
+                DistributeTarget {
+                    addr: remainder_target,
+                    weight: 0,
+                    msg_hook: None,
+                    preferred_asset: None,
+                    usd_target: None,
+                    hook_gas_limit: None,
+                    ibc: None,
+                },
+            ));
+        }
+    }
+
+    let distributed = payouts.iter().map(|(amount, _)| *amount).sum::<Uint128>();
+
+    Ok(DistributionPlan {
+        undistributed: amount_distribute
+            .checked_sub(distributed)
+            .map_err(StdError::from)?,
+        payouts,
+    })
+}
+
+/// Distributed token amounts worth the USD targets, scaled down to `amount_distribute`
+/// when they exceed it
+fn _usd_target_payouts(
+    deps: Deps,
+    env: &Env,
+    token: Option<&AssetInfo>,
+    amount_distribute: Uint128,
+    targets: Vec<DistributeTarget>,
+) -> Result<Vec<(Uint128, DistributeTarget)>, ContractError> {
+    if targets.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let denom = match token {
+        Some(token) => asset_info_to_string(token),
+        None => CONFIG.load(deps.storage)?.distribute_token.to_string(),
+    };
+    let unpriced = || ContractError::UsdTargetUnpriced {
+        denom: denom.clone(),
+    };
+    let oracle = ORACLE.may_load(deps.storage)?.ok_or_else(unpriced)?;
+    // a stale price would pay the targets a wrong token amount
+    let price = _fresh_oracle_price(deps, env, &oracle, denom.clone())?;
+    if price.is_zero() {
+        return Err(unpriced());
+    }
+
+    let mut payouts = targets
+        .into_iter()
+        .map(|target| {
+            let amount = target
+                .usd_target
+                .unwrap_or_default()
+                .checked_multiply_ratio(price.denominator(), price.numerator())
+                .map_err(|_| unpriced())?;
+            Ok((amount, target))
+        })
+        .collect::<Result<Vec<(Uint128, DistributeTarget)>, ContractError>>()?;
+
+    let total = payouts.iter().map(|(amount, _)| *amount).sum::<Uint128>();
+    if total > amount_distribute {
+        for (amount, _) in payouts.iter_mut() {
+            *amount = amount.multiply_ratio(amount_distribute, total);
+        }
+    }
+    Ok(payouts)
+}
+
+fn _build_target_messages(
+    querier: &QuerierWrapper,
+    router: Option<&Addr>,
+    payouts: &[(Uint128, DistributeTarget)],
+    distribute_token: Addr,
+) -> Result<Vec<WasmMsg>, ContractError> {
+    let distribute_asset_info = AssetInfo::Token {
+        contract_addr: distribute_token.clone(),
+    };
+    let mut swapped = vec![];
+    payouts
+        .iter()
+        .map(
+            |(transfer_amount, target)| -> Result<WasmMsg, ContractError> {
+                let transfer_amount = *transfer_amount;
+                // cw20 tokens leave the chain through an ics20 contract, not a transfer message
+                if target.ibc.is_some() {
+                    return Err(ContractError::IbcTargetRequiresNativeToken {
+                        addr: target.addr.to_string(),
+                    });
+                }
+
+                if let Some(operations) =
+                    _preferred_asset_operations(&distribute_asset_info, target)
+                {
+                    let router = router.ok_or(ContractError::RouterAndApproverNotSet {})?;
+                    let minimum_receive = _preferred_asset_minimum(
+                        querier,
+                        router,
+                        &operations,
+                        &mut swapped,
+                        transfer_amount,
+                    )?;
+                    return Ok(WasmMsg::Execute {
+                        contract_addr: distribute_token.clone().into(),
+                        msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                            contract: router.to_string(),
+                            amount: transfer_amount,
+                            msg: to_json_binary(&Cw20RouterHookMsg::ExecuteSwapOperations {
+                                operations,
+                                minimum_receive: Some(minimum_receive),
+                                to: Some(target.addr.to_string()),
+                                affiliates: None,
+                            })?,
+                        })?,
+                        funds: vec![],
+                    });
+                }
+
+                let msg = match target.clone().msg_hook {
+                    None => WasmMsg::Execute {
+                        contract_addr: distribute_token.clone().into(),
+                        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                            recipient: target.clone().addr.into(),
+                            amount: transfer_amount,
+                        })?,
+                        funds: vec![],
+                    },
+                    Some(msg_hook) => WasmMsg::Execute {
+                        contract_addr: distribute_token.clone().into(),
+                        msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                            contract: target.clone().addr.into(),
+                            amount: transfer_amount,
+                            msg: msg_hook,
+                        })?,
+                        funds: vec![],
+                    },
+                };
+                Ok(msg)
+            },
+        )
+        .collect::<Result<Vec<WasmMsg>, ContractError>>()
+}
+
+/// Target messages as submessages. While hook retries are enabled the hook
+/// payouts are tracked so a failing hook is recorded instead of reverting
+fn _build_target_submessages(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    payouts: &[(Uint128, DistributeTarget)],
+    distribute_token: Addr,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let router = CONFIG.load(storage)?.router;
+    let messages =
+        _build_target_messages(querier, router.as_ref(), payouts, distribute_token.clone())?;
+    if HOOK_RETRY.may_load(storage)?.is_none() {
+        return Ok(messages
+            .into_iter()
+            .zip(payouts)
+            .map(|(msg, (_, target))| _with_hook_gas_limit(SubMsg::new(msg), target))
+            .collect());
+    }
+
+    let first_id = _next_hook_reply_id(storage)?;
+    let mut submessages = vec![];
+    for (index, (msg, (amount, target))) in messages.into_iter().zip(payouts).enumerate() {
+        match &target.msg_hook {
+            None => submessages.push(SubMsg::new(msg)),
+            Some(msg_hook) => {
+                let id = first_id + index as u64;
+                HOOK_ATTEMPTS.save(
+                    storage,
+                    id,
+                    &HookAttempt {
+                        target: target.addr.clone(),
+                        token: distribute_token.clone(),
+                        amount: *amount,
+                        msg_hook: msg_hook.clone(),
+                        retries: 0,
+                        retry_at_height: 0,
+                        gas_limit: target.hook_gas_limit,
+                    },
+                )?;
+                submessages.push(_with_hook_gas_limit(SubMsg::reply_always(msg, id), target));
+            }
+        }
+    }
+    Ok(submessages)
+}
+
+/// First reply id above the hooks in flight. A response can carry several batches
+/// and retries, each hook gets an id no other one holds
+fn _next_hook_reply_id(storage: &dyn Storage) -> StdResult<u64> {
+    Ok(HOOK_ATTEMPTS
+        .keys(storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map_or(HOOK_REPLY_ID_START, |id| id + 1))
+}
+
+/// Caps the gas of a hook payout at the target's limit, plain transfers stay uncapped
+fn _with_hook_gas_limit(submsg: SubMsg, target: &DistributeTarget) -> SubMsg {
+    match (&target.msg_hook, target.hook_gas_limit) {
+        (Some(_), Some(gas_limit)) => submsg.with_gas_limit(gas_limit),
+        _ => submsg,
+    }
+}
+
+/// Output a preferred asset swap of `amount` must at least return. The swaps of a
+/// distribution run in order, so a route is quoted on top of the amount already
+/// swapped through it
+fn _preferred_asset_minimum(
+    querier: &QuerierWrapper,
+    router: &Addr,
+    operations: &[SwapOperation],
+    swapped: &mut Vec<(Vec<SwapOperation>, Uint128)>,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    let quote = |offer_amount: Uint128| -> StdResult<Uint128> {
+        if offer_amount.is_zero() {
+            return Ok(Uint128::zero());
+        }
+        let simulation: SimulateSwapOperationsResponse = querier.query_wasm_smart(
+            router,
+            &RouterQueryMsg::SimulateSwapOperations {
+                offer_amount,
+                operations: operations.to_vec(),
+            },
+        )?;
+        Ok(simulation.amount)
+    };
+
+    let prior = match swapped.iter_mut().find(|(route, _)| route == operations) {
+        Some((_, total)) => {
+            let prior = *total;
+            *total += amount;
+            prior
+        }
+        None => {
+            swapped.push((operations.to_vec(), amount));
+            Uint128::zero()
+        }
+    };
+    let received = quote(prior + amount)?.saturating_sub(quote(prior)?);
+    Ok(received * (Decimal::one() - PREFERRED_ASSET_SLIPPAGE))
+}
+
+/// Router operations swapping the distributed asset into the target's preferred
+/// asset, `None` when the target takes the distributed asset as is
+fn _preferred_asset_operations(
+    distributed_asset: &AssetInfo,
+    target: &DistributeTarget,
+) -> Option<Vec<SwapOperation>> {
+    target
+        .preferred_asset
+        .as_ref()
+        .filter(|preferred_asset| *preferred_asset != distributed_asset)
+        .map(|preferred_asset| {
+            vec![SwapOperation::OraiSwap {
+                offer_asset_info: distributed_asset.clone(),
+                ask_asset_info: preferred_asset.clone(),
+            }]
+        })
+}
+
+/// Bank sends of the payouts, native tokens can not carry a target hook
+fn _build_native_target_messages(
+    querier: &QuerierWrapper,
+    env: &Env,
+    router: Option<&Addr>,
+    payouts: &[(Uint128, DistributeTarget)],
+    denom: &str,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let distributed_asset = AssetInfo::NativeToken {
+        denom: denom.to_string(),
+    };
+    let mut swapped = vec![];
+    payouts
+        .iter()
+        .map(|(amount, target)| {
+            _assert_hooks_supported(&distributed_asset, std::slice::from_ref(target))?;
+            if let Some(operations) = _preferred_asset_operations(&distributed_asset, target) {
+                let router = router.ok_or(ContractError::RouterAndApproverNotSet {})?;
+                let minimum_receive =
+                    _preferred_asset_minimum(querier, router, &operations, &mut swapped, *amount)?;
+                return Ok(_build_swap_message(
+                    router,
+                    &distributed_asset,
+                    *amount,
+                    operations,
+                    &target.addr,
+                    Some(minimum_receive),
+                )?);
+            }
+            if let Some(ibc) = &target.ibc {
+                return _build_ibc_transfer_message(env, ibc, *amount, denom);
+            }
+            Ok(CosmosMsg::Bank(BankMsg::Send {
+                to_address: target.addr.to_string(),
+                amount: coins(amount.u128(), denom),
+            }))
+        })
+        .collect()
+}
+
+fn _build_ibc_transfer_message(
+    env: &Env,
+    ibc: &IbcTarget,
+    amount: Uint128,
+    denom: &str,
+) -> Result<CosmosMsg, ContractError> {
+    let memo = match &ibc.forward {
+        Some(forward) => to_json_string(&PacketMemo {
+            forward: forward.clone(),
+        })?,
+        None => String::new(),
+    };
+    let value = Binary::from(
+        MsgTransfer {
+            source_port: "transfer".to_string(),
+            source_channel: ibc.channel_id.clone(),
+            token: Some(Coin {
+                denom: denom.to_string(),
+                amount: amount.to_string(),
+            }),
+            sender: env.contract.address.to_string(),
+            receiver: ibc.receiver.clone(),
+            timeout_height: None,
+            timeout_timestamp: env.block.time.plus_seconds(ibc.timeout_seconds).nanos(),
+            memo,
+        }
+        .encode_to_vec(),
+    );
+    Ok(CosmosMsg::Stargate {
+        type_url: "/ibc.applications.transfer.v1.MsgTransfer".to_string(),
+        value,
+    })
+}
+
+fn target_share(amount_distribute: Uint128, target: &DistributeTarget) -> Uint128 {
+    amount_distribute * Decimal::percent(target.weight as u64)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Ownership {} => to_json_binary(&cw_ownable::get_ownership(deps.storage)?),
+        QueryMsg::Config {} => to_json_binary(&ConfigResponse(CONFIG.load(deps.storage)?)),
+        QueryMsg::ConfigHistory { limit } => to_json_binary(
+            &CONFIG_HISTORY
+                .may_load(deps.storage)?
+                .unwrap_or_default()
+                .into_iter()
+                .rev()
+                .take(limit.map_or(MAX_CONFIG_HISTORY, |limit| limit as usize))
+                .collect::<Vec<ConfigHistoryEntry>>(),
+        ),
+        QueryMsg::DistributionHistory { limit } => to_json_binary(
+            &DISTRIBUTION_HISTORY
+                .may_load(deps.storage)?
+                .unwrap_or_default()
+                .into_iter()
+                .rev()
+                .take(limit.map_or(MAX_DISTRIBUTION_HISTORY, |limit| limit as usize))
+                .collect::<Vec<DistributionHistoryEntry>>(),
+        ),
+        QueryMsg::HistoryMaxAge {} => {
+            to_json_binary(&HISTORY_MAX_AGE_SECONDS.may_load(deps.storage)?)
+        }
+        QueryMsg::GenesisConfig {} => to_json_binary(&GENESIS_CONFIG.may_load(deps.storage)?),
+        QueryMsg::DistributeTargets {} => to_json_binary(&DistributeTargetsResponse(
+            DISTRIBUTION_TARGETS.load(deps.storage)?,
+        )),
+        QueryMsg::TokenDistributeTargets { token } => to_json_binary(&DistributeTargetsResponse(
+            _token_targets(deps, Some(&token))?,
+        )),
+        QueryMsg::HooksSupported { token } => {
+            to_json_binary(&matches!(token, AssetInfo::Token { .. }))
+        }
+        QueryMsg::IsExecutor { addr } => {
+            to_json_binary(&EXECUTORS.load(deps.storage, &addr).unwrap_or_default())
+        }
+        QueryMsg::PauseFlags {} => to_json_binary(&load_pause_flags(deps.storage)?),
+        QueryMsg::EffectiveDistribution { amount } => {
+            let plan = _compute_distribution_plan(deps, &env, None, amount).map_err(_std_error)?;
+            to_json_binary(&EffectiveDistributionResponse {
+                shares: plan
+                    .payouts
+                    .into_iter()
+                    .map(|(amount, target)| DistributionShare {
+                        addr: target.addr,
+                        amount,
+                    })
+                    .collect(),
+                undistributed: plan.undistributed,
+            })
+        }
+        QueryMsg::SimulateBalancesAfter { amount } => {
+            to_json_binary(&query_simulate_balances_after(deps, env, amount)?)
+        }
+        QueryMsg::RoundingLoss { amount } => {
+            let plan = _compute_distribution_plan(deps, &env, None, amount).map_err(_std_error)?;
+            let active_weight = plan
+                .payouts
+                .iter()
+                .map(|(_, target)| target.weight as u64)
+                .sum::<u64>();
+            let (usd_paid, weighted_paid) = plan.payouts.iter().fold(
+                (Uint128::zero(), Uint128::zero()),
+                |(usd_paid, weighted_paid), (share, target)| match target.usd_target {
+                    Some(_) => (usd_paid + share, weighted_paid),
+                    None => (usd_paid, weighted_paid + share),
+                },
+            );
+            // USD targets are paid exact amounts, only the weighted split rounds
+            to_json_binary(
+                &amount
+                    .checked_sub(usd_paid)?
+                    .multiply_ratio(active_weight, MAX_TOTAL_WEIGHT)
+                    .checked_sub(weighted_paid)?,
+            )
+        }
+        QueryMsg::ZeroShareTargets { amount } => to_json_binary(
+            &_compute_distribution_plan(deps, &env, None, amount)
+                .map_err(_std_error)?
+                .payouts
+                .into_iter()
+                .filter(|(share, target)| share.is_zero() && target.weight > 0)
+                .map(|(_, target)| target.addr)
+                .collect::<Vec<Addr>>(),
+        ),
+        QueryMsg::NextPayout {
+            addr,
+            assumed_amount,
+        } => to_json_binary(
+            &_compute_distribution_plan(deps, &env, None, assumed_amount)
+                .map_err(_std_error)?
+                .payouts
+                .into_iter()
+                .filter(|(_, target)| target.addr == addr)
+                .map(|(amount, _)| amount)
+                .sum::<Uint128>(),
+        ),
+        QueryMsg::FullCyclePlan {
+            collect_fee_requirements,
+        } => to_json_binary(&query_full_cycle_plan(deps, env, collect_fee_requirements)?),
+        QueryMsg::CollectionWorthwhile {
+            assets,
+            gas_price,
+            gas_estimate,
+        } => to_json_binary(&query_collection_worthwhile(
+            deps,
+            env,
+            assets,
+            gas_price,
+            gas_estimate,
+        )?),
+        QueryMsg::Tvl {
+            quote_denom,
+            assets,
+        } => to_json_binary(&query_tvl(deps, env, quote_denom, assets)?),
+        QueryMsg::SupportedActions {} => to_json_binary(
+            &SUPPORTED_ACTIONS
+                .iter()
+                .map(|action| action.to_string())
+                .collect::<Vec<String>>(),
+        ),
+        QueryMsg::TargetKindRestriction {} => to_json_binary(
+            &TARGET_KIND_RESTRICTION
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::WeightRegistry {} => to_json_binary(&WEIGHT_REGISTRY.may_load(deps.storage)?),
+        QueryMsg::MaxHooksPerDistribution {} => {
+            to_json_binary(&MAX_HOOKS_PER_DISTRIBUTION.may_load(deps.storage)?)
+        }
+        QueryMsg::PendingDistribution {} => {
+            to_json_binary(&PENDING_DISTRIBUTION.may_load(deps.storage)?)
+        }
+        QueryMsg::CollectCooldown {} => to_json_binary(
+            &COLLECT_COOLDOWN_SECONDS
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::CanCollectFor { granter } => {
+            let granter = deps.api.addr_validate(&granter)?;
+            let grant = COLLECT_GRANTS.may_load(deps.storage, &granter)?;
+            to_json_binary(&grant.is_some_and(|grant| {
+                grant
+                    .expiration
+                    .map_or(true, |expiration| env.block.time < expiration)
+            }))
+        }
+        QueryMsg::DistributionNonce {} => to_json_binary(
+            &DISTRIBUTION_NONCE
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::AggregateEvents {} => {
+            to_json_binary(&AGGREGATE_EVENTS.may_load(deps.storage)?.unwrap_or_default())
+        }
+        QueryMsg::CurrentEpoch {} => to_json_binary(
+            &EPOCH
+                .may_load(deps.storage)?
+                .map(|epoch| _current_epoch(&epoch, env.block.time)),
+        ),
+        QueryMsg::NextScheduledDistribution {} => {
+            let distribute_token = CONFIG.load(deps.storage)?.distribute_token;
+            let balance: BalanceResponse = deps.querier.query_wasm_smart(
+                distribute_token.clone(),
+                &cw20_base::msg::QueryMsg::Balance {
+                    address: env.contract.address.to_string(),
+                },
+            )?;
+            to_json_binary(&NextScheduledDistributionResponse {
+                available_at: _next_distribution_at(deps.storage, &env)?,
+                amount: balance
+                    .balance
+                    .saturating_sub(_reserved_amount(deps, &distribute_token)?),
+            })
+        }
+        QueryMsg::MaxAssetsPerCollect {} => {
+            to_json_binary(&MAX_ASSETS_PER_COLLECT.may_load(deps.storage)?)
+        }
+        QueryMsg::PendingCollect {} => {
+            to_json_binary(&PENDING_COLLECT.may_load(deps.storage)?.unwrap_or_default())
+        }
+        QueryMsg::Oracle {} => to_json_binary(&ORACLE.may_load(deps.storage)?),
+        QueryMsg::HookRetry {} => to_json_binary(&HOOK_RETRY.may_load(deps.storage)?),
+        QueryMsg::FailedHooks {} => to_json_binary(
+            &FAILED_HOOKS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, failed)| failed))
+                .collect::<StdResult<Vec<HookAttempt>>>()?,
+        ),
+        QueryMsg::SunsetRecipient {} => to_json_binary(&SUNSET_RECIPIENT.may_load(deps.storage)?),
+        QueryMsg::StateSnapshot { start_after, limit } => {
+            to_json_binary(&query_state_snapshot(deps, start_after, limit)?)
+        }
+        QueryMsg::Sunset {} => to_json_binary(&SUNSET.may_load(deps.storage)?),
+        QueryMsg::DistributionMode {} => to_json_binary(
+            &DISTRIBUTION_MODE
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::Claimable { addr } => to_json_binary(&_query_claimable(deps, &addr)?),
+        QueryMsg::ClaimExpiry {} => to_json_binary(&CLAIM_EXPIRY_SECONDS.may_load(deps.storage)?),
+        QueryMsg::TargetProfile { addr, token } => {
+            to_json_binary(&query_target_profile(deps, env, addr, token)?)
+        }
+        QueryMsg::MaxTargetShare {} => {
+            to_json_binary(&MAX_TARGET_SHARE_BPS.may_load(deps.storage)?)
+        }
+        QueryMsg::Vault {} => to_json_binary(&VAULT.may_load(deps.storage)?),
+        QueryMsg::ExecuteAllowlist {} => to_json_binary(
+            &EXECUTE_ALLOWLIST
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::RemainderTarget {} => to_json_binary(&REMAINDER_TARGET.may_load(deps.storage)?),
+        QueryMsg::RemovalGrace {} => to_json_binary(
+            &REMOVAL_GRACE_SECONDS
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::PendingRemovals {} => to_json_binary(
+            &PENDING_REMOVALS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(addr, effective_at)| PendingRemoval { addr, effective_at }))
+                .collect::<StdResult<Vec<PendingRemoval>>>()?,
+        ),
+    }
+}
+
+/// Queries only return `StdError`, the contract errors keep their message
+fn _std_error(err: ContractError) -> StdError {
+    match err {
+        ContractError::Std(err) => err,
+        err => StdError::generic_err(err.to_string()),
+    }
+}
+
+/// Mirrors the skips of `CollectFees`: the per call cap, the cooldown and `min_price`
+fn query_full_cycle_plan(
+    deps: Deps,
+    env: Env,
+    collect_fee_requirements: Vec<CollectFeeRequirement>,
+) -> StdResult<FullCyclePlanResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let router = config.router.ok_or_else(|| {
+        StdError::generic_err(ContractError::RouterAndApproverNotSet {}.to_string())
+    })?;
+    let distribute_asset_info =
+        asset_info_from_string(deps.api, config.distribute_token.clone().into());
+    let max_assets = MAX_ASSETS_PER_COLLECT
+        .may_load(deps.storage)?
+        .map_or(usize::MAX, |max_assets| max_assets as usize);
+    let collect_cooldown_seconds = COLLECT_COOLDOWN_SECONDS
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    let mut collects = vec![];
+    for requirement in collect_fee_requirements.into_iter().take(max_assets) {
+        let Some(collected) =
+            _build_collect_fee_messages(deps, &env, &router, &distribute_asset_info, &requirement)?
+        else {
+            continue;
+        };
+        let offer_key = asset_info_to_string(&collected.offer_asset);
+        if let Some(last_collected) = LAST_COLLECTED.may_load(deps.storage, &offer_key)? {
+            if env.block.time < last_collected.plus_seconds(collect_cooldown_seconds) {
+                continue;
+            }
+        }
+
+        let simulated_amount = if collected.offer_asset == distribute_asset_info {
+            collected.offer_amount
+        } else {
+            let simulation: SimulateSwapOperationsResponse = deps.querier.query_wasm_smart(
+                router.clone(),
+                &RouterQueryMsg::SimulateSwapOperations {
+                    offer_amount: collected.offer_amount,
+                    operations: requirement.swap_operations.clone(),
+                },
+            )?;
+            simulation.amount
+        };
+        if requirement.min_price.is_some_and(|min_price| {
+            Decimal::from_ratio(simulated_amount, collected.offer_amount) < min_price
+        }) {
+            continue;
+        }
+
+        collects.push(CollectPreview {
+            approver: requirement.approver,
+            offer_asset: Asset {
+                info: collected.offer_asset,
+                amount: collected.offer_amount,
+            },
+            simulated_amount,
+        });
+    }
+
+    let balance: BalanceResponse = deps.querier.query_wasm_smart(
+        config.distribute_token.clone(),
+        &cw20_base::msg::QueryMsg::Balance {
+            address: env.contract.address.to_string(),
+        },
+    )?;
+    let distributable = balance
+        .balance
+        .saturating_sub(_reserved_amount(deps, &config.distribute_token)?)
+        + collects
+            .iter()
+            .map(|collect| collect.simulated_amount)
+            .sum::<Uint128>();
+    let plan = _compute_distribution_plan(deps, &env, None, distributable).map_err(_std_error)?;
+
+    Ok(FullCyclePlanResponse {
+        collects,
+        distributable,
+        shares: plan
+            .payouts
+            .into_iter()
+            .map(|(amount, target)| DistributionShare {
+                addr: target.addr,
+                amount,
+            })
+            .collect(),
+        undistributed: plan.undistributed,
+    })
+}
+
+fn query_collection_worthwhile(
+    deps: Deps,
+    env: Env,
+    assets: Vec<CollectFeeRequirement>,
+    gas_price: Decimal,
+    gas_estimate: u64,
+) -> StdResult<CollectionWorthwhileResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let router = config.router.ok_or_else(|| {
+        StdError::generic_err(ContractError::RouterAndApproverNotSet {}.to_string())
+    })?;
+    let distribute_asset_info = asset_info_from_string(deps.api, config.distribute_token.into());
+
+    let mut total_output = Uint128::zero();
+    for requirement in assets {
+        let Some(collected) =
+            _build_collect_fee_messages(deps, &env, &router, &distribute_asset_info, &requirement)?
+        else {
+            continue;
+        };
+        // the distribute token is pulled as is, everything else goes through the router
+        total_output += if collected.offer_asset == distribute_asset_info {
+            collected.offer_amount
+        } else {
+            let simulation: SimulateSwapOperationsResponse = deps.querier.query_wasm_smart(
+                router.clone(),
+                &RouterQueryMsg::SimulateSwapOperations {
+                    offer_amount: collected.offer_amount,
+                    operations: requirement.swap_operations,
+                },
+            )?;
+            simulation.amount
+        };
+    }
+
+    let gas_cost = Uint128::from(gas_estimate) * gas_price;
+    Ok(CollectionWorthwhileResponse {
+        total_output,
+        gas_cost,
+        worthwhile: total_output > gas_cost,
+    })
+}
+
+/// Contract balance of `asset` and its value in `quote_denom`, `None` when the
+/// router can not price it
+fn _value_asset(
+    deps: Deps,
+    env: &Env,
+    router: Option<&Addr>,
+    quote_denom: &str,
+    asset: &TvlAsset,
+) -> StdResult<(Uint128, Option<Uint128>)> {
+    let balance = asset
+        .info
+        .query_pool(&deps.querier, env.contract.address.clone())?;
+    if asset_info_to_string(&asset.info) == quote_denom {
+        return Ok((balance, Some(balance)));
+    }
+    if balance.is_zero() {
+        return Ok((balance, Some(Uint128::zero())));
+    }
+
+    // a missing route or a failing simulation leaves the asset unpriced
+    let value = router
+        .filter(|_| !asset.operations.is_empty())
+        .and_then(|router| {
+            deps.querier
+                .query_wasm_smart::<SimulateSwapOperationsResponse>(
+                    router.clone(),
+                    &RouterQueryMsg::SimulateSwapOperations {
+                        offer_amount: balance,
+                        operations: asset.operations.clone(),
+                    },
+                )
+                .ok()
+        })
+        .map(|simulation| simulation.amount);
+    Ok((balance, value))
+}
+
+fn query_tvl(
+    deps: Deps,
+    env: Env,
+    quote_denom: String,
+    assets: Vec<TvlAsset>,
+) -> StdResult<TvlResponse> {
+    let router = CONFIG.load(deps.storage)?.router;
+
+    let mut tvl = TvlResponse {
+        total: Uint128::zero(),
+        assets: vec![],
+        unpriced: vec![],
+    };
+    for asset in assets {
+        let (balance, value) = _value_asset(deps, &env, router.as_ref(), &quote_denom, &asset)?;
+        match value {
+            Some(value) => {
+                tvl.total += value;
+                tvl.assets.push(AssetValue {
+                    info: asset.info,
+                    balance,
+                    value,
+                });
+            }
+            None => tvl.unpriced.push(AssetValue {
+                info: asset.info,
+                balance,
+                value: Uint128::zero(),
+            }),
+        }
+    }
+
+    Ok(tvl)
+}
+
+fn query_simulate_balances_after(
+    deps: Deps,
+    env: Env,
+    amount: Uint128,
+) -> StdResult<SimulateBalancesAfterResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let query_balance = |address: String| -> StdResult<Uint128> {
+        let balance: BalanceResponse = deps.querier.query_wasm_smart(
+            config.distribute_token.clone(),
+            &cw20_base::msg::QueryMsg::Balance { address },
+        )?;
+        Ok(balance.balance)
+    };
+
+    let plan = _compute_distribution_plan(deps, &env, None, amount).map_err(_std_error)?;
+
+    let mut distributed = Uint128::zero();
+    let mut targets: Vec<AccountBalance> = vec![];
+    for (share, target) in plan.payouts {
+        distributed += share;
+        // a target listed more than once receives every one of its shares
+        match targets
+            .iter()
+            .position(|account| account.addr == target.addr)
+        {
+            Some(index) => targets[index].balance += share,
+            None => targets.push(AccountBalance {
+                balance: query_balance(target.addr.to_string())? + share,
+                addr: target.addr,
+            }),
+        }
+    }
+
+    Ok(SimulateBalancesAfterResponse {
+        contract_balance: query_balance(env.contract.address.to_string())?
+            .checked_sub(distributed)?,
+        targets,
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        COLLECT_FEES_REPLY_ID => reply_collect_fees(deps, env),
+        id if id >= HOOK_REPLY_ID_START => reply_hook(deps, env, id, msg.result),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+fn reply_hook(
+    deps: DepsMut,
+    env: Env,
+    id: u64,
+    result: SubMsgResult,
+) -> Result<Response, ContractError> {
+    let attempt = HOOK_ATTEMPTS.load(deps.storage, id)?;
+    HOOK_ATTEMPTS.remove(deps.storage, id);
+    if result.is_ok() {
+        return Ok(Response::new());
+    }
+
+    // the payout stays in the contract until it is retried or swept to the fallback
+    let backoff_blocks = HOOK_RETRY
+        .may_load(deps.storage)?
+        .map(|hook_retry| hook_retry.backoff_blocks)
+        .unwrap_or_default();
+    // a failure merges with the earlier one of the same target and token, keeping
+    // the most retries so the payout still reaches the fallback in time
+    let (amount, retries) =
+        match FAILED_HOOKS.may_load(deps.storage, (&attempt.target, &attempt.token))? {
+            Some(failed) => (
+                failed.amount + attempt.amount,
+                failed.retries.max(attempt.retries),
+            ),
+            None => (attempt.amount, attempt.retries),
+        };
+    let failed = HookAttempt {
+        amount,
+        retries,
+        retry_at_height: env.block.height + backoff_blocks * (retries as u64 + 1),
+        ..attempt
+    };
+    FAILED_HOOKS.save(deps.storage, (&failed.target, &failed.token), &failed)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "hook_failed")
+        .add_attribute("target", failed.target.as_str())
+        .add_attribute("amount", failed.amount.to_string()))
+}
+
+fn reply_collect_fees(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let snapshot = COLLECT_SNAPSHOT.load(deps.storage)?;
+    COLLECT_SNAPSHOT.remove(deps.storage);
+
+    let after = _query_asset_balances(deps.as_ref(), &env.contract.address, &snapshot.assets)?;
+    let mut response = Response::new();
+
+    if let Some(burn_ratio) = snapshot.burn_ratio {
+        // only what this collection brought in is burned and distributed
+        let distribute_token = CONFIG.load(deps.storage)?.distribute_token;
+        let key = distribute_token.to_string();
+        let collected = after
+            .get(&key)
+            .copied()
+            .unwrap_or_default()
+            .saturating_sub(snapshot.before.get(&key).copied().unwrap_or_default());
+        let burn_amount = collected * burn_ratio;
+        let distribute_amount = collected - burn_amount;
+
+        if !burn_amount.is_zero() {
+            response = response.add_message(WasmMsg::Execute {
+                contract_addr: distribute_token.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                    amount: burn_amount,
+                })?,
+                funds: vec![],
+            });
+        }
+        response = response
+            .add_attribute("action", "collect_burn_distribute")
+            .add_attribute("burn_amount", burn_amount.to_string())
+            .add_attribute("amount_distribute", distribute_amount.to_string());
+
+        if !distribute_amount.is_zero() {
+            let plan = _compute_distribution_plan(
+                deps.as_ref(),
+                &env,
+                Some(&AssetInfo::Token {
+                    contract_addr: distribute_token.clone(),
+                }),
+                distribute_amount,
+            )?;
+            let settled = _settle_distribution(
+                deps,
+                &env,
+                distribute_token,
+                distribute_amount,
+                plan.payouts,
+            )?;
+            response = response
+                .add_submessages(settled.messages)
+                .add_events(settled.events)
+                .add_attributes(settled.attributes);
+        }
+    }
+
+    Ok(response.set_data(to_json_binary(&Reconciliation {
+        before: snapshot.before,
+        after,
+    })?))
+}
+
+fn query_state_snapshot(
+    deps: Deps,
+    start_after: Option<(Addr, AssetInfo)>,
+    limit: Option<u32>,
+) -> StdResult<StateSnapshot> {
+    let start_after =
+        start_after.map(|(addr, asset_info)| (addr, asset_info_to_string(&asset_info)));
+    let limit = limit.map_or(MAX_SNAPSHOT_ENTRIES, |limit| {
+        (limit as usize).min(MAX_SNAPSHOT_ENTRIES)
+    });
+
+    let claimable = _snapshot_assets(deps, &CLAIMABLE, start_after.as_ref(), limit)?;
+    // the shares are ordered by target
+    let mut credited = claimable
+        .iter()
+        .map(|(addr, _)| addr.clone())
+        .collect::<Vec<Addr>>();
+    credited.dedup();
+
+    Ok(StateSnapshot {
+        version: get_contract_version(deps.storage)?.version,
+        owner: cw_ownable::get_ownership(deps.storage)?.owner,
+        config: CONFIG.load(deps.storage)?,
+        distribute_targets: DISTRIBUTION_TARGETS.load(deps.storage)?,
+        token_distribute_targets: TOKEN_DISTRIBUTION_TARGETS
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<(String, Vec<DistributeTarget>)>>>()?,
+        executors: EXECUTORS
+            .range(deps.storage, None, None, Order::Ascending)
+            .filter_map(|item| match item {
+                Ok((executor, true)) => Some(Ok(executor)),
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<StdResult<Vec<Addr>>>()?,
+        pause_flags: PAUSE_FLAGS.may_load(deps.storage)?.unwrap_or_default(),
+        distribution_mode: DISTRIBUTION_MODE
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        oracle: ORACLE.may_load(deps.storage)?,
+        vault: VAULT.may_load(deps.storage)?,
+        vault_held: VAULT_HELD.may_load(deps.storage)?.unwrap_or_default(),
+        hook_retry: HOOK_RETRY.may_load(deps.storage)?,
+        distribution_interval: DISTRIBUTION_INTERVAL
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        epoch: EPOCH.may_load(deps.storage)?,
+        target_kind_restriction: TARGET_KIND_RESTRICTION
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        max_target_share_bps: MAX_TARGET_SHARE_BPS.may_load(deps.storage)?,
+        remainder_target: REMAINDER_TARGET.may_load(deps.storage)?,
+        weight_registry: WEIGHT_REGISTRY.may_load(deps.storage)?,
+        removal_grace_seconds: REMOVAL_GRACE_SECONDS
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        pending_removals: PENDING_REMOVALS
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(addr, effective_at)| PendingRemoval { addr, effective_at }))
+            .collect::<StdResult<Vec<PendingRemoval>>>()?,
+        execute_allowlist: EXECUTE_ALLOWLIST
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        sunset_recipient: SUNSET_RECIPIENT.may_load(deps.storage)?,
+        aggregate_events: AGGREGATE_EVENTS.may_load(deps.storage)?.unwrap_or_default(),
+        max_hooks_per_distribution: MAX_HOOKS_PER_DISTRIBUTION.may_load(deps.storage)?,
+        max_assets_per_collect: MAX_ASSETS_PER_COLLECT.may_load(deps.storage)?,
+        collect_cooldown_seconds: COLLECT_COOLDOWN_SECONDS
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        history_max_age_seconds: HISTORY_MAX_AGE_SECONDS.may_load(deps.storage)?,
+        pending_distribution: PENDING_DISTRIBUTION.may_load(deps.storage)?,
+        failed_hooks: FAILED_HOOKS
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, failed)| failed))
+            .collect::<StdResult<Vec<HookAttempt>>>()?,
+        claim_expiry_seconds: CLAIM_EXPIRY_SECONDS.may_load(deps.storage)?,
+        last_credited: credited
+            .into_iter()
+            .map(|addr| {
+                let credited_at = LAST_CREDITED.load(deps.storage, &addr)?;
+                Ok((addr, credited_at))
+            })
+            .collect::<StdResult<Vec<(Addr, Timestamp)>>>()?,
+        claimable,
+        received: _snapshot_assets(deps, &TARGET_RECEIVED, start_after.as_ref(), limit)?,
+    })
+}
+
+/// Page of a per target and asset balance, after `start_after`
+fn _snapshot_assets<'a>(
+    deps: Deps,
+    map: &Map<'a, (&'a Addr, &'a str), Uint128>,
+    start_after: Option<&'a (Addr, String)>,
+    limit: usize,
+) -> StdResult<Vec<(Addr, Asset)>> {
+    map.range(
+        deps.storage,
+        start_after.map(|(addr, key)| Bound::exclusive((addr, key.as_str()))),
+        None,
+        Order::Ascending,
+    )
+    .take(limit)
+    .map(|item| {
+        item.map(|((addr, key), amount)| {
+            (
+                addr,
+                Asset {
+                    info: asset_info_from_string(deps.api, key),
+                    amount,
+                },
+            )
+        })
+    })
+    .collect()
+}
+
+fn _query_claimable(deps: Deps, addr: &Addr) -> StdResult<Vec<Asset>> {
+    CLAIMABLE
+        .prefix(addr)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(key, amount)| Asset {
+                info: asset_info_from_string(deps.api, key),
+                amount,
+            })
+        })
+        .collect()
+}
+
+fn query_target_profile(
+    deps: Deps,
+    env: Env,
+    addr: Addr,
+    token: Option<AssetInfo>,
+) -> StdResult<Option<TargetProfileResponse>> {
+    // the list `token` is paid to, or the first one naming the target
+    let mut lists = match &token {
+        Some(token) => {
+            let key = asset_info_to_string(token);
+            let token_list = TOKEN_DISTRIBUTION_TARGETS
+                .has(deps.storage, &key)
+                .then_some(key);
+            vec![(token_list, _token_targets(deps, Some(token))?)]
+        }
+        None => vec![(None, DISTRIBUTION_TARGETS.load(deps.storage)?)],
+    };
+    if token.is_none() {
+        for item in TOKEN_DISTRIBUTION_TARGETS.range(deps.storage, None, None, Order::Ascending) {
+            let (key, targets) = item?;
+            lists.push((Some(key), targets));
+        }
+    }
+    let Some((token_list, targets, target)) = lists.into_iter().find_map(|(key, targets)| {
+        let target = targets.iter().find(|target| target.addr == addr).cloned()?;
+        Some((key, targets, target))
+    }) else {
+        return Ok(None);
+    };
+
+    let total_weight = targets
+        .iter()
+        .map(|target| target.weight as u64)
+        .sum::<u64>();
+    let removal_effective_at = PENDING_REMOVALS.may_load(deps.storage, &addr)?;
+    let received = TARGET_RECEIVED
+        .prefix(&addr)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(key, amount)| Asset {
+                info: asset_info_from_string(deps.api, key),
+                amount,
+            })
+        })
+        .collect::<StdResult<Vec<Asset>>>()?;
+
+    Ok(Some(TargetProfileResponse {
+        share_bps: (target.weight as u64 * 10_000)
+            .checked_div(total_weight)
+            .unwrap_or_default(),
+        enabled: removal_effective_at.map_or(true, |effective_at| effective_at > env.block.time),
+        removal_effective_at,
+        received,
+        claimable: _query_claimable(deps, &addr)?,
+        max_share_bps: MAX_TARGET_SHARE_BPS.may_load(deps.storage)?,
+        hook_type: target.msg_hook.as_ref().and_then(_hook_type),
+        token_list,
+        target,
+    }))
+}
+
+/// Variant name of a JSON enum hook message, either `{"ping":{}}` or `"ping"`
+fn _hook_type(msg_hook: &Binary) -> Option<String> {
+    from_json::<BTreeMap<String, IgnoredAny>>(msg_hook)
+        .ok()
+        .and_then(|variants| variants.into_keys().next())
+        .or_else(|| from_json::<String>(msg_hook).ok())
+}
+
+/// Replace the exported parts of the state with `snapshot`, its claimable shares are
+/// added to the ones imported from earlier pages. The targets are validated like an
+/// update and the contract must hold what the imported state reserves
+fn _import_state_snapshot(
+    deps: DepsMut,
+    env: &Env,
+    snapshot: StateSnapshot,
+) -> Result<(), ContractError> {
+    // the layout is only stable within a minor version
+    let release = |version: &str| version.splitn(3, '.').take(2).collect::<Vec<&str>>();
+    if release(&snapshot.version) != release(CONTRACT_VERSION) {
+        return Err(ContractError::IncompatibleSnapshot {
+            version: snapshot.version,
+            expected: CONTRACT_VERSION.to_string(),
+        });
+    }
+
+    _validate_snapshot_addrs(deps.as_ref(), &snapshot)?;
+
+    cw_ownable::initialize_owner(
+        deps.storage,
+        deps.api,
+        snapshot.owner.as_ref().map(Addr::as_str),
+    )?;
+    CONFIG.save(deps.storage, &snapshot.config)?;
+    PAUSE_FLAGS.save(deps.storage, &snapshot.pause_flags)?;
+    DISTRIBUTION_MODE.save(deps.storage, &snapshot.distribution_mode)?;
+    save_or_remove(deps.storage, &ORACLE, snapshot.oracle.as_ref())?;
+    save_or_remove(deps.storage, &VAULT, snapshot.vault.as_ref())?;
+    VAULT_HELD.save(deps.storage, &snapshot.vault_held)?;
+    save_or_remove(deps.storage, &HOOK_RETRY, snapshot.hook_retry.as_ref())?;
+    DISTRIBUTION_INTERVAL.save(deps.storage, &snapshot.distribution_interval)?;
+    save_or_remove(deps.storage, &EPOCH, snapshot.epoch.as_ref())?;
+    TARGET_KIND_RESTRICTION.save(deps.storage, &snapshot.target_kind_restriction)?;
+    save_or_remove(
+        deps.storage,
+        &MAX_TARGET_SHARE_BPS,
+        snapshot.max_target_share_bps.as_ref(),
+    )?;
+    save_or_remove(
+        deps.storage,
+        &REMAINDER_TARGET,
+        snapshot.remainder_target.as_ref(),
+    )?;
+    save_or_remove(
+        deps.storage,
+        &WEIGHT_REGISTRY,
+        snapshot.weight_registry.as_ref(),
+    )?;
+    REMOVAL_GRACE_SECONDS.save(deps.storage, &snapshot.removal_grace_seconds)?;
+    EXECUTE_ALLOWLIST.save(deps.storage, &snapshot.execute_allowlist)?;
+    save_or_remove(
+        deps.storage,
+        &SUNSET_RECIPIENT,
+        snapshot.sunset_recipient.as_ref(),
+    )?;
+    AGGREGATE_EVENTS.save(deps.storage, &snapshot.aggregate_events)?;
+    save_or_remove(
+        deps.storage,
+        &MAX_HOOKS_PER_DISTRIBUTION,
+        snapshot.max_hooks_per_distribution.as_ref(),
+    )?;
+    save_or_remove(
+        deps.storage,
+        &MAX_ASSETS_PER_COLLECT,
+        snapshot.max_assets_per_collect.as_ref(),
+    )?;
+    COLLECT_COOLDOWN_SECONDS.save(deps.storage, &snapshot.collect_cooldown_seconds)?;
+    save_or_remove(
+        deps.storage,
+        &HISTORY_MAX_AGE_SECONDS,
+        snapshot.history_max_age_seconds.as_ref(),
+    )?;
+    save_or_remove(
+        deps.storage,
+        &PENDING_DISTRIBUTION,
+        snapshot.pending_distribution.as_ref(),
+    )?;
+    save_or_remove(
+        deps.storage,
+        &CLAIM_EXPIRY_SECONDS,
+        snapshot.claim_expiry_seconds.as_ref(),
+    )?;
+
+    // validated against the restriction and share cap imported above
+    let distribute_targets =
+        validate_distribute_targets(deps.as_ref(), &snapshot.distribute_targets)?;
+    DISTRIBUTION_TARGETS.save(deps.storage, &distribute_targets)?;
+    TOKEN_DISTRIBUTION_TARGETS.clear(deps.storage);
+    for (key, targets) in snapshot.token_distribute_targets.iter() {
+        let token = asset_info_from_string(deps.api, key.clone());
+        let targets = _validate_token_targets(deps.as_ref(), &token, targets)?;
+        TOKEN_DISTRIBUTION_TARGETS.save(deps.storage, key, &targets)?;
+    }
+    PENDING_REMOVALS.clear(deps.storage);
+    for removal in snapshot.pending_removals.iter() {
+        PENDING_REMOVALS.save(deps.storage, &removal.addr, &removal.effective_at)?;
+    }
+    EXECUTORS.clear(deps.storage);
+    for executor in snapshot.executors.iter() {
+        EXECUTORS.save(deps.storage, executor, &true)?;
+    }
+    FAILED_HOOKS.clear(deps.storage);
+    for failed in snapshot.failed_hooks.iter() {
+        FAILED_HOOKS.save(deps.storage, (&failed.target, &failed.token), failed)?;
+    }
+    // pages land on top of each other, a share imported twice is not counted twice
+    // and the totals follow so the credited shares stay reserved
+    for (addr, asset) in snapshot.claimable.iter() {
+        let key = asset_info_to_string(&asset.info);
+        let previous = CLAIMABLE
+            .may_load(deps.storage, (addr, key.as_str()))?
+            .unwrap_or_default();
+        CLAIMABLE.save(deps.storage, (addr, key.as_str()), &asset.amount)?;
+        TOTAL_CLAIMABLE.update(deps.storage, key.as_str(), |total| -> StdResult<Uint128> {
+            Ok(total.unwrap_or_default().checked_sub(previous)? + asset.amount)
+        })?;
+    }
+    for (addr, credited_at) in snapshot.last_credited.iter() {
+        LAST_CREDITED.save(deps.storage, addr, credited_at)?;
+    }
+    for (addr, asset) in snapshot.received.iter() {
+        let key = asset_info_to_string(&asset.info);
+        TARGET_RECEIVED.save(deps.storage, (addr, key.as_str()), &asset.amount)?;
+    }
+
+    _assert_reserves_held(deps.as_ref(), env)
+}
+
+/// Every address a snapshot carries outside of its targets, which are validated when
+/// they are imported
+fn _validate_snapshot_addrs(deps: Deps, snapshot: &StateSnapshot) -> StdResult<()> {
+    let allowlist = snapshot
+        .execute_allowlist
+        .iter()
+        .map(|allowed| match allowed {
+            AllowedMsg::BankSend { to_address, .. } => to_address,
+            AllowedMsg::WasmExecute { contract_addr, .. } => contract_addr,
+        });
+    let pending = snapshot
+        .pending_distribution
+        .iter()
+        .flat_map(|pending| std::iter::once(pending).chain(pending.queued.iter()));
+    let balances = snapshot
+        .claimable
+        .iter()
+        .chain(snapshot.received.iter())
+        .flat_map(|(addr, asset)| {
+            let token = match &asset.info {
+                AssetInfo::Token { contract_addr } => Some(contract_addr),
+                AssetInfo::NativeToken { .. } => None,
+            };
+            std::iter::once(addr).chain(token)
+        });
+
+    let addrs = std::iter::once(&snapshot.config.distribute_token)
+        .chain(snapshot.config.router.iter())
+        .chain(snapshot.owner.iter())
+        .chain(snapshot.executors.iter())
+        .chain(snapshot.oracle.iter().map(|oracle| &oracle.oracle))
+        .chain(snapshot.vault.iter().map(|vault| &vault.receipt_token))
+        .chain(
+            snapshot
+                .hook_retry
+                .iter()
+                .map(|hook_retry| &hook_retry.fallback),
+        )
+        .chain(snapshot.remainder_target.iter())
+        .chain(snapshot.weight_registry.iter())
+        .chain(snapshot.sunset_recipient.iter())
+        .chain(
+            snapshot
+                .pending_removals
+                .iter()
+                .map(|removal| &removal.addr),
+        )
+        .chain(allowlist)
+        .chain(pending.flat_map(|pending| {
+            std::iter::once(&pending.distribute_token)
+                .chain(pending.payouts.iter().map(|(_, target)| &target.addr))
+        }))
+        .chain(
+            snapshot
+                .failed_hooks
+                .iter()
+                .flat_map(|failed| [&failed.target, &failed.token]),
+        )
+        .chain(balances)
+        .chain(snapshot.last_credited.iter().map(|(addr, _)| addr));
+    for addr in addrs {
+        deps.api.addr_validate(addr.as_str())?;
+    }
+    Ok(())
+}
+
+/// Fails unless the contract holds every asset its pending distributions, failed
+/// hooks, claimable shares and vault deposits reserve
+fn _assert_reserves_held(deps: Deps, env: &Env) -> Result<(), ContractError> {
+    let mut reserved_assets = TOTAL_CLAIMABLE
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|key| key.map(|key| asset_info_from_string(deps.api, key)))
+        .collect::<StdResult<Vec<AssetInfo>>>()?;
+    if let Some(vault) = VAULT.may_load(deps.storage)? {
+        reserved_assets.push(AssetInfo::NativeToken { denom: vault.denom });
+    }
+    if let Some(mut pending) = PENDING_DISTRIBUTION.may_load(deps.storage)? {
+        let queued = std::mem::take(&mut pending.queued);
+        reserved_assets.extend(std::iter::once(pending).chain(queued).map(|pending| {
+            AssetInfo::Token {
+                contract_addr: pending.distribute_token,
+            }
+        }));
+    }
+    for item in FAILED_HOOKS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, failed) = item?;
+        reserved_assets.push(AssetInfo::Token {
+            contract_addr: failed.token,
+        });
+    }
+
+    let mut checked: Vec<AssetInfo> = vec![];
+    for asset in reserved_assets {
+        if checked.contains(&asset) {
+            continue;
+        }
+        checked.push(asset.clone());
+        let reserved = _reserved_asset_amount(deps, &asset)?;
+        let balance = asset.query_pool(&deps.querier, env.contract.address.clone())?;
+        if balance < reserved {
+            return Err(ContractError::ReservesNotHeld {
+                asset: asset_info_to_string(&asset),
+                reserved,
+                balance,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    // configs stored before the move to cw-ownable carried the owner inline
+    if let Ok(legacy_config) = LEGACY_CONFIG.load(deps.storage) {
+        cw_ownable::initialize_owner(deps.storage, deps.api, Some(legacy_config.owner.as_str()))?;
+        CONFIG.save(
+            deps.storage,
+            &Config {
+                distribute_token: legacy_config.distribute_token,
+                router: legacy_config.router,
+            },
+        )?;
+    }
+
+    // contracts deployed before Pull became the default were pushing
+    if DISTRIBUTION_MODE.may_load(deps.storage)?.is_none() {
+        DISTRIBUTION_MODE.save(deps.storage, &DistributionMode::Push)?;
+    }
+
+    if let Some(snapshot) = msg.import {
+        _import_state_snapshot(deps.branch(), &env, snapshot)?;
+    }
+
+    let mut config = CONFIG.load(deps.storage)?;
+    config.router = Some(msg.new_router);
+    _save_config(deps.storage, &env, &config)?;
+    Ok(Response::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::msg::{ConfigResponse, DistributeTargetsResponse, InstantiateMsg, QueryMsg};
+    use crate::state::{Config, DistributeTarget, ForwardMemo};
+    use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{from_json, OwnedDeps, Uint128};
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_env, mock_info},
+        Addr,
+    };
+
+    use super::*;
+    use crate::multitest::contract::target;
+
+    fn _instantiate_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        let init_distribution_targets = vec![
+            DistributeTarget {
+                msg_hook: Some(to_json_binary(&"hook1").unwrap()),
+                ..target("target1", 40)
+            },
+            target("target2", 60),
+        ];
+
+        let msg = InstantiateMsg {
+            owner: Addr::unchecked("owner"),
+            distribute_token: Addr::unchecked("distribute_token"),
+            init_distribution_targets: init_distribution_targets.clone(),
+            router: Some(Addr::unchecked("router")),
             executors: vec![Addr::unchecked("owner"), Addr::unchecked("executor")],
         };
 
-        let mock_info = mock_info("owner", &[]);
-        let _res = instantiate(deps.as_mut(), mock_env(), mock_info, msg).unwrap();
+        let mock_info = mock_info("owner", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), mock_info, msg).unwrap();
+        DISTRIBUTION_MODE
+            .save(deps.as_mut().storage, &DistributionMode::Push)
+            .unwrap();
+
+        let config_binary = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config = from_json::<ConfigResponse>(&config_binary).unwrap();
+
+        assert_eq!(
+            config,
+            ConfigResponse(Config {
+                distribute_token: Addr::unchecked("distribute_token"),
+                router: Some(Addr::unchecked("router")),
+            })
+        );
+
+        let distribute_targets_binary =
+            query(deps.as_ref(), mock_env(), QueryMsg::DistributeTargets {}).unwrap();
+
+        let distribute_targets =
+            from_json::<DistributeTargetsResponse>(&distribute_targets_binary).unwrap();
+
+        assert_eq!(
+            distribute_targets,
+            DistributeTargetsResponse(init_distribution_targets)
+        );
+
+        // send token
+
+        deps
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let _deps = _instantiate_deps();
+    }
+
+    #[test]
+    fn test_build_target_messages() {
+        let deps = _instantiate_deps();
+
+        let amount_distribute = Uint128::from(1000u128);
+        let distribute_token = Addr::unchecked("distribute_token");
+
+        let plan = _compute_distribution_plan(
+            deps.as_ref(),
+            &mock_env(),
+            Some(&AssetInfo::Token {
+                contract_addr: distribute_token.clone(),
+            }),
+            amount_distribute,
+        )
+        .unwrap();
+        let messages = _build_target_messages(
+            &deps.as_ref().querier,
+            None,
+            &plan.payouts,
+            distribute_token,
+        )
+        .unwrap();
+
+        assert_eq!(
+            messages,
+            vec![
+                WasmMsg::Execute {
+                    contract_addr: "distribute_token".into(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                        contract: "target1".into(),
+                        amount: Uint128::from(400u64),
+                        msg: to_json_binary(&"hook1").unwrap()
+                    })
+                    .unwrap(),
+                    funds: vec![]
+                },
+                WasmMsg::Execute {
+                    contract_addr: "distribute_token".into(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: "target2".into(),
+                        amount: Uint128::from(600u64)
+                    })
+                    .unwrap(),
+                    funds: vec![]
+                }
+            ]
+        )
+    }
+
+    #[test]
+    fn test_hook_submessage_carries_gas_limit() {
+        let mut deps = _instantiate_deps();
+        let hook_target = DistributeTarget {
+            msg_hook: Some(to_json_binary(&"hook1").unwrap()),
+            hook_gas_limit: Some(200_000),
+            ..target("target1", 40)
+        };
+        let payouts = vec![
+            (Uint128::from(400u128), hook_target),
+            (
+                Uint128::from(600u128),
+                DistributeTarget {
+                    hook_gas_limit: Some(200_000),
+                    ..target("target2", 60)
+                },
+            ),
+        ];
+
+        let deps = deps.as_mut();
+        let submessages = _build_target_submessages(
+            deps.storage,
+            &deps.querier,
+            &payouts,
+            Addr::unchecked("distribute_token"),
+        )
+        .unwrap();
+        assert_eq!(submessages[0].gas_limit, Some(200_000));
+        // the cap only applies to hooks
+        assert_eq!(submessages[1].gas_limit, None);
+    }
 
-        let config_binary = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
-        let config = from_json::<ConfigResponse>(&config_binary).unwrap();
+    #[test]
+    fn test_ibc_target_transfer_carries_forward_memo() {
+        let deps = mock_dependencies();
+        let env = mock_env();
+        let ibc = IbcTarget {
+            channel_id: "channel-15".to_string(),
+            receiver: "cosmos1hop".to_string(),
+            timeout_seconds: 600,
+            forward: Some(ForwardMemo {
+                receiver: "osmo1final".to_string(),
+                port: "transfer".to_string(),
+                channel: "channel-141".to_string(),
+                timeout: Some("10m".to_string()),
+                retries: Some(2),
+                next: None,
+            }),
+        };
+        assert_eq!(_validate_ibc_target(&ibc), Ok(()));
+
+        let payouts = vec![(
+            Uint128::from(400u128),
+            DistributeTarget {
+                ibc: Some(ibc),
+                ..target("target1", 40)
+            },
+        )];
+        let messages = _build_native_target_messages(
+            &QuerierWrapper::new(&deps.querier),
+            &env,
+            None,
+            &payouts,
+            "orai",
+        )
+        .unwrap();
+
+        let CosmosMsg::Stargate { type_url, value } = &messages[0] else {
+            panic!("expected a stargate transfer, got {:?}", messages[0]);
+        };
+        assert_eq!(type_url, "/ibc.applications.transfer.v1.MsgTransfer");
+        let transfer = MsgTransfer::decode(value.as_slice()).unwrap();
+        assert_eq!(transfer.source_channel, "channel-15");
+        assert_eq!(transfer.receiver, "cosmos1hop");
+        assert_eq!(
+            transfer.token,
+            Some(Coin {
+                denom: "orai".to_string(),
+                amount: "400".to_string(),
+            })
+        );
+        assert_eq!(
+            transfer.timeout_timestamp,
+            env.block.time.plus_seconds(600).nanos()
+        );
+        assert_eq!(
+            transfer.memo,
+            r#"{"forward":{"receiver":"osmo1final","port":"transfer","channel":"channel-141","timeout":"10m","retries":2}}"#
+        );
+    }
+
+    #[test]
+    fn test_validate_ibc_target() {
+        let ibc = |channel_id: &str, timeout: Option<&str>| IbcTarget {
+            channel_id: channel_id.to_string(),
+            receiver: "cosmos1hop".to_string(),
+            timeout_seconds: 600,
+            forward: Some(ForwardMemo {
+                receiver: "osmo1final".to_string(),
+                port: "transfer".to_string(),
+                channel: "channel-141".to_string(),
+                timeout: timeout.map(str::to_string),
+                retries: None,
+                next: None,
+            }),
+        };
+
+        assert_eq!(
+            _validate_ibc_target(&ibc("channel-0", Some("1h30m"))),
+            Ok(())
+        );
+        assert_eq!(
+            _validate_ibc_target(&ibc("15", None)),
+            Err(ContractError::InvalidIbcTarget {
+                reason: "channel_id must look like channel-<n>".to_string(),
+            })
+        );
+        assert_eq!(
+            _validate_ibc_target(&ibc("channel-0", Some("10 minutes"))),
+            Err(ContractError::InvalidIbcTarget {
+                reason: "forward timeout must be a duration such as 10m".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_query_supported_actions() {
+        let deps = _instantiate_deps();
+
+        let actions = from_json::<Vec<String>>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::SupportedActions {}).unwrap(),
+        )
+        .unwrap();
+
+        for action in [
+            "update_config",
+            "update_distribute_target",
+            "update_executors",
+            "distribute",
+            "collect_fees",
+        ] {
+            assert!(actions.contains(&action.to_string()), "missing {}", action);
+        }
+    }
+
+    #[test]
+    fn test_execute_update_config() {
+        let mut deps = _instantiate_deps();
+
+        let msg = ExecuteMsg::UpdateConfig {
+            distribute_token: Some(Addr::unchecked("new_distribute_token")),
+        };
+
+        // act
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let raw_config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+        let config = from_json::<ConfigResponse>(&raw_config).unwrap();
+
+        // assert
+        assert_eq!(
+            config.0.distribute_token,
+            Addr::unchecked("new_distribute_token")
+        );
+    }
+
+    #[test]
+    fn test_config_history_newest_first() {
+        let mut deps = _instantiate_deps();
+        let mut env = mock_env();
+
+        for token in ["token_a", "token_b"] {
+            env.block.time = env.block.time.plus_seconds(10);
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("owner", &[]),
+                ExecuteMsg::UpdateConfig {
+                    distribute_token: Some(Addr::unchecked(token)),
+                },
+            )
+            .unwrap();
+        }
+
+        let history = from_json::<Vec<ConfigHistoryEntry>>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::ConfigHistory { limit: None },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|entry| (entry.config.distribute_token.as_str(), entry.updated_at))
+                .collect::<Vec<_>>(),
+            vec![
+                ("token_b", mock_env().block.time.plus_seconds(20)),
+                ("token_a", mock_env().block.time.plus_seconds(10)),
+            ]
+        );
+
+        let history = from_json::<Vec<ConfigHistoryEntry>>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::ConfigHistory { limit: Some(1) },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(history.len(), 1);
+    }
+
+    fn _query_ownership(deps: Deps) -> cw_ownable::Ownership<Addr> {
+        from_json(&query(deps, mock_env(), QueryMsg::Ownership {}).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_ownership_transfer_and_accept() {
+        let mut deps = _instantiate_deps();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateOwnership(cw_ownable::Action::TransferOwnership {
+                new_owner: "new_owner".to_string(),
+                expiry: None,
+            }),
+        )
+        .unwrap();
+
+        // the offer is pending, the current owner keeps control until it is accepted
+        let ownership = _query_ownership(deps.as_ref());
+        assert_eq!(ownership.owner, Some(Addr::unchecked("owner")));
+        assert_eq!(ownership.pending_owner, Some(Addr::unchecked("new_owner")));
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("not_owner", &[]),
+            ExecuteMsg::UpdateOwnership(cw_ownable::Action::AcceptOwnership),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Ownership(OwnershipError::NotPendingOwner)
+        );
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_owner", &[]),
+            ExecuteMsg::UpdateOwnership(cw_ownable::Action::AcceptOwnership),
+        )
+        .unwrap();
+
+        let ownership = _query_ownership(deps.as_ref());
+        assert_eq!(ownership.owner, Some(Addr::unchecked("new_owner")));
+        assert_eq!(ownership.pending_owner, None);
+
+        // owner-only actions follow the new owner
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateConfig {
+                distribute_token: None,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_owner", &[]),
+            ExecuteMsg::UpdateConfig {
+                distribute_token: None,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_ownership_transfer_paused_with_admin() {
+        let mut deps = _instantiate_deps();
+        PAUSE_FLAGS
+            .save(
+                deps.as_mut().storage,
+                &PauseFlags {
+                    distribute: false,
+                    collect: false,
+                    admin: true,
+                },
+            )
+            .unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateOwnership(cw_ownable::Action::TransferOwnership {
+                new_owner: "new_owner".to_string(),
+                expiry: None,
+            }),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Paused {
+                operation: "admin".to_string(),
+            }
+        );
+        assert_eq!(_query_ownership(deps.as_ref()).pending_owner, None);
+    }
+
+    #[test]
+    fn test_ownership_renounce() {
+        let mut deps = _instantiate_deps();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateOwnership(cw_ownable::Action::RenounceOwnership),
+        )
+        .unwrap();
+
+        assert_eq!(_query_ownership(deps.as_ref()).owner, None);
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateDistributeTarget {
+                distribute_targets: vec![],
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn test_execute_update_distribute_targets() {
+        let mut deps = _instantiate_deps();
+
+        let init_distribution_targets = vec![
+            DistributeTarget {
+                msg_hook: Some(to_json_binary(&"hook1").unwrap()),
+                ..target("target3", 40)
+            },
+            target("target4", 60),
+        ];
+
+        // act
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateDistributeTarget {
+                distribute_targets: init_distribution_targets.clone(),
+            },
+        )
+        .unwrap();
+
+        let raw_distribute_targets =
+            query(deps.as_ref(), mock_env(), QueryMsg::DistributeTargets {}).unwrap();
+
+        let distribute_targets = from_json::<DistributeTargetsResponse>(&raw_distribute_targets)
+            .unwrap()
+            .0;
+
+        // assert
+        assert_eq!(distribute_targets[0], init_distribution_targets[0]);
+        assert_eq!(distribute_targets[1], init_distribution_targets[1]);
+    }
+
+    #[test]
+    fn test_execute_update_distribute_targets_hook_too_large() {
+        let mut deps = _instantiate_deps();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateDistributeTarget {
+                distribute_targets: vec![DistributeTarget {
+                    msg_hook: Some(Binary::from(vec![0u8; MAX_HOOK_SIZE + 1])),
+                    ..target("target3", 100)
+                }],
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            ContractError::HookTooLarge {
+                size: MAX_HOOK_SIZE + 1,
+                max: MAX_HOOK_SIZE
+            }
+        );
+
+        // the stored targets are left untouched
+        let distribute_targets = from_json::<DistributeTargetsResponse>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::DistributeTargets {}).unwrap(),
+        )
+        .unwrap()
+        .0;
+        assert_eq!(distribute_targets[0].addr, Addr::unchecked("target1"));
+    }
+
+    #[test]
+    fn test_execute_update_token_distribute_targets_hook_on_native() {
+        let mut deps = _instantiate_deps();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateTokenDistributeTargets {
+                token: AssetInfo::NativeToken {
+                    denom: "orai".to_string(),
+                },
+                distribute_targets: vec![DistributeTarget {
+                    msg_hook: Some(to_json_binary(&"hook1").unwrap()),
+                    ..target("target1", 100)
+                }],
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::HooksRequireCw20Token {
+                addr: "target1".to_string(),
+            }
+        );
+
+        // hooks go out with a cw20 Send, native denoms can not carry them
+        let hooks_supported = |token: AssetInfo| -> bool {
+            from_json(
+                query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::HooksSupported { token },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+        assert!(!hooks_supported(AssetInfo::NativeToken {
+            denom: "orai".to_string(),
+        }));
+        assert!(hooks_supported(AssetInfo::Token {
+            contract_addr: Addr::unchecked("distribute_token"),
+        }));
+    }
+
+    #[test]
+    fn test_execute_update_distribute_targets_share_too_high() {
+        let mut deps = _instantiate_deps();
+
+        let update_max_target_share = |max_bps: u32| ExecuteMsg::UpdateMaxTargetShare {
+            max_target_share_bps: Some(max_bps),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            update_max_target_share(0),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidMaxTargetShare { max_bps: 0 });
+
+        // target2 already holds 60% of the weight
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            update_max_target_share(5000),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TargetShareTooHigh {
+                addr: "target2".to_string(),
+                share_bps: 6000,
+                max_bps: 5000
+            }
+        );
 
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            update_max_target_share(6000),
+        )
+        .unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateDistributeTarget {
+                distribute_targets: vec![target("target3", 90), target("target4", 10)],
+            },
+        )
+        .unwrap_err();
         assert_eq!(
-            config,
-            ConfigResponse(Config {
-                owner: Addr::unchecked("owner"),
-                distribute_token: Addr::unchecked("distribute_token"),
-                router: Some(Addr::unchecked("router")),
-            })
+            err,
+            ContractError::TargetShareTooHigh {
+                addr: "target3".to_string(),
+                share_bps: 9000,
+                max_bps: 6000
+            }
         );
 
-        let distribute_targets_binary =
-            query(deps.as_ref(), mock_env(), QueryMsg::DistributeTargets {}).unwrap();
+        let max_target_share = from_json::<Option<u32>>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::MaxTargetShare {}).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(max_target_share, Some(6000));
+    }
 
-        let distribute_targets =
-            from_json::<DistributeTargetsResponse>(&distribute_targets_binary).unwrap();
+    #[test]
+    fn test_query_next_payout() {
+        let mut deps = _instantiate_deps();
+        let query_next_payout = |deps: Deps, addr: &str| {
+            from_json::<Uint128>(
+                &query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::NextPayout {
+                        addr: Addr::unchecked(addr),
+                        assumed_amount: Uint128::from(1000u128),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
 
         assert_eq!(
-            distribute_targets,
-            DistributeTargetsResponse(init_distribution_targets)
+            query_next_payout(deps.as_ref(), "target1"),
+            Uint128::from(400u128)
         );
+        assert_eq!(query_next_payout(deps.as_ref(), "unknown"), Uint128::zero());
 
-        // send token
-
-        deps
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::RemoveDistributeTarget {
+                addr: Addr::unchecked("target1"),
+            },
+        )
+        .unwrap();
+        assert_eq!(query_next_payout(deps.as_ref(), "target1"), Uint128::zero());
     }
 
     #[test]
-    fn test_instantiate() {
-        let _deps = _instantiate_deps();
+    fn test_query_zero_share_targets() {
+        let mut deps = _instantiate_deps();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateDistributeTarget {
+                distribute_targets: vec![target("target1", 95), target("target2", 5)],
+            },
+        )
+        .unwrap();
+
+        let zero_share_targets = |deps: Deps, amount: u128| {
+            from_json::<Vec<Addr>>(
+                &query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::ZeroShareTargets {
+                        amount: Uint128::from(amount),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+
+        // 5% of 10 rounds down to nothing, of 20 it is one unit
+        assert_eq!(
+            zero_share_targets(deps.as_ref(), 10),
+            vec![Addr::unchecked("target2")]
+        );
+        assert!(zero_share_targets(deps.as_ref(), 20).is_empty());
     }
 
     #[test]
-    fn test_load_target_messages() {
+    fn test_distribution_plan_sweeps_residue_to_remainder() {
         let mut deps = _instantiate_deps();
+        let amount = Uint128::from(999u128);
+        let query_plan = |deps: Deps| {
+            from_json::<EffectiveDistributionResponse>(
+                &query(deps, mock_env(), QueryMsg::EffectiveDistribution { amount }).unwrap(),
+            )
+            .unwrap()
+        };
+        let total_outflow = |plan: &EffectiveDistributionResponse| {
+            plan.shares
+                .iter()
+                .map(|share| share.amount)
+                .sum::<Uint128>()
+                + plan.undistributed
+        };
 
-        let amount_distribute = Uint128::from(1000u128);
-        let distribute_token = Addr::unchecked("distribute_token");
-
-        let messages =
-            _load_target_messages(&mut deps.storage, amount_distribute, distribute_token).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateRemainderTarget {
+                remainder_target: Some(Addr::unchecked("remainder")),
+            },
+        )
+        .unwrap();
 
+        // 399.6 and 599.4 round down, the lost unit goes to the remainder target
+        let plan = query_plan(deps.as_ref());
         assert_eq!(
-            messages,
+            plan.shares,
             vec![
-                WasmMsg::Execute {
-                    contract_addr: "distribute_token".into(),
-                    msg: to_json_binary(&Cw20ExecuteMsg::Send {
-                        contract: "target1".into(),
-                        amount: Uint128::from(400u64),
-                        msg: to_json_binary(&"hook1").unwrap()
-                    })
-                    .unwrap(),
-                    funds: vec![]
+                DistributionShare {
+                    addr: Addr::unchecked("target1"),
+                    amount: Uint128::from(399u128),
+                },
+                DistributionShare {
+                    addr: Addr::unchecked("target2"),
+                    amount: Uint128::from(599u128),
+                },
+                DistributionShare {
+                    addr: Addr::unchecked("remainder"),
+                    amount: Uint128::one(),
                 },
-                WasmMsg::Execute {
-                    contract_addr: "distribute_token".into(),
-                    msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-                        recipient: "target2".into(),
-                        amount: Uint128::from(600u64)
-                    })
-                    .unwrap(),
-                    funds: vec![]
-                }
             ]
+        );
+        assert_eq!(plan.undistributed, Uint128::zero());
+        assert_eq!(total_outflow(&plan), amount);
+
+        // with no grace period the removal applies right away
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::RemoveDistributeTarget {
+                addr: Addr::unchecked("target1"),
+            },
         )
+        .unwrap();
+
+        let plan = query_plan(deps.as_ref());
+        assert_eq!(
+            plan.shares,
+            vec![DistributionShare {
+                addr: Addr::unchecked("target2"),
+                amount: Uint128::from(599u128),
+            }]
+        );
+        assert_eq!(plan.undistributed, Uint128::from(400u128));
+        assert_eq!(total_outflow(&plan), amount);
     }
 
     #[test]
-    fn test_execute_update_config() {
+    fn test_execute_update_weights() {
         let mut deps = _instantiate_deps();
 
-        let msg = ExecuteMsg::UpdateConfig {
-            owner: Some(Addr::unchecked("new_owner")),
-            distribute_token: Some(Addr::unchecked("new_distribute_token")),
-        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateWeights {
+                updates: vec![(Addr::unchecked("target1"), 30)],
+            },
+        )
+        .unwrap();
 
-        // act
-        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let distribute_targets = from_json::<DistributeTargetsResponse>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::DistributeTargets {}).unwrap(),
+        )
+        .unwrap()
+        .0;
+        assert_eq!(distribute_targets[0].weight, 30);
+        // the other target is left unchanged
+        assert_eq!(distribute_targets[1], target("target2", 60));
 
-        let raw_config = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
-        let config = from_json::<ConfigResponse>(&raw_config).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateWeights {
+                updates: vec![(Addr::unchecked("unknown"), 10)],
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TargetNotFound {
+                addr: "unknown".to_string()
+            }
+        );
 
-        // assert
-        assert_eq!(config.0.owner, Addr::unchecked("new_owner"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateWeights {
+                updates: vec![(Addr::unchecked("target1"), 50)],
+            },
+        )
+        .unwrap_err();
         assert_eq!(
-            config.0.distribute_token,
-            Addr::unchecked("new_distribute_token")
+            err,
+            ContractError::InvalidTotalWeight {
+                total: 110,
+                max: MAX_TOTAL_WEIGHT
+            }
         );
+
+        // a removed target keeps its place and frees its weight
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::RemoveDistributeTarget {
+                addr: Addr::unchecked("target1"),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateWeights {
+                updates: vec![(Addr::unchecked("target2"), 100)],
+            },
+        )
+        .unwrap();
+        let distribute_targets = from_json::<DistributeTargetsResponse>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::DistributeTargets {}).unwrap(),
+        )
+        .unwrap()
+        .0;
+        assert_eq!(distribute_targets[0].addr, Addr::unchecked("target1"));
+        assert_eq!(distribute_targets[1], target("target2", 100));
     }
 
     #[test]
-    fn test_execute_update_distribute_targets() {
+    fn test_target_kind_restriction_contracts_only() {
         let mut deps = _instantiate_deps();
 
-        let init_distribution_targets = vec![
-            DistributeTarget {
-                weight: 40,
-                addr: Addr::unchecked("target3"),
-                msg_hook: Some(to_json_binary(&"hook1").unwrap()),
-            },
-            DistributeTarget {
-                weight: 60,
-                addr: Addr::unchecked("target4"),
-                msg_hook: None,
+        // the instantiated targets are wallets, so they block the restriction
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateTargetKindRestriction {
+                restriction: TargetKindRestriction::ContractsOnly,
             },
-        ];
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TargetKindMismatch {
+                addr: "target1".to_string(),
+                expected: "contract".to_string()
+            }
+        );
 
-        // act
         execute(
             deps.as_mut(),
             mock_env(),
             mock_info("owner", &[]),
             ExecuteMsg::UpdateDistributeTarget {
-                distribute_targets: init_distribution_targets.clone(),
+                distribute_targets: vec![],
             },
         )
         .unwrap();
 
-        let raw_distribute_targets =
-            query(deps.as_ref(), mock_env(), QueryMsg::DistributeTargets {}).unwrap();
+        // a per-token list blocks it just the same
+        let token_targets =
+            |distribute_targets: Vec<DistributeTarget>| ExecuteMsg::UpdateTokenDistributeTargets {
+                token: AssetInfo::Token {
+                    contract_addr: Addr::unchecked("token_x"),
+                },
+                distribute_targets,
+            };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            token_targets(vec![target("wallet", 100)]),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateTargetKindRestriction {
+                restriction: TargetKindRestriction::ContractsOnly,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TargetKindMismatch {
+                addr: "wallet".to_string(),
+                expected: "contract".to_string()
+            }
+        );
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            token_targets(vec![]),
+        )
+        .unwrap();
 
-        let distribute_targets = from_json::<DistributeTargetsResponse>(&raw_distribute_targets)
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateTargetKindRestriction {
+                restriction: TargetKindRestriction::ContractsOnly,
+            },
+        )
+        .unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateDistributeTarget {
+                distribute_targets: vec![target("wallet", 100)],
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::TargetKindMismatch {
+                addr: "wallet".to_string(),
+                expected: "contract".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_can_collect_for_registered_grant() {
+        let mut deps = _instantiate_deps();
+        let can_collect_for = |deps: Deps, env: Env| {
+            from_json::<bool>(
+                &query(
+                    deps,
+                    env,
+                    QueryMsg::CanCollectFor {
+                        granter: "approver".to_string(),
+                    },
+                )
+                .unwrap(),
+            )
             .unwrap()
-            .0;
+        };
+        assert!(!can_collect_for(deps.as_ref(), mock_env()));
 
-        // assert
-        assert_eq!(distribute_targets[0], init_distribution_targets[0]);
-        assert_eq!(distribute_targets[1], init_distribution_targets[1]);
+        let env = mock_env();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::RegisterGrant {
+                granter: "approver".to_string(),
+                grant: Some(CollectGrant {
+                    expiration: Some(env.block.time.plus_seconds(100)),
+                }),
+            },
+        )
+        .unwrap();
+        assert!(can_collect_for(deps.as_ref(), env.clone()));
+
+        let mut expired = env;
+        expired.block.time = expired.block.time.plus_seconds(100);
+        assert!(!can_collect_for(deps.as_ref(), expired));
     }
 
     #[test]
@@ -614,6 +5868,8 @@ mod tests {
             mock_info("not_owner", &[]),
             ExecuteMsg::Distribute {
                 amount_distribute: Uint128::from(1000u128),
+                token: None,
+                nonce: None,
             },
         )
         .unwrap_err();
@@ -623,7 +5879,6 @@ mod tests {
             mock_env(),
             mock_info("not_owner", &[]),
             ExecuteMsg::UpdateConfig {
-                owner: None,
                 distribute_token: None,
             },
         )
@@ -650,4 +5905,40 @@ mod tests {
             ContractError::Unauthorized {}
         );
     }
+
+    #[test]
+    fn test_distribution_mode_defaults_to_pull() {
+        let mut deps = _instantiate_deps();
+        DISTRIBUTION_MODE.remove(deps.as_mut().storage);
+
+        // contracts migrated from before the Pull default keep pushing
+        migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                new_router: Addr::unchecked("router"),
+                import: None,
+            },
+        )
+        .unwrap();
+        let mode: DistributionMode =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::DistributionMode {}).unwrap())
+                .unwrap();
+        assert_eq!(mode, DistributionMode::Push);
+
+        // a fresh instance credits its distributions for claiming
+        let mut fresh = mock_dependencies();
+        let msg = InstantiateMsg {
+            owner: Addr::unchecked("owner"),
+            distribute_token: Addr::unchecked("distribute_token"),
+            init_distribution_targets: DISTRIBUTION_TARGETS.load(deps.as_ref().storage).unwrap(),
+            router: Some(Addr::unchecked("router")),
+            executors: vec![],
+        };
+        instantiate(fresh.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        let mode: DistributionMode =
+            from_json(query(fresh.as_ref(), mock_env(), QueryMsg::DistributionMode {}).unwrap())
+                .unwrap();
+        assert_eq!(mode, DistributionMode::Pull);
+    }
 }
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError, Timestamp, Uint128};
+use cw_ownable::OwnershipError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,6 +7,9 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Ownership(#[from] OwnershipError),
+
     #[error("Unauthorized")]
     Unauthorized {},
     #[error("Exceeds the contract balance")]
@@ -14,4 +18,127 @@ pub enum ContractError {
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
     #[error("Router and approver are not set")]
     RouterAndApproverNotSet {},
+    #[error("Operation {operation} is paused")]
+    Paused { operation: String },
+    #[error("Weight registry is not set")]
+    RegistryNotSet {},
+    #[error("Weight registry returned no positive weight for the distribute targets")]
+    InvalidRegistryWeights {},
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+    #[error("Target {addr} must be a {expected}")]
+    TargetKindMismatch { addr: String, expected: String },
+    #[error("Distribute target {addr} not found")]
+    TargetNotFound { addr: String },
+    #[error("Total weight {total} of the distribute targets exceeds {max}")]
+    InvalidTotalWeight { total: u64, max: u64 },
+    #[error("Allowance {allowance} does not cover the amount {amount}")]
+    InsufficientAllowance { allowance: Uint128, amount: Uint128 },
+    #[error("No collect fee requirements provided")]
+    NoRequirements {},
+    #[error("Snapshot height {height} must be in the past")]
+    InvalidSnapshotHeight { height: u64 },
+    #[error("Holders have no voting power at height {height}")]
+    NoVotingPower { height: u64 },
+    #[error("A previous distribution is still pending, continue it first")]
+    DistributionInProgress {},
+    #[error("No pending distribution to continue")]
+    NoPendingDistribution {},
+    #[error("Target {addr} has a hook, hooks are only sent with a cw20 token")]
+    HooksRequireCw20Token { addr: String },
+    #[error("Total offer of {offer_amount} exceeds the guard of {max_total_offer}")]
+    OfferExceedsGuard {
+        offer_amount: Uint128,
+        max_total_offer: Uint128,
+    },
+    #[error("No failed hook recorded for {addr} in {token}")]
+    NoFailedHook { addr: String, token: String },
+    #[error("Hook can not be retried before height {height}")]
+    RetryTooEarly { height: u64 },
+    #[error("Target ratios must sum to 1")]
+    InvalidTargetRatios {},
+    #[error("Router can not price {asset}")]
+    UnpricedAsset { asset: String },
+    #[error("Next distribution is available at {available_at}")]
+    DistributionThrottled { available_at: Timestamp },
+    #[error("A previous collection is still pending, continue it first")]
+    CollectInProgress {},
+    #[error("No pending collection to continue")]
+    NoPendingCollect {},
+    #[error("Oracle price was last updated at {updated_at}")]
+    StaleOracle { updated_at: Timestamp },
+    #[error("Target {addr} can not have both a preferred asset and a hook")]
+    PreferredAssetWithHook { addr: String },
+    #[error("Message {index} is not in the execute allowlist")]
+    MsgNotAllowed { index: u32 },
+    #[error("Vault is not set")]
+    VaultNotSet {},
+    #[error("Deposit must be a non zero amount of {denom} only")]
+    InvalidDeposit { denom: String },
+    #[error("Vault amount rounds down to zero")]
+    ZeroVaultAmount {},
+    #[error("Vault shares are outstanding, the vault can not change")]
+    VaultInUse {},
+    #[error("Target {addr} share of {share_bps} bps exceeds the cap of {max_bps} bps")]
+    TargetShareTooHigh {
+        addr: String,
+        share_bps: u64,
+        max_bps: u32,
+    },
+    #[error("Max target share of {max_bps} bps must be between 1 and 10000")]
+    InvalidMaxTargetShare { max_bps: u32 },
+    #[error("Burn ratio {burn_ratio} exceeds 1")]
+    InvalidBurnRatio { burn_ratio: Decimal },
+    #[error("Nothing to claim for {addr}")]
+    NothingToClaim { addr: String },
+    #[error("Claim expiry is not set")]
+    ClaimExpiryNotSet {},
+    #[error("Shares of {addr} can not be reclaimed before {expires_at}")]
+    ClaimNotExpired { addr: String, expires_at: Timestamp },
+    #[error("Sunset recipient is not set")]
+    SunsetRecipientNotSet {},
+    #[error("Contract was sunset at {sunset_at}")]
+    Sunset { sunset_at: Timestamp },
+    #[error("Emergency withdrawals are only allowed once the contract is sunset")]
+    NotSunset {},
+    #[error("Distribution nonce mismatch, expected {expected}")]
+    NonceMismatch { expected: u64 },
+    #[error("Target {addr} has a USD target, its weight must be 0")]
+    UsdTargetWithWeight { addr: String },
+    #[error("No oracle price of {denom} to value the USD targets with")]
+    UsdTargetUnpriced { denom: String },
+    #[error("Epoch length must be greater than 0")]
+    InvalidEpochLength {},
+    #[error("Epoch {epoch} already had its distribution")]
+    EpochAlreadyDistributed { epoch: u64 },
+    #[error("Validator weights must be non-zero and list each validator once")]
+    InvalidValidatorWeights {},
+    #[error("{validator} is not a validator")]
+    UnknownValidator { validator: String },
+    #[error("Allocation ratios must be non-zero, list each asset once and sum to 1")]
+    InvalidAllocation {},
+    #[error("Max assets per collect must be greater than 0")]
+    InvalidMaxAssets {},
+    #[error("Max hooks per distribution must be greater than 0")]
+    InvalidMaxHooks {},
+    #[error("No router TWAP of {denom} to normalize the distribution with")]
+    NormalizationUnpriced { denom: String },
+    #[error("Snapshot of version {version} can not be imported by version {expected}")]
+    IncompatibleSnapshot { version: String, expected: String },
+    #[error("Imported state reserves {reserved} {asset} but the contract holds {balance}")]
+    ReservesNotHeld {
+        asset: String,
+        reserved: Uint128,
+        balance: Uint128,
+    },
+    #[error("Holder {addr} is listed more than once")]
+    DuplicateHolder { addr: String },
+    #[error("Target {addr} is paid over IBC, it can not also have a hook or a preferred asset")]
+    IbcTargetConflict { addr: String },
+    #[error("Target {addr} is paid over IBC, only native tokens can be transferred to it")]
+    IbcTargetRequiresNativeToken { addr: String },
+    #[error("Invalid IBC target: {reason}")]
+    InvalidIbcTarget { reason: String },
+    #[error("Hook message size {size} exceeds the maximum of {max} bytes")]
+    HookTooLarge { size: usize, max: usize },
 }
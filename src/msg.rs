@@ -1,8 +1,15 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, CosmosMsg, Decimal, Timestamp, Uint128};
+use oraiswap::asset::{Asset, AssetInfo};
 use oraiswap::mixed_router::SwapOperation;
+use std::collections::BTreeMap;
 
-use crate::state::{Config, DistributeTarget};
+use crate::state::{
+    AllowedMsg, CollectGrant, Config, ConfigHistoryEntry, DistributeTarget,
+    DistributionHistoryEntry, DistributionMode, EpochConfig, GenesisConfig, HookAttempt,
+    HookRetryConfig, OracleConfig, PauseFlags, PendingDistribution, PendingRemoval,
+    TargetKindRestriction, VaultConfig,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -18,19 +25,197 @@ pub enum ExecuteMsg {
     /////////////////
     /// Owner API ///
     ////////////////
+    UpdateOwnership(cw_ownable::Action),
     UpdateConfig {
-        owner: Option<Addr>,
         distribute_token: Option<Addr>,
     },
     UpdateDistributeTarget {
         distribute_targets: Vec<DistributeTarget>,
     },
+    /// Set the target list used when distributing `token`, an empty list makes
+    /// the token fall back to the default distribute targets
+    UpdateTokenDistributeTargets {
+        token: AssetInfo,
+        distribute_targets: Vec<DistributeTarget>,
+    },
     UpdateExecutors {
         executors: Vec<Addr>,
         permission: bool,
     },
+    /// Split `amount_distribute` of `token`, the configured distribute token
+    /// when omitted, among the distribute targets. Rejected when `nonce` is set
+    /// and differs from the current distribution nonce
     Distribute {
         amount_distribute: Uint128,
+        token: Option<AssetInfo>,
+        nonce: Option<u64>,
+    },
+    SetPauseFlags {
+        pause_flags: PauseFlags,
+    },
+    /// Set the weight of existing targets, leaving the others unchanged
+    UpdateWeights {
+        updates: Vec<(Addr, u32)>,
+    },
+    UpdateTargetKindRestriction {
+        restriction: TargetKindRestriction,
+    },
+    /// Pull `amount` of `token` from `owner` through the allowance granted to the
+    /// contract and split it among the distribute targets
+    DistributeFromAllowance {
+        token: Addr,
+        owner: Addr,
+        amount: Uint128,
+    },
+    /// Split `amount` among `holders` pro-rata to their `token` balance at `height`,
+    /// `token` must be a cw20 keeping balance snapshots
+    DistributeByVotingPower {
+        token: Addr,
+        height: u64,
+        holders: Vec<Addr>,
+        amount: Uint128,
+    },
+    UpdateWeightRegistry {
+        registry: Option<Addr>,
+    },
+    /// Split `amount` among the distribute targets using the weights currently
+    /// reported by the weight registry instead of the stored ones
+    DistributeFromRegistry {
+        amount: Uint128,
+    },
+    /// Cap how many hook-bearing targets fire in one distribution, the rest is
+    /// left for `ContinueDistribution`. `None` removes the cap
+    UpdateMaxHooksPerDistribution {
+        max_hooks: Option<u32>,
+    },
+    /// Split `amount` of distribute token value by weight, targets preferring another
+    /// asset are paid their share out of that asset's holdings at the router TWAP.
+    /// Checked against `nonce`, the throttle and the epoch like `Distribute`. In Pull
+    /// mode the shares are credited in the asset they would have been paid in
+    DistributeNormalized {
+        amount: Uint128,
+        nonce: Option<u64>,
+    },
+    /// Swap `amount` of distribute token into the `allocation` basket at the router
+    /// quote, then split each asset received among its distribute targets.
+    /// Checked against `nonce`, the throttle and the epoch like `Distribute`. In Pull
+    /// mode the quoted shares are credited
+    DistributeDiversified {
+        amount: Uint128,
+        allocation: Vec<AllocationEntry>,
+        nonce: Option<u64>,
+    },
+    /// Delegate `amount` of the bonded denom, split among `validators` by weight
+    DistributeAsStake {
+        amount: Uint128,
+        validators: Vec<ValidatorWeight>,
+    },
+    /// Fire the next batch of hook-bearing payouts of a capped distribution
+    ContinueDistribution {},
+    UpdateRemovalGrace {
+        removal_grace_seconds: u64,
+    },
+    /// Stop paying `addr` once the removal grace period has elapsed
+    RemoveDistributeTarget {
+        addr: Addr,
+    },
+    /// Minimum seconds between two `Distribute` calls, 0 disables the throttle
+    UpdateDistributionInterval {
+        distribution_interval: u64,
+    },
+    UpdateEpoch {
+        epoch: Option<EpochConfig>,
+    },
+    /// Emit a single `distribute-summary` event carrying every payout as JSON, off by
+    /// default where distributions only carry their action attributes
+    UpdateAggregateEvents {
+        aggregate_events: bool,
+    },
+    /// Prune distribution history entries older than `history_max_age_seconds` on
+    /// the next distribution, `None` only bounds the history by size
+    UpdateHistoryMaxAge {
+        history_max_age_seconds: Option<u64>,
+    },
+    /// Minimum seconds before a collection swaps the same asset again, 0 disables it
+    UpdateCollectCooldown {
+        collect_cooldown_seconds: u64,
+    },
+    /// Record the authz grant `granter` gave for collecting its native fees,
+    /// `None` removes it
+    RegisterGrant {
+        granter: String,
+        grant: Option<CollectGrant>,
+    },
+    /// Cap how many requirements a `CollectFees` call processes, the rest is
+    /// left for `ContinueCollect`. `None` removes the cap
+    UpdateMaxAssetsPerCollect {
+        max_assets: Option<u32>,
+    },
+    /// Reject distributions and collections while the oracle price is stale
+    UpdateOracle {
+        oracle: Option<OracleConfig>,
+    },
+    UpdateHookRetry {
+        hook_retry: Option<HookRetryConfig>,
+    },
+    /// Send the payout of a failed hook to `target` again, or to the fallback
+    /// once the retries are exhausted. Without a hook retry config it is only sent
+    /// again. `token` defaults to the distribute token
+    RetryHook {
+        target: Addr,
+        token: Option<Addr>,
+    },
+    /// Swap held assets through the router toward `target_ratios` of their summed
+    /// value in `quote_denom`, assets within `tolerance` of their ratio are left alone.
+    /// Each swap fails when it settles more than `max_slippage` below its quote
+    Rebalance {
+        quote_denom: String,
+        target_ratios: Vec<TargetRatio>,
+        tolerance: Decimal,
+        max_slippage: Decimal,
+    },
+    /// Account receiving the rounding residue of the weighted split
+    UpdateRemainderTarget {
+        remainder_target: Option<Addr>,
+    },
+    UpdateExecuteAllowlist {
+        allowlist: Vec<AllowedMsg>,
+    },
+    /// Dispatch `msgs` from the treasury, each one must match the execute allowlist
+    /// and together they can not spend the balances held for others
+    Execute {
+        msgs: Vec<CosmosMsg>,
+    },
+    UpdateVault {
+        vault: Option<VaultConfig>,
+    },
+    UpdateDistributionMode {
+        mode: DistributionMode,
+    },
+    /// Seconds after its last credit a target's unclaimed shares can be reclaimed,
+    /// `None` keeps them claimable for good
+    UpdateClaimExpiry {
+        claim_expiry_seconds: Option<u64>,
+    },
+    /// Release every share credited to `addr` back to the distributable balance once
+    /// the claim expiry has passed since it was last credited
+    ReclaimUnclaimed {
+        addr: Addr,
+    },
+    UpdateSunsetRecipient {
+        recipient: Option<Addr>,
+    },
+    /// Permanently pause every operation, only `EmergencyWithdraw` to the sunset
+    /// recipient is left. Can not be undone
+    Sunset {},
+    /// Send the whole contract balance of `assets` to the sunset recipient
+    EmergencyWithdraw {
+        assets: Vec<AssetInfo>,
+    },
+    /// Cap the share of the total weight a single target may hold, checked on
+    /// every target update. `None` removes the cap
+    UpdateMaxTargetShare {
+        max_target_share_bps: Option<u32>,
     },
     /////////////////
     ///Executors////
@@ -38,24 +223,254 @@ pub enum ExecuteMsg {
     CollectFees {
         collect_fee_requirements: Vec<CollectFeeRequirement>,
     },
+    /// Same as `CollectFees` but reverts before any swap when the offer amounts of
+    /// all collected requirements sum to more than `max_total_offer`
+    CollectFeesGuarded {
+        collect_fee_requirements: Vec<CollectFeeRequirement>,
+        max_total_offer: Uint128,
+    },
+    /// Process the next batch of requirements of a capped collection, under what
+    /// is left of the `max_total_offer` guard of the call that started it
+    ContinueCollect {},
+    /// Collect like `CollectFees`, then burn `burn_ratio` of the distribute token
+    /// received and split the rest among the distribute targets as `Distribute`
+    /// does, honouring the distribution mode and the hook cap. Requirements left
+    /// over by the per call cap are continued as a plain collection. Checked against
+    /// `nonce`, the throttle and the epoch like `Distribute`
+    CollectBurnDistribute {
+        collect_fee_requirements: Vec<CollectFeeRequirement>,
+        burn_ratio: Decimal,
+        nonce: Option<u64>,
+    },
+    /////////////////
+    ///Targets//////
+    ///////////////
+    /// Withdraw every share credited to the sender in Pull mode
+    Claim {},
+    /////////////////
+    ///Depositors////
+    ///////////////
+    /// Mint receipt shares for the vault denom sent along
+    Deposit {},
+    /// Burn `shares` of the sender, who must have approved them to the contract,
+    /// for their pro-rata part of the vault denom deposited
+    Redeem {
+        shares: Uint128,
+    },
 }
 
+/// Execute actions supported by this version, keep in sync with `ExecuteMsg`
+pub const SUPPORTED_ACTIONS: &[&str] = &[
+    "update_ownership",
+    "update_config",
+    "update_distribute_target",
+    "update_token_distribute_targets",
+    "update_executors",
+    "distribute",
+    "set_pause_flags",
+    "update_weights",
+    "update_target_kind_restriction",
+    "distribute_from_allowance",
+    "distribute_by_voting_power",
+    "update_weight_registry",
+    "distribute_from_registry",
+    "update_max_hooks_per_distribution",
+    "distribute_normalized",
+    "distribute_diversified",
+    "distribute_as_stake",
+    "continue_distribution",
+    "update_removal_grace",
+    "remove_distribute_target",
+    "update_distribution_interval",
+    "update_epoch",
+    "update_aggregate_events",
+    "update_history_max_age",
+    "update_collect_cooldown",
+    "register_grant",
+    "update_max_assets_per_collect",
+    "update_oracle",
+    "update_hook_retry",
+    "retry_hook",
+    "rebalance",
+    "update_remainder_target",
+    "update_execute_allowlist",
+    "execute",
+    "update_vault",
+    "update_max_target_share",
+    "update_distribution_mode",
+    "update_claim_expiry",
+    "reclaim_unclaimed",
+    "update_sunset_recipient",
+    "sunset",
+    "emergency_withdraw",
+    "collect_fees",
+    "collect_fees_guarded",
+    "continue_collect",
+    "collect_burn_distribute",
+    "claim",
+    "deposit",
+    "redeem",
+];
+
 #[cw_serde]
 pub struct CollectFeeRequirement {
     pub approver: Addr,
     pub swap_operations: Vec<SwapOperation>,
     pub minimum_receive: Option<Uint128>,
+    /// skip the swap while the router quotes less distribute token per offered unit
+    pub min_price: Option<Decimal>,
+}
+
+/// Contract balances of every asset involved in a CollectFees call, keyed by
+/// denom or cw20 address, set as the response data
+#[cw_serde]
+pub struct Reconciliation {
+    pub before: BTreeMap<String, Uint128>,
+    pub after: BTreeMap<String, Uint128>,
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
+    #[returns(cw_ownable::Ownership<Addr>)]
+    Ownership {},
     #[returns(ConfigResponse)]
     Config {},
+    /// Past config updates, newest first
+    #[returns(Vec<ConfigHistoryEntry>)]
+    ConfigHistory { limit: Option<u32> },
+    /// Past distributions, newest first
+    #[returns(Vec<DistributionHistoryEntry>)]
+    DistributionHistory { limit: Option<u32> },
+    #[returns(Option<u64>)]
+    HistoryMaxAge {},
+    /// Parameters the contract was instantiated with, unset for contracts
+    /// instantiated before it was recorded
+    #[returns(Option<GenesisConfig>)]
+    GenesisConfig {},
     #[returns(DistributeTargetsResponse)]
     DistributeTargets {},
+    /// Targets receiving `token`, the default list unless a specific one is set
+    #[returns(DistributeTargetsResponse)]
+    TokenDistributeTargets { token: AssetInfo },
+    /// Whether targets receiving `token` can carry a hook, only a cw20 is sent with one
+    #[returns(bool)]
+    HooksSupported { token: AssetInfo },
     #[returns(bool)]
     IsExecutor { addr: Addr },
+    #[returns(PauseFlags)]
+    PauseFlags {},
+    #[returns(EffectiveDistributionResponse)]
+    EffectiveDistribution { amount: Uint128 },
+    #[returns(SimulateBalancesAfterResponse)]
+    SimulateBalancesAfter { amount: Uint128 },
+    /// What `addr` would receive from a distribution of `assumed_amount` made now
+    #[returns(Uint128)]
+    NextPayout { addr: Addr, assumed_amount: Uint128 },
+    /// Part of a distribution of `amount` left in the contract by the rounding down
+    /// of the weighted shares, zero while a remainder target collects it
+    #[returns(Uint128)]
+    RoundingLoss { amount: Uint128 },
+    /// Weighted targets whose share of a distribution of `amount` rounds down to zero
+    #[returns(Vec<Addr>)]
+    ZeroShareTargets { amount: Uint128 },
+    /// Whether collecting `assets` yields more distribute token than the gas
+    /// cost of `gas_estimate` at `gas_price`, quoted in the distribute token
+    #[returns(CollectionWorthwhileResponse)]
+    CollectionWorthwhile {
+        assets: Vec<CollectFeeRequirement>,
+        gas_price: Decimal,
+        gas_estimate: u64,
+    },
+    /// Outcome of running `CollectFees` with `collect_fee_requirements` then
+    /// distributing every free distribute token, at the current router quotes
+    #[returns(FullCyclePlanResponse)]
+    FullCyclePlan {
+        collect_fee_requirements: Vec<CollectFeeRequirement>,
+    },
+    /// Value of the contract holdings of `assets` in `quote_denom`, priced
+    /// through the router
+    #[returns(TvlResponse)]
+    Tvl {
+        quote_denom: String,
+        assets: Vec<TvlAsset>,
+    },
+    #[returns(Vec<String>)]
+    SupportedActions {},
+    #[returns(TargetKindRestriction)]
+    TargetKindRestriction {},
+    #[returns(Option<Addr>)]
+    WeightRegistry {},
+    #[returns(Option<u32>)]
+    MaxHooksPerDistribution {},
+    #[returns(Option<PendingDistribution>)]
+    PendingDistribution {},
+    #[returns(u64)]
+    RemovalGrace {},
+    #[returns(Vec<PendingRemoval>)]
+    PendingRemovals {},
+    #[returns(Option<Addr>)]
+    RemainderTarget {},
+    #[returns(NextScheduledDistributionResponse)]
+    NextScheduledDistribution {},
+    /// Unset when no epoch length is configured
+    #[returns(Option<CurrentEpochResponse>)]
+    CurrentEpoch {},
+    #[returns(bool)]
+    AggregateEvents {},
+    /// Nonce the next `Distribute` is expected to carry
+    #[returns(u64)]
+    DistributionNonce {},
+    #[returns(u64)]
+    CollectCooldown {},
+    /// Whether a grant of `granter` is registered and not expired
+    #[returns(bool)]
+    CanCollectFor { granter: String },
+    #[returns(Option<u32>)]
+    MaxAssetsPerCollect {},
+    #[returns(Vec<CollectFeeRequirement>)]
+    PendingCollect {},
+    #[returns(Option<OracleConfig>)]
+    Oracle {},
+    #[returns(Option<HookRetryConfig>)]
+    HookRetry {},
+    #[returns(Vec<HookAttempt>)]
+    FailedHooks {},
+    #[returns(Vec<AllowedMsg>)]
+    ExecuteAllowlist {},
+    #[returns(Option<VaultConfig>)]
+    Vault {},
+    #[returns(Option<u32>)]
+    MaxTargetShare {},
+    #[returns(DistributionMode)]
+    DistributionMode {},
+    #[returns(Option<Addr>)]
+    SunsetRecipient {},
+    /// Time the contract was sunset at, unset while it is live
+    #[returns(Option<Timestamp>)]
+    Sunset {},
+    /// Export of the state a new instance can be migrated from. Claimable shares and
+    /// received totals are each paged after the `start_after` target and asset, up to
+    /// `limit` entries. The next page starts after the earlier last entry of the two
+    /// lists that filled up, an entry imported twice is imported once
+    #[returns(StateSnapshot)]
+    StateSnapshot {
+        start_after: Option<(Addr, AssetInfo)>,
+        limit: Option<u32>,
+    },
+    /// Shares credited to `addr` in Pull mode and not claimed yet
+    #[returns(Vec<Asset>)]
+    Claimable { addr: Addr },
+    #[returns(Option<u64>)]
+    ClaimExpiry {},
+    /// Everything known about a distribute target, read from the list `token` is paid
+    /// to or else from the default list then the per-token ones. Unset for addresses
+    /// on none of them
+    #[returns(Option<TargetProfileResponse>)]
+    TargetProfile {
+        addr: Addr,
+        token: Option<AssetInfo>,
+    },
 }
 
 #[cw_serde]
@@ -64,7 +479,243 @@ pub struct ConfigResponse(pub Config);
 #[cw_serde]
 pub struct DistributeTargetsResponse(pub Vec<DistributeTarget>);
 
+#[cw_serde]
+pub struct DistributionShare {
+    pub addr: Addr,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct EffectiveDistributionResponse {
+    pub shares: Vec<DistributionShare>,
+    /// part of the amount that stays in the contract
+    pub undistributed: Uint128,
+}
+
+#[cw_serde]
+pub struct AccountBalance {
+    pub addr: Addr,
+    pub balance: Uint128,
+}
+
+#[cw_serde]
+pub struct SimulateBalancesAfterResponse {
+    pub contract_balance: Uint128,
+    pub targets: Vec<AccountBalance>,
+}
+
+#[cw_serde]
+pub struct CollectionWorthwhileResponse {
+    /// distribute token expected from the collection
+    pub total_output: Uint128,
+    pub gas_cost: Uint128,
+    pub worthwhile: bool,
+}
+
+#[cw_serde]
+pub struct CollectPreview {
+    pub approver: Addr,
+    /// balance pulled from the approver
+    pub offer_asset: Asset,
+    /// distribute token received for it
+    pub simulated_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct FullCyclePlanResponse {
+    /// requirements a collection would swap, skipped ones are left out
+    pub collects: Vec<CollectPreview>,
+    /// free distribute token once the collection went through
+    pub distributable: Uint128,
+    pub shares: Vec<DistributionShare>,
+    pub undistributed: Uint128,
+}
+
+#[cw_serde]
+pub struct NextScheduledDistributionResponse {
+    pub available_at: Timestamp,
+    /// distribute token balance free to be distributed
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct TargetProfileResponse {
+    pub target: DistributeTarget,
+    /// part of the total weight, in basis points
+    pub share_bps: u64,
+    /// false once its removal took effect
+    pub enabled: bool,
+    pub removal_effective_at: Option<Timestamp>,
+    /// every share paid out or credited so far, per distributed token
+    pub received: Vec<Asset>,
+    pub claimable: Vec<Asset>,
+    pub max_share_bps: Option<u32>,
+    /// variant of the JSON hook message, `ping` for `{"ping":{}}`
+    pub hook_type: Option<String>,
+    /// denom or cw20 address of the per-token list the target is on, unset for the
+    /// default list
+    pub token_list: Option<String>,
+}
+
+#[cw_serde]
+pub struct CurrentEpochResponse {
+    pub epoch: u64,
+    pub seconds_remaining: u64,
+}
+
+/// Held asset and the router operations swapping it into the quote denom
+#[cw_serde]
+pub struct TvlAsset {
+    pub info: AssetInfo,
+    pub operations: Vec<SwapOperation>,
+}
+
+/// Part of a diversified distribution swapped into `asset`, through the direct
+/// oraiswap pair unless `swap_operations` routes it
+#[cw_serde]
+pub struct AllocationEntry {
+    pub asset: AssetInfo,
+    pub ratio: Decimal,
+    pub swap_operations: Option<Vec<SwapOperation>>,
+}
+
+#[cw_serde]
+pub struct ValidatorWeight {
+    pub validator: String,
+    pub weight: u32,
+}
+
+/// Share of the rebalanced value `asset` should hold. Excess is sold through
+/// `asset.operations`, a deficit is bought from the quote through `buy_operations`
+#[cw_serde]
+pub struct TargetRatio {
+    pub asset: TvlAsset,
+    pub ratio: Decimal,
+    pub buy_operations: Vec<SwapOperation>,
+}
+
+#[cw_serde]
+pub struct AssetValue {
+    pub info: AssetInfo,
+    pub balance: Uint128,
+    pub value: Uint128,
+}
+
+#[cw_serde]
+pub struct TvlResponse {
+    pub total: Uint128,
+    pub assets: Vec<AssetValue>,
+    /// held assets the router could not price, left out of the total
+    pub unpriced: Vec<AssetValue>,
+}
+
+/// Query interface a weight registry must implement to drive `DistributeFromRegistry`
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum WeightRegistryQueryMsg {
+    #[returns(RegistryWeightsResponse)]
+    Weights { addrs: Vec<String> },
+}
+
+#[cw_serde]
+pub struct RegistryWeight {
+    pub addr: Addr,
+    pub weight: u64,
+}
+
+#[cw_serde]
+pub struct RegistryWeightsResponse {
+    pub weights: Vec<RegistryWeight>,
+}
+
+/// Time-weighted price query of the router, `price` is the `ask_asset_info` paid per
+/// unit of `offer_asset_info` averaged over the last `window_seconds`
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum RouterTwapQueryMsg {
+    #[returns(TwapResponse)]
+    Twap {
+        offer_asset_info: AssetInfo,
+        ask_asset_info: AssetInfo,
+        window_seconds: u64,
+    },
+}
+
+#[cw_serde]
+pub struct TwapResponse {
+    pub price: Decimal,
+}
+
+/// Query interface of the price oracle
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum OracleQueryMsg {
+    #[returns(OraclePriceResponse)]
+    Price { denom: String },
+}
+
+#[cw_serde]
+pub struct OraclePriceResponse {
+    pub price: Decimal,
+    pub updated_at: Timestamp,
+}
+
+/// Snapshot query of cw20 tokens keeping historical balances
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum SnapshotQueryMsg {
+    #[returns(cw20::BalanceResponse)]
+    BalanceAt { address: String, height: u64 },
+}
+
+/// State export taken at contract `version`, re-importable through `MigrateMsg::import`
+/// by a contract of the same major and minor version. Claimable shares are one page
+/// of at most `MAX_SNAPSHOT_ENTRIES` entries, each page is imported on top of the
+/// previous ones
+#[cw_serde]
+pub struct StateSnapshot {
+    pub version: String,
+    pub owner: Option<Addr>,
+    pub config: Config,
+    pub distribute_targets: Vec<DistributeTarget>,
+    /// keyed by denom or cw20 address
+    pub token_distribute_targets: Vec<(String, Vec<DistributeTarget>)>,
+    pub executors: Vec<Addr>,
+    pub pause_flags: PauseFlags,
+    pub distribution_mode: DistributionMode,
+    pub oracle: Option<OracleConfig>,
+    pub vault: Option<VaultConfig>,
+    /// vault denom deposited and not redeemed yet
+    pub vault_held: Uint128,
+    pub hook_retry: Option<HookRetryConfig>,
+    pub distribution_interval: u64,
+    pub epoch: Option<EpochConfig>,
+    pub target_kind_restriction: TargetKindRestriction,
+    pub max_target_share_bps: Option<u32>,
+    pub remainder_target: Option<Addr>,
+    pub weight_registry: Option<Addr>,
+    pub removal_grace_seconds: u64,
+    pub pending_removals: Vec<PendingRemoval>,
+    pub execute_allowlist: Vec<AllowedMsg>,
+    pub sunset_recipient: Option<Addr>,
+    pub aggregate_events: bool,
+    pub max_hooks_per_distribution: Option<u32>,
+    pub max_assets_per_collect: Option<u32>,
+    pub collect_cooldown_seconds: u64,
+    pub history_max_age_seconds: Option<u64>,
+    pub pending_distribution: Option<PendingDistribution>,
+    pub failed_hooks: Vec<HookAttempt>,
+    pub claim_expiry_seconds: Option<u64>,
+    pub claimable: Vec<(Addr, Asset)>,
+    /// last credit of the targets in `claimable`, their claim expiry runs from it
+    pub last_credited: Vec<(Addr, Timestamp)>,
+    pub received: Vec<(Addr, Asset)>,
+}
+
 #[cw_serde]
 pub struct MigrateMsg {
     pub new_router: Addr,
+    /// State replacing the current one before `new_router` is applied, the claimable
+    /// shares it lists are added to the ones already imported
+    pub import: Option<StateSnapshot>,
 }
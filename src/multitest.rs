@@ -1,6 +1,9 @@
 pub mod contract;
 pub mod contract_ping_pong_mock;
 pub mod mock_cw20_contract;
+pub mod mock_oracle;
 pub mod mock_router_contract;
+pub mod mock_snapshot_cw20;
+pub mod mock_weight_registry;
 #[cfg(test)]
 mod tests;
@@ -4,10 +4,23 @@ use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Addr, Uint128};
 use cw_multi_test::{AppResponse, ContractWrapper, Executor};
 
-use crate::contract::{execute, instantiate, migrate, query};
+use crate::contract::{execute, instantiate, migrate, query, reply};
 
 use super::tests::StargateAccpetingModuleApp;
 
+/// Plain weighted target, tests set the other fields through struct update syntax
+pub fn target(addr: impl Into<String>, weight: u32) -> DistributeTarget {
+    DistributeTarget {
+        addr: Addr::unchecked(addr),
+        weight,
+        msg_hook: None,
+        preferred_asset: None,
+        usd_target: None,
+        hook_gas_limit: None,
+        ibc: None,
+    }
+}
+
 #[cw_serde]
 pub struct TreasuryContract(Addr);
 
@@ -17,7 +30,9 @@ impl TreasuryContract {
     }
 
     pub fn store_code(app: &mut StargateAccpetingModuleApp) -> u64 {
-        let contract = ContractWrapper::new(execute, instantiate, query).with_migrate(migrate);
+        let contract = ContractWrapper::new(execute, instantiate, query)
+            .with_reply(reply)
+            .with_migrate(migrate);
         app.store_code(Box::new(contract))
     }
 
@@ -60,7 +75,11 @@ impl TreasuryContract {
         app.execute_contract(
             sender.clone(),
             self.0.clone(),
-            &ExecuteMsg::Distribute { amount_distribute },
+            &ExecuteMsg::Distribute {
+                amount_distribute,
+                token: None,
+                nonce: None,
+            },
             &[],
         )
         .map_err(|err| err.downcast().unwrap())
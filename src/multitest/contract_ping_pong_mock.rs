@@ -1,12 +1,17 @@
 use super::tests::StargateAccpetingModuleApp;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Binary, Response, StdResult};
+use cosmwasm_std::{Addr, Binary, DepsMut, Response, StdError, StdResult};
 use cw20::Cw20ReceiveMsg;
 use cw_multi_test::{ContractWrapper, Executor};
+use cw_storage_plus::Item;
 
 #[cw_serde]
 pub enum MockExecuteMsg {
     Receive(Cw20ReceiveMsg),
+    /// Make the following pings fail, to exercise hook failures
+    SetFailing {
+        failing: bool,
+    },
 }
 
 #[cw_serde]
@@ -23,6 +28,8 @@ pub struct MockInstantiateMsg {}
 #[cw_serde]
 pub struct MockPingPongContract(Addr);
 
+const FAILING: Item<bool> = Item::new("failing");
+
 impl MockPingPongContract {
     pub fn addr(&self) -> &Addr {
         &self.0
@@ -30,11 +37,18 @@ impl MockPingPongContract {
 
     pub fn store_code(app: &mut StargateAccpetingModuleApp) -> u64 {
         let contract = ContractWrapper::new(
-            |_, _, _, msg: MockExecuteMsg| -> StdResult<Response> {
+            |deps: DepsMut, _, _, msg: MockExecuteMsg| -> StdResult<Response> {
                 match msg {
                     MockExecuteMsg::Receive(_) => {
+                        if FAILING.may_load(deps.storage)?.unwrap_or_default() {
+                            return Err(StdError::generic_err("pong refused"));
+                        }
                         Ok(Response::new().add_attribute("action", "ping"))
                     }
+                    MockExecuteMsg::SetFailing { failing } => {
+                        FAILING.save(deps.storage, &failing)?;
+                        Ok(Response::default())
+                    }
                 }
             },
             |_, _, _, _: MockInstantiateMsg| -> StdResult<Response> { Ok(Response::default()) },
@@ -58,6 +72,16 @@ impl MockPingPongContract {
 
         MockPingPongContract(contract_addr)
     }
+
+    pub fn set_failing(&self, app: &mut StargateAccpetingModuleApp, sender: &Addr, failing: bool) {
+        app.execute_contract(
+            sender.clone(),
+            self.0.clone(),
+            &MockExecuteMsg::SetFailing { failing },
+            &[],
+        )
+        .unwrap();
+    }
 }
 
 impl From<MockPingPongContract> for Addr {
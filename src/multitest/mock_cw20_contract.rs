@@ -1,7 +1,7 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Addr, Uint128};
 
-use cw20::{BalanceResponse, Cw20Coin};
+use cw20::{BalanceResponse, Cw20Coin, MinterResponse};
 use cw20_base::{
     contract::{execute, instantiate, migrate, query},
     ContractError,
@@ -52,6 +52,35 @@ impl MockCw20Contract {
         .map_err(|err| err.downcast().unwrap())
     }
 
+    /// Token without initial balances, mintable by `minter` only
+    pub fn instantiate_mintable(
+        app: &mut StargateAccpetingModuleApp,
+        sender: &Addr,
+        minter: &Addr,
+    ) -> Result<Self, ContractError> {
+        let code_id = Self::store_code(app);
+        app.instantiate_contract(
+            code_id,
+            sender.clone(),
+            &cw20_base::msg::InstantiateMsg {
+                name: "MockReceipt".to_string(),
+                symbol: "MRC".to_string(),
+                decimals: 6,
+                initial_balances: vec![],
+                mint: Some(MinterResponse {
+                    minter: minter.to_string(),
+                    cap: None,
+                }),
+                marketing: None,
+            },
+            &[],
+            "MockReceipt",
+            None,
+        )
+        .map(MockCw20Contract)
+        .map_err(|err| err.downcast().unwrap())
+    }
+
     pub fn query_balance(&self, app: &StargateAccpetingModuleApp, addr: &Addr) -> BalanceResponse {
         app.wrap()
             .query_wasm_smart::<_>(
@@ -0,0 +1,117 @@
+use super::tests::StargateAccpetingModuleApp;
+use crate::msg::{OraclePriceResponse, OracleQueryMsg};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    Timestamp,
+};
+use cw_multi_test::{ContractWrapper, Executor};
+use cw_storage_plus::{Item, Map};
+
+#[cw_serde]
+pub enum MockExecuteMsg {}
+
+#[cw_serde]
+pub struct MockInstantiateMsg {
+    pub price: OraclePriceResponse,
+}
+
+#[cw_serde]
+pub enum MockSudoMsg {
+    /// Price quoted for `denom` instead of the instantiated one
+    SetPrice { denom: String, price: Decimal },
+}
+
+#[cw_serde]
+pub struct MockOracle(Addr);
+
+// every denom is quoted at the same price unless it has its own
+const PRICE: Item<OraclePriceResponse> = Item::new("price");
+const PRICES: Map<&str, Decimal> = Map::new("prices");
+
+fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: MockInstantiateMsg,
+) -> StdResult<Response> {
+    PRICE.save(deps.storage, &msg.price)?;
+    Ok(Response::default())
+}
+
+fn query(deps: Deps, _env: Env, msg: OracleQueryMsg) -> StdResult<Binary> {
+    match msg {
+        OracleQueryMsg::Price { denom } => {
+            let mut price = PRICE.load(deps.storage)?;
+            if let Some(denom_price) = PRICES.may_load(deps.storage, &denom)? {
+                price.price = denom_price;
+            }
+            to_json_binary(&price)
+        }
+    }
+}
+
+impl MockOracle {
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    pub fn store_code(app: &mut StargateAccpetingModuleApp) -> u64 {
+        let contract = ContractWrapper::new(
+            |_, _, _, msg: MockExecuteMsg| -> StdResult<Response> { match msg {} },
+            instantiate,
+            query,
+        )
+        .with_sudo(
+            |deps: DepsMut, _, msg: MockSudoMsg| -> StdResult<Response> {
+                match msg {
+                    MockSudoMsg::SetPrice { denom, price } => {
+                        PRICES.save(deps.storage, &denom, &price)?;
+                        Ok(Response::default())
+                    }
+                }
+            },
+        );
+        app.store_code(Box::new(contract))
+    }
+
+    pub fn instantiate(
+        app: &mut StargateAccpetingModuleApp,
+        sender: &Addr,
+        price: Decimal,
+        updated_at: Timestamp,
+    ) -> Self {
+        let code_id = Self::store_code(app);
+        let contract_addr = app
+            .instantiate_contract(
+                code_id,
+                sender.clone(),
+                &MockInstantiateMsg {
+                    price: OraclePriceResponse { price, updated_at },
+                },
+                &[],
+                "oracle",
+                None,
+            )
+            .unwrap();
+
+        MockOracle(contract_addr)
+    }
+
+    pub fn set_price(&self, app: &mut StargateAccpetingModuleApp, denom: &str, price: Decimal) {
+        app.wasm_sudo(
+            self.0.clone(),
+            &MockSudoMsg::SetPrice {
+                denom: denom.to_string(),
+                price,
+            },
+        )
+        .unwrap();
+    }
+}
+
+impl From<MockOracle> for Addr {
+    fn from(contract: MockOracle) -> Self {
+        contract.0
+    }
+}
@@ -1,16 +1,22 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, DepsMut, Env, MessageInfo, Response, StdResult, WasmMsg,
+    coins, from_json, to_json_binary, Addr, BankMsg, Binary, CosmosMsg, Decimal, Deps, DepsMut,
+    Env, MessageInfo, Response, StdResult, Storage, Uint128, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 use cw_multi_test::{ContractWrapper, Executor};
-use cw_storage_plus::Item;
-use oraiswap::mixed_router::ExecuteMsg as RouterExecuteMsg;
+use cw_storage_plus::{Item, Map};
+use oraiswap::asset::AssetInfo;
+use oraiswap::mixed_router::{
+    Cw20HookMsg as RouterCw20HookMsg, ExecuteMsg as RouterExecuteMsg, QueryMsg as RouterQueryMsg,
+    SwapOperation,
+};
+use oraiswap::router::SimulateSwapOperationsResponse;
 
-use super::tests::StargateAccpetingModuleApp;
+use crate::helpers::asset_info_to_string;
+use crate::msg::{RouterTwapQueryMsg, TwapResponse};
 
-#[cw_serde]
-pub enum MockQueryMsg {}
+use super::tests::StargateAccpetingModuleApp;
 
 #[cw_serde]
 pub enum Cw20Hook {
@@ -22,6 +28,23 @@ pub struct MockInstantiateMsg {
     pub usdc: Addr,
 }
 
+#[cw_serde]
+pub enum MockSudoMsg {
+    /// Units of `ask_asset` paid per offered unit, 1 unless set
+    SetRate { ask_asset: String, rate: Decimal },
+    /// Time-weighted units of `ask_asset` per offered unit, the spot rate unless set
+    SetTwap { ask_asset: String, price: Decimal },
+}
+
+/// The router queries, or the TWAP one the treasury reads for its normalized
+/// distributions
+#[cw_serde]
+#[serde(untagged)]
+pub enum MockQueryMsg {
+    Router(RouterQueryMsg),
+    Twap(RouterTwapQueryMsg),
+}
+
 #[cw_serde]
 pub struct MockRouter(Addr);
 
@@ -30,6 +53,8 @@ pub struct MockRouter(Addr);
  * It is used to test the integration of the Router with other contracts.
  */
 const USDC: Item<Addr> = Item::new("usdc");
+const RATES: Map<&str, Decimal> = Map::new("rates");
+const TWAPS: Map<&str, Decimal> = Map::new("twaps");
 fn instantiate(
     deps: DepsMut,
     _env: Env,
@@ -39,6 +64,49 @@ fn instantiate(
     USDC.save(deps.storage, &msg.usdc)?;
     Ok(Response::default())
 }
+
+/// Asset asked by the last oraiswap operation, usdc otherwise
+fn ask_asset(storage: &dyn Storage, operations: &[SwapOperation]) -> StdResult<AssetInfo> {
+    Ok(match operations.last() {
+        Some(SwapOperation::OraiSwap { ask_asset_info, .. }) => ask_asset_info.clone(),
+        _ => AssetInfo::Token {
+            contract_addr: USDC.load(storage)?,
+        },
+    })
+}
+
+fn swap_amount(
+    storage: &dyn Storage,
+    ask_asset_info: &AssetInfo,
+    offer_amount: Uint128,
+) -> StdResult<Uint128> {
+    let rate = RATES
+        .may_load(storage, &asset_info_to_string(ask_asset_info))?
+        .unwrap_or(Decimal::one());
+    Ok(offer_amount * rate)
+}
+
+/// Pays the swap output of `amount` in the asked asset
+fn swap_output(
+    storage: &dyn Storage,
+    operations: &[SwapOperation],
+    recipient: String,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    let ask_asset_info = ask_asset(storage, operations)?;
+    let amount = swap_amount(storage, &ask_asset_info, amount)?;
+    Ok(match ask_asset_info {
+        AssetInfo::Token { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer { recipient, amount })?,
+            funds: vec![],
+        }),
+        AssetInfo::NativeToken { denom } => CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient,
+            amount: coins(amount.u128(), denom),
+        }),
+    })
+}
 impl MockRouter {
     pub fn addr(&self) -> &Addr {
         &self.0
@@ -47,23 +115,21 @@ impl MockRouter {
     pub fn store_code(app: &mut StargateAccpetingModuleApp) -> u64 {
         let contract = ContractWrapper::new(
             |deps: DepsMut, _, info: MessageInfo, msg: RouterExecuteMsg| -> StdResult<Response> {
-                // swap 1:1 (cw20-> usdc, orai -> usdc)
+                // swap 1:1 into the asked asset, usdc unless the route ends on an oraiswap pair
                 match msg {
                     RouterExecuteMsg::Receive(Cw20ReceiveMsg {
                         sender,
                         amount,
-                        msg: _,
+                        msg,
                     }) => {
-                        // return usdc to sender
-                        let usdc = USDC.load(deps.storage)?;
-                        let msg = WasmMsg::Execute {
-                            contract_addr: usdc.to_string(),
-                            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-                                recipient: sender.to_string(),
-                                amount,
-                            })?,
-                            funds: vec![],
+                        // return the output to the hook recipient, or the sender
+                        let (operations, recipient) = match from_json(&msg) {
+                            Ok(RouterCw20HookMsg::ExecuteSwapOperations {
+                                operations, to, ..
+                            }) => (operations, to.unwrap_or_else(|| sender.clone())),
+                            _ => (vec![], sender.clone()),
                         };
+                        let msg = swap_output(deps.storage, &operations, recipient, amount)?;
 
                         Ok(Response::new()
                             .add_message(msg)
@@ -72,21 +138,17 @@ impl MockRouter {
                             .add_attribute("amount", amount.to_string()))
                     }
                     RouterExecuteMsg::ExecuteSwapOperations {
-                        operations: _,
+                        operations,
                         minimum_receive: _,
                         to,
                         affiliates: _,
                     } => {
-                        let usdc = USDC.load(deps.storage)?;
-                        // return usdc to sender
-                        let msg = WasmMsg::Execute {
-                            contract_addr: usdc.to_string(),
-                            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-                                recipient: to.unwrap().to_string(),
-                                amount: info.funds[0].amount,
-                            })?,
-                            funds: vec![],
-                        };
+                        let msg = swap_output(
+                            deps.storage,
+                            &operations,
+                            to.unwrap().to_string(),
+                            info.funds[0].amount,
+                        )?;
 
                         Ok(Response::new()
                             .add_message(msg)
@@ -99,7 +161,45 @@ impl MockRouter {
                 }
             },
             instantiate,
-            |_, _, _: MockQueryMsg| -> StdResult<Binary> { Ok(Binary::default()) },
+            |deps: Deps, _, msg: MockQueryMsg| -> StdResult<Binary> {
+                match msg {
+                    // quotes the same rate the swaps are executed at
+                    MockQueryMsg::Router(RouterQueryMsg::SimulateSwapOperations {
+                        offer_amount,
+                        operations,
+                    }) => {
+                        let ask_asset_info = ask_asset(deps.storage, &operations)?;
+                        to_json_binary(&SimulateSwapOperationsResponse {
+                            amount: swap_amount(deps.storage, &ask_asset_info, offer_amount)?,
+                        })
+                    }
+                    MockQueryMsg::Twap(RouterTwapQueryMsg::Twap { ask_asset_info, .. }) => {
+                        let key = asset_info_to_string(&ask_asset_info);
+                        let price = match TWAPS.may_load(deps.storage, &key)? {
+                            Some(price) => price,
+                            None => RATES
+                                .may_load(deps.storage, &key)?
+                                .unwrap_or(Decimal::one()),
+                        };
+                        to_json_binary(&TwapResponse { price })
+                    }
+                    _ => Ok(Binary::default()),
+                }
+            },
+        )
+        .with_sudo(
+            |deps: DepsMut, _, msg: MockSudoMsg| -> StdResult<Response> {
+                match msg {
+                    MockSudoMsg::SetRate { ask_asset, rate } => {
+                        RATES.save(deps.storage, &ask_asset, &rate)?;
+                        Ok(Response::default())
+                    }
+                    MockSudoMsg::SetTwap { ask_asset, price } => {
+                        TWAPS.save(deps.storage, &ask_asset, &price)?;
+                        Ok(Response::default())
+                    }
+                }
+            },
         );
         app.store_code(Box::new(contract))
     }
@@ -119,6 +219,38 @@ impl MockRouter {
 
         MockRouter(contract_addr)
     }
+
+    pub fn set_twap(
+        &self,
+        app: &mut StargateAccpetingModuleApp,
+        ask_asset_info: &AssetInfo,
+        price: Decimal,
+    ) {
+        app.wasm_sudo(
+            self.0.clone(),
+            &MockSudoMsg::SetTwap {
+                ask_asset: asset_info_to_string(ask_asset_info),
+                price,
+            },
+        )
+        .unwrap();
+    }
+
+    pub fn set_rate(
+        &self,
+        app: &mut StargateAccpetingModuleApp,
+        ask_asset_info: &AssetInfo,
+        rate: Decimal,
+    ) {
+        app.wasm_sudo(
+            self.0.clone(),
+            &MockSudoMsg::SetRate {
+                ask_asset: asset_info_to_string(ask_asset_info),
+                rate,
+            },
+        )
+        .unwrap();
+    }
 }
 
 impl From<MockRouter> for Addr {
@@ -0,0 +1,101 @@
+use super::tests::StargateAccpetingModuleApp;
+use crate::msg::SnapshotQueryMsg;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
+};
+use cw20::BalanceResponse;
+use cw_multi_test::{ContractWrapper, Executor};
+use cw_storage_plus::Item;
+
+#[cw_serde]
+pub enum MockExecuteMsg {}
+
+#[cw_serde]
+pub struct BalanceCheckpoint {
+    pub address: Addr,
+    pub height: u64,
+    pub balance: Uint128,
+}
+
+#[cw_serde]
+pub struct MockInstantiateMsg {
+    pub checkpoints: Vec<BalanceCheckpoint>,
+}
+
+/**
+ * MockSnapshotCw20 only answers `BalanceAt` from a fixed list of balance checkpoints.
+ */
+#[cw_serde]
+pub struct MockSnapshotCw20(Addr);
+
+const CHECKPOINTS: Item<Vec<BalanceCheckpoint>> = Item::new("checkpoints");
+
+fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: MockInstantiateMsg,
+) -> StdResult<Response> {
+    CHECKPOINTS.save(deps.storage, &msg.checkpoints)?;
+    Ok(Response::default())
+}
+
+fn query(deps: Deps, _env: Env, msg: SnapshotQueryMsg) -> StdResult<Binary> {
+    match msg {
+        SnapshotQueryMsg::BalanceAt { address, height } => {
+            // latest checkpoint of the address at or before the height
+            let balance = CHECKPOINTS
+                .load(deps.storage)?
+                .into_iter()
+                .filter(|checkpoint| {
+                    checkpoint.address.as_str() == address.as_str() && checkpoint.height <= height
+                })
+                .max_by_key(|checkpoint| checkpoint.height)
+                .map(|checkpoint| checkpoint.balance)
+                .unwrap_or_default();
+            to_json_binary(&BalanceResponse { balance })
+        }
+    }
+}
+
+impl MockSnapshotCw20 {
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    pub fn store_code(app: &mut StargateAccpetingModuleApp) -> u64 {
+        let contract = ContractWrapper::new(
+            |_, _, _, msg: MockExecuteMsg| -> StdResult<Response> { match msg {} },
+            instantiate,
+            query,
+        );
+        app.store_code(Box::new(contract))
+    }
+
+    pub fn instantiate(
+        app: &mut StargateAccpetingModuleApp,
+        sender: &Addr,
+        checkpoints: Vec<BalanceCheckpoint>,
+    ) -> Self {
+        let code_id = Self::store_code(app);
+        let contract_addr = app
+            .instantiate_contract(
+                code_id,
+                sender.clone(),
+                &MockInstantiateMsg { checkpoints },
+                &[],
+                "snapshot_cw20",
+                None,
+            )
+            .unwrap();
+
+        MockSnapshotCw20(contract_addr)
+    }
+}
+
+impl From<MockSnapshotCw20> for Addr {
+    fn from(contract: MockSnapshotCw20) -> Self {
+        contract.0
+    }
+}
@@ -0,0 +1,84 @@
+use super::tests::StargateAccpetingModuleApp;
+use crate::msg::{RegistryWeight, RegistryWeightsResponse, WeightRegistryQueryMsg};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
+use cw_multi_test::{ContractWrapper, Executor};
+use cw_storage_plus::Item;
+
+#[cw_serde]
+pub enum MockExecuteMsg {}
+
+#[cw_serde]
+pub struct MockInstantiateMsg {
+    pub weights: Vec<RegistryWeight>,
+}
+
+#[cw_serde]
+pub struct MockWeightRegistry(Addr);
+
+const WEIGHTS: Item<Vec<RegistryWeight>> = Item::new("weights");
+
+fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: MockInstantiateMsg,
+) -> StdResult<Response> {
+    WEIGHTS.save(deps.storage, &msg.weights)?;
+    Ok(Response::default())
+}
+
+fn query(deps: Deps, _env: Env, msg: WeightRegistryQueryMsg) -> StdResult<Binary> {
+    match msg {
+        WeightRegistryQueryMsg::Weights { addrs } => to_json_binary(&RegistryWeightsResponse {
+            weights: WEIGHTS
+                .load(deps.storage)?
+                .into_iter()
+                .filter(|weight| addrs.contains(&weight.addr.to_string()))
+                .collect(),
+        }),
+    }
+}
+
+impl MockWeightRegistry {
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    pub fn store_code(app: &mut StargateAccpetingModuleApp) -> u64 {
+        let contract = ContractWrapper::new(
+            |_, _, _, msg: MockExecuteMsg| -> StdResult<Response> { match msg {} },
+            instantiate,
+            query,
+        );
+        app.store_code(Box::new(contract))
+    }
+
+    pub fn instantiate(
+        app: &mut StargateAccpetingModuleApp,
+        sender: &Addr,
+        weights: Vec<RegistryWeight>,
+    ) -> Self {
+        let code_id = Self::store_code(app);
+        let contract_addr = app
+            .instantiate_contract(
+                code_id,
+                sender.clone(),
+                &MockInstantiateMsg { weights },
+                &[],
+                "weight_registry",
+                None,
+            )
+            .unwrap();
+
+        MockWeightRegistry(contract_addr)
+    }
+}
+
+impl From<MockWeightRegistry> for Addr {
+    fn from(contract: MockWeightRegistry) -> Self {
+        contract.0
+    }
+}
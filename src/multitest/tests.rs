@@ -1,25 +1,40 @@
 use crate::contract::{execute, execute_collect_fees, query};
-use crate::msg::{CollectFeeRequirement, ExecuteMsg, QueryMsg};
-use crate::state::{Config, CONFIG, EXECUTORS};
+use crate::msg::{
+    AccountBalance, AllocationEntry, AssetValue, CollectFeeRequirement,
+    CollectionWorthwhileResponse, CurrentEpochResponse, DistributionShare,
+    EffectiveDistributionResponse, ExecuteMsg, FullCyclePlanResponse, MigrateMsg,
+    NextScheduledDistributionResponse, QueryMsg, Reconciliation, RegistryWeight,
+    SimulateBalancesAfterResponse, StateSnapshot, TargetProfileResponse, TargetRatio, TvlAsset,
+    TvlResponse, ValidatorWeight,
+};
+use crate::state::{
+    AllowedMsg, Config, DistributionHistoryEntry, DistributionMode, EpochConfig, GenesisConfig,
+    HookAttempt, HookRetryConfig, OracleConfig, PauseFlags, PendingDistribution, PendingRemoval,
+    VaultConfig, CONFIG, EXECUTORS,
+};
 use crate::{state::DistributeTarget, ContractError};
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi};
 use cosmwasm_std::{
-    coin, from_json, to_json_binary, Addr, Empty, Event, GovMsg, IbcMsg, IbcQuery, MemoryStorage,
-    Uint128,
+    coin, from_json, to_json_binary, to_json_string, Addr, BankMsg, CosmosMsg, Decimal, Empty,
+    Event, GovMsg, IbcMsg, IbcQuery, MemoryStorage, Uint128, Validator, WasmMsg,
 };
-use cw20::{BalanceResponse, Cw20ExecuteMsg};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, TokenInfoResponse};
 use cw_multi_test::{
-    AcceptingModule, App, AppBuilder, BankKeeper, DistributionKeeper, Executor, FailingModule,
-    StakeKeeper, StargateAcceptingModule, StargateMsg, StargateQuery, WasmKeeper,
+    AcceptingModule, App, AppBuilder, AppResponse, BankKeeper, DistributionKeeper, Executor,
+    FailingModule, StakeKeeper, StakingInfo, StargateAcceptingModule, StargateMsg, StargateQuery,
+    WasmKeeper,
 };
-use oraiswap::asset::AssetInfo;
+use oraiswap::asset::{Asset, AssetInfo};
 use oraiswap::mixed_router::SwapOperation;
 use oraiswap_v3::percentage::Percentage;
 use oraiswap_v3::{FeeTier, PoolKey};
 
 use super::contract_ping_pong_mock::MockPingPongContract;
+use super::mock_oracle::MockOracle;
+use super::mock_snapshot_cw20::{BalanceCheckpoint, MockSnapshotCw20};
+use super::mock_weight_registry::MockWeightRegistry;
 use super::{
-    contract::TreasuryContract,
+    contract::{target, TreasuryContract},
     mock_cw20_contract::MockCw20Contract,
     mock_router_contract::{Cw20Hook, MockRouter},
 };
@@ -101,18 +116,23 @@ fn mock_app() -> (
         router.addr(),
         vec![
             DistributeTarget {
-                weight: 40,
-                addr: ping_pong.addr().clone(),
                 msg_hook: Some(to_json_binary(&Cw20Hook::Ping {}).unwrap()),
+                ..target(ping_pong.addr().clone(), 40)
             },
-            DistributeTarget {
-                weight: 60,
-                addr: finance,
-                msg_hook: None,
-            },
+            target(finance, 60),
         ],
     )
     .unwrap();
+    // the tests below push their distributions unless they switch to Pull
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributionMode {
+            mode: DistributionMode::Push,
+        },
+        &[],
+    )
+    .unwrap();
 
     // send token to router and owner
     usdc.transfer(
@@ -218,7 +238,6 @@ fn test_execute_collect_fees_router_approver_not_set() {
         .save(
             deps.as_mut().storage,
             &Config {
-                owner: Addr::unchecked("owner"),
                 distribute_token: Addr::unchecked("token"),
                 router: None,
             },
@@ -247,12 +266,35 @@ fn test_execute_collect_fees_router_approver_not_set() {
             approver: Addr::unchecked("owner"),
             swap_operations: vec![],
             minimum_receive: None,
+            min_price: None,
         }],
     )
     .unwrap_err();
     assert_eq!(result, ContractError::RouterAndApproverNotSet {});
 }
 
+#[test]
+fn test_execute_collect_fees_no_requirements() {
+    let mut deps = mock_dependencies();
+    CONFIG
+        .save(
+            deps.as_mut().storage,
+            &Config {
+                distribute_token: Addr::unchecked("token"),
+                router: Some(Addr::unchecked("router")),
+            },
+        )
+        .unwrap();
+    EXECUTORS
+        .save(deps.as_mut().storage, &Addr::unchecked("sender"), &true)
+        .unwrap();
+
+    let err = execute_collect_fees(deps.as_mut(), mock_env(), mock_info("sender", &[]), vec![])
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::NoRequirements {});
+}
+
 #[test]
 fn test_execute_collect_fees_unauthorize() {
     let mut deps = mock_dependencies();
@@ -260,12 +302,12 @@ fn test_execute_collect_fees_unauthorize() {
         .save(
             deps.as_mut().storage,
             &Config {
-                owner: Addr::unchecked("owner"),
                 distribute_token: Addr::unchecked("token"),
                 router: None,
             },
         )
         .unwrap();
+    cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some("owner")).unwrap();
     let err = execute(
         deps.as_mut(),
         mock_env(),
@@ -287,6 +329,7 @@ fn test_execute_collect_fees_unauthorize() {
             approver: Addr::unchecked("owner"),
             swap_operations: vec![],
             minimum_receive: None,
+            min_price: None,
         }],
     )
     .unwrap_err();
@@ -346,6 +389,7 @@ fn test_collect_fees_balance_distribute() {
                             x_to_y: true,
                         }],
                         minimum_receive: None,
+                        min_price: None,
                     },
                     CollectFeeRequirement {
                         approver: Addr::unchecked("owner"),
@@ -361,6 +405,7 @@ fn test_collect_fees_balance_distribute() {
                             x_to_y: true,
                         }],
                         minimum_receive: None,
+                        min_price: None,
                     },
                     CollectFeeRequirement {
                         approver: Addr::unchecked("owner"),
@@ -376,6 +421,7 @@ fn test_collect_fees_balance_distribute() {
                             x_to_y: true,
                         }],
                         minimum_receive: None,
+                        min_price: None,
                     },
                 ],
             },
@@ -401,3 +447,3642 @@ fn test_collect_fees_balance_distribute() {
             .unwrap()
     );
 }
+
+#[test]
+fn test_pause_collect_only() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, _cw20, ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::SetPauseFlags {
+            pause_flags: PauseFlags {
+                distribute: false,
+                collect: true,
+                admin: false,
+            },
+        },
+        &[],
+    )
+    .unwrap();
+
+    let pause_flags: PauseFlags = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::PauseFlags {})
+        .unwrap();
+    assert!(pause_flags.collect);
+
+    // collection is halted
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![],
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::Paused {
+            operation: "collect".to_string()
+        }
+    );
+
+    // distribution of already-held funds still works
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    let ping_pong_balance: BalanceResponse = usdc.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::from(40u128));
+    let finance_balance: BalanceResponse = usdc.query_balance(&app, &finance);
+    assert_eq!(finance_balance.balance, Uint128::from(60u128));
+}
+
+#[test]
+fn test_simulate_balances_after_distribute() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let distribute_amount = Uint128::from(100u64);
+    let (mut app, treasury, _cw20, ping_pong, _router, usdc) = mock_app();
+
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(150u64));
+
+    let simulation: SimulateBalancesAfterResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::SimulateBalancesAfter {
+                amount: distribute_amount,
+            },
+        )
+        .unwrap();
+
+    treasury
+        .distribute_token(&owner, &mut app, distribute_amount)
+        .unwrap();
+
+    assert_eq!(
+        simulation.contract_balance,
+        usdc.query_balance(&app, treasury.addr()).balance
+    );
+    assert_eq!(
+        simulation.targets,
+        vec![
+            AccountBalance {
+                addr: ping_pong.addr().clone(),
+                balance: usdc.query_balance(&app, ping_pong.addr()).balance,
+            },
+            AccountBalance {
+                addr: finance.clone(),
+                balance: usdc.query_balance(&app, &finance).balance,
+            },
+        ]
+    );
+    assert_eq!(simulation.contract_balance, Uint128::from(50u64));
+}
+
+#[test]
+fn test_distribute_from_registry() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, _cw20, ping_pong, _router, usdc) = mock_app();
+
+    // the registry inverts the stored 40/60 split
+    let registry = MockWeightRegistry::instantiate(
+        &mut app,
+        &owner,
+        vec![
+            RegistryWeight {
+                addr: ping_pong.addr().clone(),
+                weight: 3,
+            },
+            RegistryWeight {
+                addr: finance.clone(),
+                weight: 1,
+            },
+        ],
+    );
+
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+
+    // registry has to be configured first
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeFromRegistry {
+                amount: Uint128::from(100u64),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::RegistryNotSet {});
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateWeightRegistry {
+            registry: Some(registry.addr().clone()),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateAggregateEvents {
+            aggregate_events: true,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let res = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeFromRegistry {
+                amount: Uint128::from(100u64),
+            },
+            &[],
+        )
+        .unwrap();
+
+    let ping_pong_balance: BalanceResponse = usdc.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::from(75u128));
+    let finance_balance: BalanceResponse = usdc.query_balance(&app, &finance);
+    assert_eq!(finance_balance.balance, Uint128::from(25u128));
+    // recorded like a Distribute
+    assert!(res.has_event(&Event::new("wasm-distribute-summary").add_attribute("targets", "2")));
+    let history: Vec<DistributionHistoryEntry> = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::DistributionHistory { limit: None },
+        )
+        .unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|entry| (entry.token.clone(), entry.amount))
+            .collect::<Vec<(String, Uint128)>>(),
+        vec![(usdc.addr().to_string(), Uint128::from(100u128))]
+    );
+}
+
+#[test]
+fn test_collect_fees_reconciliation() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+
+    let response = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![CollectFeeRequirement {
+                    approver: owner.clone(),
+                    swap_operations: vec![SwapOperation::SwapV3 {
+                        pool_key: PoolKey {
+                            token_x: "orai".into(),
+                            token_y: usdc.addr().to_string(),
+                            fee_tier: FeeTier {
+                                fee: Percentage(3u64),
+                                tick_spacing: 100,
+                            },
+                        },
+                        x_to_y: true,
+                    }],
+                    minimum_receive: None,
+                    min_price: None,
+                }],
+            },
+            &[],
+        )
+        .unwrap();
+
+    let reconciliation = from_json::<Reconciliation>(&response.data.unwrap()).unwrap();
+    let swapped = Uint128::from(INITIAL_BALANCE)
+        .checked_sub(Uint128::from(1000000u128))
+        .unwrap();
+
+    // the offered orai left the contract and came back as the distribution token
+    assert_eq!(reconciliation.before["orai"], swapped);
+    assert_eq!(reconciliation.after["orai"], Uint128::zero());
+    assert_eq!(reconciliation.before[usdc.addr().as_str()], Uint128::zero());
+    assert_eq!(reconciliation.after[usdc.addr().as_str()], swapped);
+}
+
+#[test]
+fn test_effective_distribution_matches_execution() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    // not evenly divisible by the 40/60 weights
+    let distribute_amount = Uint128::from(103u64);
+    let (mut app, treasury, _cw20, ping_pong, _router, usdc) = mock_app();
+
+    usdc.transfer(&mut app, &owner, treasury.addr(), distribute_amount);
+
+    let preview: EffectiveDistributionResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::EffectiveDistribution {
+                amount: distribute_amount,
+            },
+        )
+        .unwrap();
+
+    treasury
+        .distribute_token(&owner, &mut app, distribute_amount)
+        .unwrap();
+
+    assert_eq!(
+        preview.shares,
+        vec![
+            DistributionShare {
+                addr: ping_pong.addr().clone(),
+                amount: usdc.query_balance(&app, ping_pong.addr()).balance,
+            },
+            DistributionShare {
+                addr: finance.clone(),
+                amount: usdc.query_balance(&app, &finance).balance,
+            },
+        ]
+    );
+    assert_eq!(
+        preview.undistributed,
+        usdc.query_balance(&app, treasury.addr()).balance
+    );
+    assert_eq!(preview.undistributed, Uint128::from(1u64));
+}
+
+#[test]
+fn test_distribute_from_allowance() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, cw20, ping_pong, ..) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        cw20.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(100u64),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeFromAllowance {
+                token: cw20.addr().clone(),
+                owner: owner.clone(),
+                amount: Uint128::from(101u64),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::InsufficientAllowance {
+            allowance: Uint128::from(100u64),
+            amount: Uint128::from(101u64),
+        }
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateAggregateEvents {
+            aggregate_events: true,
+        },
+        &[],
+    )
+    .unwrap();
+    let res = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeFromAllowance {
+                token: cw20.addr().clone(),
+                owner: owner.clone(),
+                amount: Uint128::from(100u64),
+            },
+            &[],
+        )
+        .unwrap();
+
+    // the treasury never keeps the pulled tokens
+    assert_eq!(
+        cw20.query_balance(&app, treasury.addr()).balance,
+        Uint128::zero()
+    );
+    assert_eq!(
+        cw20.query_balance(&app, ping_pong.addr()).balance,
+        Uint128::from(40u64)
+    );
+    assert_eq!(
+        cw20.query_balance(&app, &finance).balance,
+        Uint128::from(60u64)
+    );
+    // recorded like a Distribute
+    assert!(res.has_event(&Event::new("wasm-distribute-summary").add_attribute("targets", "2")));
+    let history: Vec<DistributionHistoryEntry> = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::DistributionHistory { limit: None },
+        )
+        .unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|entry| (entry.token.clone(), entry.amount))
+            .collect::<Vec<(String, Uint128)>>(),
+        vec![(cw20.addr().to_string(), Uint128::from(100u128))]
+    );
+}
+
+#[test]
+fn test_distribute_by_voting_power() {
+    let owner = Addr::unchecked("owner");
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+
+    let current_height = app.block_info().height;
+    let snapshot_height = current_height - 1;
+    // alice moved her whole balance to bob right before the distribution
+    let voting_token = MockSnapshotCw20::instantiate(
+        &mut app,
+        &owner,
+        vec![
+            BalanceCheckpoint {
+                address: alice.clone(),
+                height: 1,
+                balance: Uint128::from(300u64),
+            },
+            BalanceCheckpoint {
+                address: bob.clone(),
+                height: 1,
+                balance: Uint128::from(100u64),
+            },
+            BalanceCheckpoint {
+                address: alice.clone(),
+                height: current_height,
+                balance: Uint128::zero(),
+            },
+            BalanceCheckpoint {
+                address: bob.clone(),
+                height: current_height,
+                balance: Uint128::from(400u64),
+            },
+        ],
+    );
+
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+
+    let distribute = |app: &mut StargateAccpetingModuleApp, holders: Vec<Addr>| {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeByVotingPower {
+                token: voting_token.addr().clone(),
+                height: snapshot_height,
+                holders,
+                amount: Uint128::from(100u64),
+            },
+            &[],
+        )
+    };
+
+    // listing bob twice would double his weight
+    let err: ContractError = distribute(&mut app, vec![alice.clone(), bob.clone(), bob.clone()])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::DuplicateHolder {
+            addr: bob.to_string(),
+        }
+    );
+
+    distribute(&mut app, vec![alice.clone(), bob.clone()]).unwrap();
+
+    // the split follows the snapshot, not the current balances
+    assert_eq!(
+        usdc.query_balance(&app, &alice).balance,
+        Uint128::from(75u64)
+    );
+    assert_eq!(usdc.query_balance(&app, &bob).balance, Uint128::from(25u64));
+    // recorded under the distributed token, not the voting one
+    let history: Vec<DistributionHistoryEntry> = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::DistributionHistory { limit: None },
+        )
+        .unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|entry| (entry.token.clone(), entry.amount))
+            .collect::<Vec<(String, Uint128)>>(),
+        vec![(usdc.addr().to_string(), Uint128::from(100u128))]
+    );
+}
+
+#[test]
+fn test_distribute_continues_after_hook_cap() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, _cw20, ping_pong, _router, usdc) = mock_app();
+
+    let hook_target = DistributeTarget {
+        msg_hook: Some(to_json_binary(&Cw20Hook::Ping {}).unwrap()),
+        ..target(ping_pong.addr().clone(), 20)
+    };
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributeTarget {
+            distribute_targets: vec![
+                hook_target.clone(),
+                hook_target.clone(),
+                hook_target,
+                target(finance.clone(), 40),
+            ],
+        },
+        &[],
+    )
+    .unwrap();
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateMaxHooksPerDistribution { max_hooks: Some(0) },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::InvalidMaxHooks {});
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateMaxHooksPerDistribution { max_hooks: Some(2) },
+        &[],
+    )
+    .unwrap();
+
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    // the third hook target is left for the follow-up call
+    let ping_pong_balance: BalanceResponse = usdc.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::from(40u128));
+    let finance_balance: BalanceResponse = usdc.query_balance(&app, &finance);
+    assert_eq!(finance_balance.balance, Uint128::from(40u128));
+    let pending: Option<PendingDistribution> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::PendingDistribution {})
+        .unwrap();
+    assert_eq!(pending.unwrap().payouts.len(), 1);
+
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(10u64))
+        .unwrap_err();
+    assert_eq!(err, ContractError::DistributionInProgress {});
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::ContinueDistribution {},
+        &[],
+    )
+    .unwrap();
+
+    let ping_pong_balance: BalanceResponse = usdc.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::from(60u128));
+    let treasury_balance: BalanceResponse = usdc.query_balance(&app, treasury.addr());
+    assert_eq!(treasury_balance.balance, Uint128::zero());
+    let pending: Option<PendingDistribution> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::PendingDistribution {})
+        .unwrap();
+    assert!(pending.is_none());
+
+    // a normalized distribution is held to the same cap
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    let res = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeNormalized {
+                amount: Uint128::from(100u128),
+                nonce: None,
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("action", "distribute_normalized")
+            .add_attribute("deferred_hooks", "1")
+    ));
+    let ping_pong_balance: BalanceResponse = usdc.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::from(100u128));
+    let pending: Option<PendingDistribution> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::PendingDistribution {})
+        .unwrap();
+    assert_eq!(pending.unwrap().payouts.len(), 1);
+}
+
+#[test]
+fn test_genesis_config_survives_config_updates() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, cw20, ping_pong, router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateConfig {
+            distribute_token: Some(cw20.addr().clone()),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributeTarget {
+            distribute_targets: vec![target(finance.clone(), 100)],
+        },
+        &[],
+    )
+    .unwrap();
+
+    let genesis: Option<GenesisConfig> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::GenesisConfig {})
+        .unwrap();
+    assert_eq!(
+        genesis,
+        Some(GenesisConfig {
+            owner,
+            distribute_token: usdc.addr().clone(),
+            router: Some(router.addr().clone()),
+            init_distribution_targets: vec![
+                DistributeTarget {
+                    msg_hook: Some(to_json_binary(&Cw20Hook::Ping {}).unwrap()),
+                    ..target(ping_pong.addr().clone(), 40)
+                },
+                target(finance, 60),
+            ],
+        })
+    );
+}
+
+#[test]
+fn test_remove_distribute_target_after_grace() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, cw20, ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateRemovalGrace {
+            removal_grace_seconds: 100,
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::RemoveDistributeTarget {
+            addr: finance.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let pending_removals: Vec<PendingRemoval> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::PendingRemovals {})
+        .unwrap();
+    assert_eq!(
+        pending_removals,
+        vec![PendingRemoval {
+            addr: finance.clone(),
+            effective_at: app.block_info().time.plus_seconds(100),
+        }]
+    );
+
+    // a second removal does not push the schedule back
+    app.update_block(|block| block.time = block.time.plus_seconds(50));
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::RemoveDistributeTarget {
+            addr: finance.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    let pending_removals: Vec<PendingRemoval> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::PendingRemovals {})
+        .unwrap();
+    assert_eq!(
+        pending_removals[0].effective_at,
+        app.block_info().time.plus_seconds(50)
+    );
+
+    // still paid during the grace period
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(200u64));
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+    let finance_balance: BalanceResponse = usdc.query_balance(&app, &finance);
+    assert_eq!(finance_balance.balance, Uint128::from(60u128));
+
+    app.update_block(|block| block.time = block.time.plus_seconds(51));
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    let finance_balance: BalanceResponse = usdc.query_balance(&app, &finance);
+    assert_eq!(finance_balance.balance, Uint128::from(60u128));
+    let ping_pong_balance: BalanceResponse = usdc.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::from(80u128));
+    let treasury_balance: BalanceResponse = usdc.query_balance(&app, treasury.addr());
+    assert_eq!(treasury_balance.balance, Uint128::from(60u128));
+
+    // the removed weight is free for the remaining targets
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateWeights {
+            updates: vec![(ping_pong.addr().clone(), 100)],
+        },
+        &[],
+    )
+    .unwrap();
+
+    // a target only on a per-token list can be removed too
+    let alice = Addr::unchecked("alice");
+    let remove_alice = |app: &mut StargateAccpetingModuleApp| {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::RemoveDistributeTarget {
+                addr: alice.clone(),
+            },
+            &[],
+        )
+    };
+    let err: ContractError = remove_alice(&mut app).unwrap_err().downcast().unwrap();
+    assert_eq!(
+        err,
+        ContractError::TargetNotFound {
+            addr: alice.to_string(),
+        }
+    );
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateTokenDistributeTargets {
+            token: AssetInfo::Token {
+                contract_addr: cw20.addr().clone(),
+            },
+            distribute_targets: vec![target(alice.clone(), 100)],
+        },
+        &[],
+    )
+    .unwrap();
+    remove_alice(&mut app).unwrap();
+    let pending_removals: Vec<PendingRemoval> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::PendingRemovals {})
+        .unwrap();
+    assert_eq!(
+        pending_removals
+            .iter()
+            .find(|removal| removal.addr == alice),
+        Some(&PendingRemoval {
+            addr: alice.clone(),
+            effective_at: app.block_info().time.plus_seconds(100),
+        })
+    );
+}
+
+#[test]
+fn test_distribute_with_token_override() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, cw20, ping_pong, _router, usdc) = mock_app();
+
+    cw20.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Distribute {
+            amount_distribute: Uint128::from(100u64),
+            token: Some(AssetInfo::Token {
+                contract_addr: cw20.addr().clone(),
+            }),
+            nonce: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let ping_pong_balance: BalanceResponse = cw20.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::from(40u128));
+    let finance_balance: BalanceResponse = cw20.query_balance(&app, &finance);
+    assert_eq!(finance_balance.balance, Uint128::from(60u128));
+    // the configured distribute token is left untouched
+    let finance_balance: BalanceResponse = usdc.query_balance(&app, &finance);
+    assert_eq!(finance_balance.balance, Uint128::zero());
+
+    // native tokens can not reach the hook of the ping pong target, rejected up front
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::Distribute {
+                amount_distribute: Uint128::from(100u64),
+                token: Some(AssetInfo::NativeToken {
+                    denom: "orai".to_string(),
+                }),
+                nonce: None,
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::HooksRequireCw20Token {
+            addr: ping_pong.addr().to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_collect_fees_guarded_exceeds_max_offer() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, router, usdc) = mock_app();
+
+    let requirement = |denom: &str| CollectFeeRequirement {
+        approver: owner.clone(),
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x: denom.into(),
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        minimum_receive: None,
+        min_price: None,
+    };
+    // each offer fits under the guard, their sum does not
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFeesGuarded {
+                collect_fee_requirements: vec![requirement("orai"), requirement("atom")],
+                max_total_offer: Uint128::from(INITIAL_BALANCE),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+
+    assert!(matches!(
+        err,
+        ContractError::OfferExceedsGuard {
+            offer_amount,
+            max_total_offer,
+        } if offer_amount > max_total_offer && max_total_offer == Uint128::from(INITIAL_BALANCE)
+    ));
+    // no swap reached the router
+    let router_orai = app.wrap().query_balance(router.addr(), "orai").unwrap();
+    assert_eq!(router_orai.amount, Uint128::zero());
+    let treasury_balance: BalanceResponse = usdc.query_balance(&app, treasury.addr());
+    assert_eq!(treasury_balance.balance, Uint128::zero());
+}
+
+#[test]
+fn test_distribute_tokens_with_separate_targets() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, cw20, ping_pong, _router, usdc) = mock_app();
+    let cw20_info = AssetInfo::Token {
+        contract_addr: cw20.addr().clone(),
+    };
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateTokenDistributeTargets {
+            token: cw20_info.clone(),
+            distribute_targets: vec![target(finance.clone(), 100)],
+        },
+        &[],
+    )
+    .unwrap();
+
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    cw20.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Distribute {
+            amount_distribute: Uint128::from(100u64),
+            token: Some(cw20_info),
+            nonce: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // usdc keeps the default 40/60 split
+    let ping_pong_balance: BalanceResponse = usdc.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::from(40u128));
+    let finance_balance: BalanceResponse = usdc.query_balance(&app, &finance);
+    assert_eq!(finance_balance.balance, Uint128::from(60u128));
+    // the cw20 only goes to finance
+    let ping_pong_balance: BalanceResponse = cw20.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::zero());
+    let finance_balance: BalanceResponse = cw20.query_balance(&app, &finance);
+    assert_eq!(finance_balance.balance, Uint128::from(100u128));
+}
+
+#[test]
+fn test_collection_worthwhile_depends_on_gas_cost() {
+    let owner = Addr::unchecked("owner");
+    let (app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+    let assets = vec![CollectFeeRequirement {
+        approver: owner.clone(),
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x: "orai".into(),
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        minimum_receive: None,
+        min_price: None,
+    }];
+    let query_worthwhile = |gas_price: Decimal| -> CollectionWorthwhileResponse {
+        app.wrap()
+            .query_wasm_smart(
+                treasury.addr(),
+                &QueryMsg::CollectionWorthwhile {
+                    assets: assets.clone(),
+                    gas_price,
+                    gas_estimate: INITIAL_BALANCE as u64,
+                },
+            )
+            .unwrap()
+    };
+
+    let cheap = query_worthwhile(Decimal::permille(1));
+    assert_eq!(
+        cheap.total_output,
+        Uint128::from(INITIAL_BALANCE - 1000000u128)
+    );
+    assert!(cheap.worthwhile);
+
+    // the gas now costs more than the swapped orai is worth
+    let expensive = query_worthwhile(Decimal::one());
+    assert_eq!(expensive.gas_cost, Uint128::from(INITIAL_BALANCE));
+    assert!(!expensive.worthwhile);
+}
+
+#[test]
+fn test_retry_failed_hook() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, _cw20, ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateHookRetry {
+            hook_retry: Some(HookRetryConfig {
+                max_retries: 1,
+                backoff_blocks: 5,
+                fallback: Addr::unchecked("fallback"),
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+
+    ping_pong.set_failing(&mut app, &owner, true);
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    // the failing hook does not revert the other payouts
+    let finance_balance: BalanceResponse = usdc.query_balance(&app, &finance);
+    assert_eq!(finance_balance.balance, Uint128::from(60u128));
+    let ping_pong_balance: BalanceResponse = usdc.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::zero());
+    let failed_hooks: Vec<HookAttempt> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::FailedHooks {})
+        .unwrap();
+    assert_eq!(failed_hooks.len(), 1);
+    assert_eq!(failed_hooks[0].amount, Uint128::from(40u128));
+
+    // the failed payout is not available to another distribution
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(40u64))
+        .unwrap_err();
+    assert_eq!(err, ContractError::ExceedContractBalance {});
+
+    ping_pong.set_failing(&mut app, &owner, false);
+    let retry = ExecuteMsg::RetryHook {
+        target: ping_pong.addr().clone(),
+        token: None,
+    };
+    let err: ContractError = app
+        .execute_contract(owner.clone(), treasury.addr().clone(), &retry, &[])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::RetryTooEarly {
+            height: failed_hooks[0].retry_at_height,
+        }
+    );
+
+    // the retry config going away does not strand the failed payout
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateHookRetry { hook_retry: None },
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.height += 5);
+    app.execute_contract(owner.clone(), treasury.addr().clone(), &retry, &[])
+        .unwrap();
+
+    let ping_pong_balance: BalanceResponse = usdc.query_balance(&app, ping_pong.addr());
+    assert_eq!(ping_pong_balance.balance, Uint128::from(40u128));
+    let failed_hooks: Vec<HookAttempt> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::FailedHooks {})
+        .unwrap();
+    assert!(failed_hooks.is_empty());
+}
+
+#[test]
+fn test_query_tvl() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, _router, usdc) = mock_app();
+    let usdc_info = AssetInfo::Token {
+        contract_addr: usdc.addr().clone(),
+    };
+    let cw20_info = AssetInfo::Token {
+        contract_addr: cw20.addr().clone(),
+    };
+    let orai_info = AssetInfo::NativeToken {
+        denom: "orai".to_string(),
+    };
+    let atom_info = AssetInfo::NativeToken {
+        denom: "atom".to_string(),
+    };
+
+    cw20.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    app.send_tokens(owner.clone(), treasury.addr().clone(), &[coin(50, "atom")])
+        .unwrap();
+
+    let tvl: TvlResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::Tvl {
+                quote_denom: usdc.addr().to_string(),
+                assets: vec![
+                    TvlAsset {
+                        info: orai_info.clone(),
+                        operations: vec![SwapOperation::OraiSwap {
+                            offer_asset_info: orai_info.clone(),
+                            ask_asset_info: usdc_info.clone(),
+                        }],
+                    },
+                    TvlAsset {
+                        info: cw20_info.clone(),
+                        operations: vec![SwapOperation::OraiSwap {
+                            offer_asset_info: cw20_info.clone(),
+                            ask_asset_info: usdc_info,
+                        }],
+                    },
+                    // no route to price atom
+                    TvlAsset {
+                        info: atom_info.clone(),
+                        operations: vec![],
+                    },
+                ],
+            },
+        )
+        .unwrap();
+
+    // the mock router prices everything 1:1
+    let orai_balance = Uint128::from(999999999999000000u128);
+    assert_eq!(tvl.total, orai_balance + Uint128::from(100u128));
+    assert_eq!(
+        tvl.assets,
+        vec![
+            AssetValue {
+                info: orai_info,
+                balance: orai_balance,
+                value: orai_balance,
+            },
+            AssetValue {
+                info: cw20_info,
+                balance: Uint128::from(100u128),
+                value: Uint128::from(100u128),
+            },
+        ]
+    );
+    assert_eq!(
+        tvl.unpriced,
+        vec![AssetValue {
+            info: atom_info,
+            balance: Uint128::from(50u128),
+            value: Uint128::zero(),
+        }]
+    );
+}
+
+#[test]
+fn test_rebalance_emits_correcting_swaps() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, cw20, _ping_pong, router, usdc) = mock_app();
+    let usdc_info = AssetInfo::Token {
+        contract_addr: usdc.addr().clone(),
+    };
+    let cw20_info = AssetInfo::Token {
+        contract_addr: cw20.addr().clone(),
+    };
+    let orai_info = AssetInfo::NativeToken {
+        denom: "orai".to_string(),
+    };
+
+    cw20.transfer(&mut app, &owner, treasury.addr(), Uint128::from(1000u64));
+    // the mock router pays the bought cw20 out of its own balance
+    cw20.transfer(
+        &mut app,
+        &owner,
+        router.addr(),
+        Uint128::from(INITIAL_BALANCE / 2),
+    );
+
+    let target_ratios = vec![
+        TargetRatio {
+            asset: TvlAsset {
+                info: orai_info.clone(),
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: orai_info.clone(),
+                    ask_asset_info: usdc_info.clone(),
+                }],
+            },
+            ratio: Decimal::percent(50),
+            buy_operations: vec![SwapOperation::OraiSwap {
+                offer_asset_info: usdc_info.clone(),
+                ask_asset_info: orai_info,
+            }],
+        },
+        TargetRatio {
+            asset: TvlAsset {
+                info: cw20_info.clone(),
+                operations: vec![SwapOperation::OraiSwap {
+                    offer_asset_info: cw20_info.clone(),
+                    ask_asset_info: usdc_info.clone(),
+                }],
+            },
+            ratio: Decimal::percent(50),
+            buy_operations: vec![SwapOperation::OraiSwap {
+                offer_asset_info: usdc_info,
+                ask_asset_info: cw20_info,
+            }],
+        },
+    ];
+
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::Rebalance {
+                quote_denom: usdc.addr().to_string(),
+                target_ratios: target_ratios[..1].to_vec(),
+                tolerance: Decimal::percent(1),
+                max_slippage: Decimal::percent(2),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::InvalidTargetRatios {});
+
+    let res = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::Rebalance {
+                quote_denom: usdc.addr().to_string(),
+                target_ratios,
+                tolerance: Decimal::percent(1),
+                max_slippage: Decimal::percent(2),
+            },
+            &[],
+        )
+        .unwrap();
+
+    // orai is far over its half and gets sold, the cw20 is bought with the proceeds
+    let half_gap = Uint128::from((999999999999000000u128 - 1000) / 2);
+    let swapped = res
+        .events
+        .iter()
+        .filter(|event| {
+            event.ty == "wasm"
+                && event.attributes.iter().any(|attr| {
+                    attr.key == "_contract_address" && attr.value == router.addr().as_str()
+                })
+        })
+        .filter_map(|event| {
+            event
+                .attributes
+                .iter()
+                .find(|attr| attr.key == "amount")
+                .map(|attr| attr.value.clone())
+        })
+        .collect::<Vec<String>>();
+    // the cw20 buy only spends the minimum the orai sale is guaranteed to return,
+    // its quote less the slippage rather than the drift tolerance
+    assert_eq!(
+        swapped,
+        vec![
+            half_gap.to_string(),
+            (half_gap * Decimal::percent(98)).to_string()
+        ]
+    );
+    let orai_balance = app.wrap().query_balance(treasury.addr(), "orai").unwrap();
+    assert_eq!(
+        orai_balance.amount,
+        Uint128::from(999999999999000000u128) - half_gap
+    );
+}
+
+#[test]
+fn test_next_scheduled_distribution_with_throttle() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributionInterval {
+            distribution_interval: 3600,
+        },
+        &[],
+    )
+    .unwrap();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(300u64));
+
+    let query_next = |app: &StargateAccpetingModuleApp| -> NextScheduledDistributionResponse {
+        app.wrap()
+            .query_wasm_smart(treasury.addr(), &QueryMsg::NextScheduledDistribution {})
+            .unwrap()
+    };
+
+    // nothing distributed yet, available right away
+    let next = query_next(&app);
+    assert_eq!(next.available_at, app.block_info().time);
+    assert_eq!(next.amount, Uint128::from(300u128));
+
+    let distributed_at = app.block_info().time;
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    let next = query_next(&app);
+    assert_eq!(next.available_at, distributed_at.plus_seconds(3600));
+    assert_eq!(next.amount, Uint128::from(200u128));
+
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::DistributionThrottled {
+            available_at: distributed_at.plus_seconds(3600),
+        }
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(3600));
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+}
+
+#[test]
+fn test_collect_fees_continues_after_asset_cap() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+
+    // the treasury needs the atom it swaps on behalf of the approver
+    app.send_tokens(
+        owner.clone(),
+        treasury.addr().clone(),
+        &[coin(INITIAL_BALANCE / 2, "atom")],
+    )
+    .unwrap();
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateMaxAssetsPerCollect {
+                max_assets: Some(0),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::InvalidMaxAssets {});
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateMaxAssetsPerCollect {
+            max_assets: Some(1),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let requirement = |denom: &str| CollectFeeRequirement {
+        approver: owner.clone(),
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x: denom.into(),
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        minimum_receive: None,
+        min_price: None,
+    };
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::CollectFees {
+            collect_fee_requirements: vec![requirement("orai"), requirement("atom")],
+        },
+        &[],
+    )
+    .unwrap();
+
+    let orai_swapped = Uint128::from(INITIAL_BALANCE - 1000000u128);
+    let treasury_balance: BalanceResponse = usdc.query_balance(&app, treasury.addr());
+    assert_eq!(treasury_balance.balance, orai_swapped);
+    let pending: Vec<CollectFeeRequirement> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::PendingCollect {})
+        .unwrap();
+    assert_eq!(pending, vec![requirement("atom")]);
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::ContinueCollect {},
+        &[],
+    )
+    .unwrap();
+
+    let treasury_balance: BalanceResponse = usdc.query_balance(&app, treasury.addr());
+    assert_eq!(
+        treasury_balance.balance,
+        orai_swapped + Uint128::from(INITIAL_BALANCE / 2)
+    );
+    let pending: Vec<CollectFeeRequirement> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::PendingCollect {})
+        .unwrap();
+    assert!(pending.is_empty());
+}
+
+#[test]
+fn test_stale_oracle_blocks_distribution() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+
+    let updated_at = app.block_info().time;
+    let oracle = MockOracle::instantiate(&mut app, &owner, Decimal::one(), updated_at);
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateOracle {
+            oracle: Some(OracleConfig {
+                oracle: oracle.addr().clone(),
+                max_oracle_staleness_seconds: 60,
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(200u64));
+
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    app.update_block(|block| block.time = block.time.plus_seconds(61));
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap_err();
+    assert_eq!(err, ContractError::StaleOracle { updated_at });
+
+    // pulling from an allowance splits at the same stale price
+    app.execute_contract(
+        owner.clone(),
+        usdc.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(100u64),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeFromAllowance {
+                token: usdc.addr().clone(),
+                owner: owner.clone(),
+                amount: Uint128::from(100u64),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::StaleOracle { updated_at });
+}
+
+#[test]
+fn test_distribute_swaps_into_preferred_assets() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    let (mut app, treasury, cw20, _ping_pong, router, usdc) = mock_app();
+
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateDistributeTarget {
+                distribute_targets: vec![DistributeTarget {
+                    msg_hook: Some(to_json_binary(&Cw20Hook::Ping {}).unwrap()),
+                    preferred_asset: Some(AssetInfo::NativeToken {
+                        denom: "orai".to_string(),
+                    }),
+                    ..target(finance.clone(), 100)
+                }],
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::PreferredAssetWithHook {
+            addr: finance.to_string()
+        }
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributeTarget {
+            distribute_targets: vec![
+                DistributeTarget {
+                    preferred_asset: Some(AssetInfo::Token {
+                        contract_addr: cw20.addr().clone(),
+                    }),
+                    ..target(finance.clone(), 40)
+                },
+                DistributeTarget {
+                    preferred_asset: Some(AssetInfo::NativeToken {
+                        denom: "orai".to_string(),
+                    }),
+                    ..target(alice.clone(), 30)
+                },
+                target(bob.clone(), 30),
+            ],
+        },
+        &[],
+    )
+    .unwrap();
+
+    // the mock router pays the swap outputs out of its own balances
+    cw20.transfer(&mut app, &owner, router.addr(), Uint128::from(1000u64));
+    app.send_tokens(owner.clone(), router.addr().clone(), &[coin(1000, "orai")])
+        .unwrap();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    assert_eq!(
+        cw20.query_balance(&app, &finance).balance,
+        Uint128::from(40u128)
+    );
+    assert_eq!(
+        app.wrap().query_balance(&alice, "orai").unwrap().amount,
+        Uint128::from(30u128)
+    );
+    assert_eq!(
+        usdc.query_balance(&app, &bob).balance,
+        Uint128::from(30u128)
+    );
+    assert_eq!(usdc.query_balance(&app, &finance).balance, Uint128::zero());
+}
+
+#[test]
+fn test_execute_passthrough_checks_allowlist() {
+    let owner = Addr::unchecked("owner");
+    let alice = Addr::unchecked("alice");
+    let (mut app, treasury, _cw20, ping_pong, _router, _usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateExecuteAllowlist {
+            allowlist: vec![AllowedMsg::BankSend {
+                to_address: alice.clone(),
+                max_amount: vec![coin(100, "orai")],
+            }],
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Execute {
+            msgs: vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: alice.to_string(),
+                amount: vec![coin(100, "orai")],
+            })],
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_balance(&alice, "orai").unwrap().amount,
+        Uint128::from(100u128)
+    );
+
+    // sends beyond the listed amount are refused
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::Execute {
+                msgs: vec![CosmosMsg::Bank(BankMsg::Send {
+                    to_address: alice.to_string(),
+                    amount: vec![coin(101, "orai")],
+                })],
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::MsgNotAllowed { index: 0 });
+
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::Execute {
+                msgs: vec![
+                    CosmosMsg::Bank(BankMsg::Send {
+                        to_address: alice.to_string(),
+                        amount: vec![coin(100, "orai")],
+                    }),
+                    CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr: ping_pong.addr().to_string(),
+                        msg: to_json_binary(&Cw20Hook::Ping {}).unwrap(),
+                        funds: vec![],
+                    }),
+                ],
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::MsgNotAllowed { index: 1 });
+}
+
+#[test]
+fn test_vault_deposit_and_redeem() {
+    let owner = Addr::unchecked("owner");
+    let not_owner = Addr::unchecked("not_owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, _usdc) = mock_app();
+
+    let receipt =
+        MockCw20Contract::instantiate_mintable(&mut app, &owner, treasury.addr()).unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateVault {
+            vault: Some(VaultConfig {
+                receipt_token: receipt.addr().clone(),
+                denom: "atom".to_string(),
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::Deposit {},
+            &[coin(100, "orai")],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::InvalidDeposit {
+            denom: "atom".to_string()
+        }
+    );
+
+    // shares are priced on the deposits only, a plain transfer does not move the price
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Deposit {},
+        &[coin(100, "atom")],
+    )
+    .unwrap();
+    app.send_tokens(owner.clone(), treasury.addr().clone(), &[coin(100, "atom")])
+        .unwrap();
+    app.execute_contract(
+        not_owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Deposit {},
+        &[coin(100, "atom")],
+    )
+    .unwrap();
+    assert_eq!(
+        receipt.query_balance(&app, &owner).balance,
+        Uint128::from(100u128)
+    );
+    assert_eq!(
+        receipt.query_balance(&app, &not_owner).balance,
+        Uint128::from(100u128)
+    );
+
+    // the vault can not be swapped under the outstanding shares
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::UpdateVault { vault: None },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::VaultInUse {});
+
+    // 100 of the 200 shares own half of the 200 atom deposited
+    app.execute_contract(
+        not_owner.clone(),
+        receipt.addr().clone(),
+        &Cw20ExecuteMsg::IncreaseAllowance {
+            spender: treasury.addr().to_string(),
+            amount: Uint128::from(100u128),
+            expires: None,
+        },
+        &[],
+    )
+    .unwrap();
+    let before = app.wrap().query_balance(&not_owner, "atom").unwrap().amount;
+    app.execute_contract(
+        not_owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Redeem {
+            shares: Uint128::from(100u128),
+        },
+        &[],
+    )
+    .unwrap();
+    let after = app.wrap().query_balance(&not_owner, "atom").unwrap().amount;
+    assert_eq!(after - before, Uint128::from(100u128));
+    assert!(receipt.query_balance(&app, &not_owner).balance.is_zero());
+}
+
+#[test]
+fn test_collect_burn_distribute() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, _cw20, ping_pong, _router, usdc) = mock_app();
+
+    // the treasury needs the atom it swaps on behalf of the approver
+    app.send_tokens(
+        owner.clone(),
+        treasury.addr().clone(),
+        &[coin(INITIAL_BALANCE / 2, "atom")],
+    )
+    .unwrap();
+    let supply_before: TokenInfoResponse = app
+        .wrap()
+        .query_wasm_smart(usdc.addr(), &Cw20QueryMsg::TokenInfo {})
+        .unwrap();
+
+    let requirement = CollectFeeRequirement {
+        approver: owner.clone(),
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x: "atom".into(),
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        minimum_receive: None,
+        min_price: None,
+    };
+    let collect_burn_distribute = |app: &mut StargateAccpetingModuleApp, nonce: u64| {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectBurnDistribute {
+                collect_fee_requirements: vec![requirement.clone()],
+                burn_ratio: Decimal::percent(20),
+                nonce: Some(nonce),
+            },
+            &[],
+        )
+    };
+    collect_burn_distribute(&mut app, 0).unwrap();
+
+    // a fifth of the collected usdc is burned, the rest split 40/60
+    let collected = Uint128::from(INITIAL_BALANCE / 2);
+    let burned = collected * Decimal::percent(20);
+    let distributed = collected - burned;
+    let supply_after: TokenInfoResponse = app
+        .wrap()
+        .query_wasm_smart(usdc.addr(), &Cw20QueryMsg::TokenInfo {})
+        .unwrap();
+    assert_eq!(
+        supply_before.total_supply - supply_after.total_supply,
+        burned
+    );
+    assert_eq!(
+        usdc.query_balance(&app, ping_pong.addr()).balance,
+        distributed.multiply_ratio(40u128, 100u128)
+    );
+    assert_eq!(
+        usdc.query_balance(&app, &finance).balance,
+        distributed.multiply_ratio(60u128, 100u128)
+    );
+    assert!(usdc.query_balance(&app, treasury.addr()).balance.is_zero());
+
+    // it is a distribution like any other, held to the nonce and the throttle
+    let err: ContractError = collect_burn_distribute(&mut app, 0)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::NonceMismatch { expected: 1 });
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributionInterval {
+            distribution_interval: 3600,
+        },
+        &[],
+    )
+    .unwrap();
+    let err: ContractError = collect_burn_distribute(&mut app, 1)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::DistributionThrottled {
+            available_at: app.block_info().time.plus_seconds(3600),
+        }
+    );
+}
+
+#[test]
+fn test_pull_mode_credits_and_claims() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, _cw20, ping_pong, _router, usdc) = mock_app();
+    let usdc_info = AssetInfo::Token {
+        contract_addr: usdc.addr().clone(),
+    };
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributionMode {
+            mode: DistributionMode::Pull,
+        },
+        &[],
+    )
+    .unwrap();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    // nothing leaves the treasury, the shares are credited instead
+    assert!(usdc.query_balance(&app, &finance).balance.is_zero());
+    let claimable = |app: &StargateAccpetingModuleApp, addr: &Addr| -> Vec<Asset> {
+        app.wrap()
+            .query_wasm_smart(treasury.addr(), &QueryMsg::Claimable { addr: addr.clone() })
+            .unwrap()
+    };
+    assert_eq!(
+        claimable(&app, ping_pong.addr()),
+        vec![Asset {
+            info: usdc_info.clone(),
+            amount: Uint128::from(40u128),
+        }]
+    );
+    assert_eq!(
+        claimable(&app, &finance),
+        vec![Asset {
+            info: usdc_info,
+            amount: Uint128::from(60u128),
+        }]
+    );
+
+    // the credited shares are not available to another distribution
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(1u64))
+        .unwrap_err();
+    assert_eq!(err, ContractError::ExceedContractBalance {});
+
+    app.execute_contract(
+        finance.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Claim {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        usdc.query_balance(&app, &finance).balance,
+        Uint128::from(60u128)
+    );
+    assert!(claimable(&app, &finance).is_empty());
+
+    let err: ContractError = app
+        .execute_contract(
+            finance.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::Claim {},
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::NothingToClaim {
+            addr: finance.to_string()
+        }
+    );
+}
+
+#[test]
+fn test_reclaim_unclaimed_after_expiry() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+    let usdc_info = AssetInfo::Token {
+        contract_addr: usdc.addr().clone(),
+    };
+
+    for msg in [
+        ExecuteMsg::UpdateDistributionMode {
+            mode: DistributionMode::Pull,
+        },
+        ExecuteMsg::UpdateClaimExpiry {
+            claim_expiry_seconds: Some(100),
+        },
+    ] {
+        app.execute_contract(owner.clone(), treasury.addr().clone(), &msg, &[])
+            .unwrap();
+    }
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+    let funded_at = app.block_info().time;
+
+    let reclaim = |app: &mut StargateAccpetingModuleApp| -> Result<AppResponse, ContractError> {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::ReclaimUnclaimed {
+                addr: finance.clone(),
+            },
+            &[],
+        )
+        .map_err(|err| err.downcast().unwrap())
+    };
+    assert_eq!(
+        reclaim(&mut app).unwrap_err(),
+        ContractError::ClaimNotExpired {
+            addr: finance.to_string(),
+            expires_at: funded_at.plus_seconds(100),
+        }
+    );
+
+    // finance never claims its 60
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+    reclaim(&mut app).unwrap();
+    let finance_claimable: Vec<Asset> = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::Claimable {
+                addr: finance.clone(),
+            },
+        )
+        .unwrap();
+    assert!(finance_claimable.is_empty());
+    let profile = app
+        .wrap()
+        .query_wasm_smart::<Option<TargetProfileResponse>>(
+            treasury.addr(),
+            &QueryMsg::TargetProfile {
+                addr: finance.clone(),
+                token: None,
+            },
+        )
+        .unwrap()
+        .unwrap();
+    assert!(profile.received.is_empty());
+
+    // the reclaimed share is distributable again, the unexpired 40 is still held
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(61u64))
+        .unwrap_err();
+    assert_eq!(err, ContractError::ExceedContractBalance {});
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(60u64))
+        .unwrap();
+    let finance_claimable: Vec<Asset> = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::Claimable {
+                addr: finance.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        finance_claimable,
+        vec![Asset {
+            info: usdc_info,
+            amount: Uint128::from(36u128),
+        }]
+    );
+}
+
+#[test]
+fn test_sunset_blocks_operations_but_emergency_withdraw() {
+    let owner = Addr::unchecked("owner");
+    let recipient = Addr::unchecked("recipient");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+
+    // half of the balance is left for the targets to claim
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributionMode {
+            mode: DistributionMode::Pull,
+        },
+        &[],
+    )
+    .unwrap();
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(50u64))
+        .unwrap();
+
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::Sunset {},
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::SunsetRecipientNotSet {});
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateSunsetRecipient {
+            recipient: Some(recipient.clone()),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Sunset {},
+        &[],
+    )
+    .unwrap();
+
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Paused {
+            operation: "distribute".to_string()
+        }
+    );
+
+    // not even the owner can lift the pause again
+    let sunset_at = app.block_info().time;
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::SetPauseFlags {
+                pause_flags: PauseFlags::default(),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::Sunset { sunset_at });
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Paused {
+            operation: "distribute".to_string()
+        }
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::EmergencyWithdraw {
+            assets: vec![AssetInfo::Token {
+                contract_addr: usdc.addr().clone(),
+            }],
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        usdc.query_balance(&app, &recipient).balance,
+        Uint128::from(50u128)
+    );
+
+    // the claimable shares are still paid out after the sunset
+    app.execute_contract(
+        finance.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Claim {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        usdc.query_balance(&app, &finance).balance,
+        Uint128::from(30u128)
+    );
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        Uint128::from(20u128)
+    );
+
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::Deposit {},
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::Paused {
+            operation: "collect".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_rounding_loss_matches_distribution_dust() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(999u64));
+
+    // 399.6 and 599.4 both round down
+    let rounding_loss: Uint128 = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::RoundingLoss {
+                amount: Uint128::from(999u128),
+            },
+        )
+        .unwrap();
+    assert_eq!(rounding_loss, Uint128::one());
+
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(999u64))
+        .unwrap();
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        rounding_loss
+    );
+}
+
+#[test]
+fn test_collect_fees_skips_below_min_price() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+
+    app.send_tokens(
+        owner.clone(),
+        treasury.addr().clone(),
+        &[coin(INITIAL_BALANCE / 2, "atom")],
+    )
+    .unwrap();
+
+    // the mock router quotes 1:1, under the required price of 2
+    let res = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![CollectFeeRequirement {
+                    approver: owner.clone(),
+                    swap_operations: vec![SwapOperation::SwapV3 {
+                        pool_key: PoolKey {
+                            token_x: "atom".into(),
+                            token_y: usdc.addr().to_string(),
+                            fee_tier: FeeTier {
+                                fee: Percentage(3u64),
+                                tick_spacing: 100,
+                            },
+                        },
+                        x_to_y: true,
+                    }],
+                    minimum_receive: None,
+                    min_price: Some(Decimal::percent(200)),
+                }],
+            },
+            &[],
+        )
+        .unwrap();
+
+    assert!(res.has_event(
+        &Event::new("wasm-collect-skipped-price")
+            .add_attribute("offer_asset", "atom")
+            .add_attribute("price", "1")
+            .add_attribute("min_price", "2")
+    ));
+    assert!(usdc.query_balance(&app, treasury.addr()).balance.is_zero());
+    let atom_balance = app.wrap().query_balance(treasury.addr(), "atom").unwrap();
+    assert_eq!(atom_balance.amount, Uint128::from(INITIAL_BALANCE / 2));
+}
+
+#[test]
+fn test_state_snapshot_round_trips_through_migrate() {
+    let owner = Addr::unchecked("owner");
+    let not_owner = Addr::unchecked("not_owner");
+    let (mut app, treasury, cw20, _ping_pong, router, usdc) = mock_app();
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::SetPauseFlags {
+            pause_flags: PauseFlags {
+                distribute: false,
+                collect: true,
+                admin: false,
+            },
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributionMode {
+            mode: DistributionMode::Pull,
+        },
+        &[],
+    )
+    .unwrap();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    // the settings travel with the snapshot
+    let updated_at = app.block_info().time;
+    let oracle = MockOracle::instantiate(&mut app, &owner, Decimal::one(), updated_at);
+    let oracle_config = OracleConfig {
+        oracle: oracle.addr().clone(),
+        max_oracle_staleness_seconds: 3600,
+    };
+    for msg in [
+        ExecuteMsg::UpdateOracle {
+            oracle: Some(oracle_config.clone()),
+        },
+        ExecuteMsg::UpdateVault {
+            vault: Some(VaultConfig {
+                receipt_token: cw20.addr().clone(),
+                denom: "orai".to_string(),
+            }),
+        },
+        ExecuteMsg::UpdateHookRetry {
+            hook_retry: Some(HookRetryConfig {
+                max_retries: 3,
+                backoff_blocks: 10,
+                fallback: not_owner.clone(),
+            }),
+        },
+        ExecuteMsg::UpdateDistributionInterval {
+            distribution_interval: 60,
+        },
+        ExecuteMsg::UpdateEpoch {
+            epoch: Some(EpochConfig {
+                epoch_length_seconds: 3600,
+                one_per_epoch: false,
+            }),
+        },
+        ExecuteMsg::UpdateMaxTargetShare {
+            max_target_share_bps: Some(6000),
+        },
+        ExecuteMsg::UpdateRemainderTarget {
+            remainder_target: Some(not_owner.clone()),
+        },
+        ExecuteMsg::UpdateWeightRegistry {
+            registry: Some(oracle.addr().clone()),
+        },
+        ExecuteMsg::UpdateRemovalGrace {
+            removal_grace_seconds: 3600,
+        },
+        ExecuteMsg::RemoveDistributeTarget {
+            addr: Addr::unchecked("finance"),
+        },
+        ExecuteMsg::UpdateExecuteAllowlist {
+            allowlist: vec![AllowedMsg::BankSend {
+                to_address: not_owner.clone(),
+                max_amount: vec![coin(10, "orai")],
+            }],
+        },
+        ExecuteMsg::UpdateSunsetRecipient {
+            recipient: Some(not_owner.clone()),
+        },
+        ExecuteMsg::UpdateAggregateEvents {
+            aggregate_events: true,
+        },
+        ExecuteMsg::UpdateMaxHooksPerDistribution { max_hooks: Some(5) },
+        ExecuteMsg::UpdateMaxAssetsPerCollect {
+            max_assets: Some(3),
+        },
+        ExecuteMsg::UpdateCollectCooldown {
+            collect_cooldown_seconds: 60,
+        },
+        ExecuteMsg::UpdateHistoryMaxAge {
+            history_max_age_seconds: Some(86400),
+        },
+        ExecuteMsg::UpdateClaimExpiry {
+            claim_expiry_seconds: Some(86400),
+        },
+    ] {
+        app.execute_contract(owner.clone(), treasury.addr().clone(), &msg, &[])
+            .unwrap();
+    }
+
+    let snapshot_page = |app: &StargateAccpetingModuleApp,
+                         contract: &Addr,
+                         start_after: Option<(Addr, AssetInfo)>,
+                         limit: Option<u32>|
+     -> StateSnapshot {
+        app.wrap()
+            .query_wasm_smart(contract, &QueryMsg::StateSnapshot { start_after, limit })
+            .unwrap()
+    };
+    let snapshot = snapshot_page(&app, treasury.addr(), None, None);
+    assert_eq!(snapshot.owner, Some(owner.clone()));
+    assert_eq!(snapshot.executors, vec![owner.clone()]);
+    assert_eq!(snapshot.distribute_targets.len(), 2);
+    assert_eq!(snapshot.claimable.len(), 2);
+    assert_eq!(snapshot.distribution_mode, DistributionMode::Pull);
+    assert_eq!(snapshot.oracle, Some(oracle_config));
+    assert_eq!(snapshot.distribution_interval, 60);
+    assert_eq!(snapshot.pending_removals.len(), 1);
+    assert_eq!(snapshot.remainder_target, Some(not_owner.clone()));
+    assert!(snapshot.aggregate_events);
+    assert_eq!(snapshot.received.len(), 2);
+    assert_eq!(snapshot.last_credited.len(), 2);
+
+    // the claimable shares are exported one page at a time
+    let first_page = snapshot_page(&app, treasury.addr(), None, Some(1));
+    assert_eq!(first_page.claimable, snapshot.claimable[..1].to_vec());
+    let (last_addr, last_asset) = first_page.claimable[0].clone();
+    let second_page = snapshot_page(
+        &app,
+        treasury.addr(),
+        Some((last_addr, last_asset.info)),
+        Some(1),
+    );
+    assert_eq!(second_page.claimable, snapshot.claimable[1..].to_vec());
+    assert_eq!(second_page.received, snapshot.received[1..].to_vec());
+    assert_eq!(
+        second_page.last_credited,
+        snapshot.last_credited[1..].to_vec()
+    );
+
+    // a fresh instance with an unrelated state takes over the exported one
+    let other = TreasuryContract::instantiate(
+        &mut app,
+        &owner,
+        &not_owner,
+        cw20.addr(),
+        Some(owner.clone().into()),
+        router.addr(),
+        vec![target(not_owner.clone(), 100)],
+    )
+    .unwrap();
+    let code_id = app.contract_data(other.addr()).unwrap().code_id;
+
+    // a snapshot of another release is refused
+    let err: ContractError = app
+        .migrate_contract(
+            owner.clone(),
+            other.addr().clone(),
+            &MigrateMsg {
+                new_router: router.addr().clone(),
+                import: Some(StateSnapshot {
+                    version: "999.0.0".to_string(),
+                    ..snapshot.clone()
+                }),
+            },
+            code_id,
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::IncompatibleSnapshot {
+            version: "999.0.0".to_string(),
+            expected: snapshot.version.clone(),
+        }
+    );
+
+    // the targets are validated like an update
+    let err: ContractError = app
+        .migrate_contract(
+            owner.clone(),
+            other.addr().clone(),
+            &MigrateMsg {
+                new_router: router.addr().clone(),
+                import: Some(StateSnapshot {
+                    max_target_share_bps: Some(5000),
+                    ..snapshot.clone()
+                }),
+            },
+            code_id,
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::TargetShareTooHigh {
+            addr: "finance".to_string(),
+            share_bps: 6000,
+            max_bps: 5000,
+        }
+    );
+    // and so is every address
+    let err: ContractError = app
+        .migrate_contract(
+            owner.clone(),
+            other.addr().clone(),
+            &MigrateMsg {
+                new_router: router.addr().clone(),
+                import: Some(StateSnapshot {
+                    sunset_recipient: Some(Addr::unchecked("")),
+                    ..snapshot.clone()
+                }),
+            },
+            code_id,
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert!(matches!(err, ContractError::Std(_)));
+
+    // the imported shares have to be funded
+    let err: ContractError = app
+        .migrate_contract(
+            owner.clone(),
+            other.addr().clone(),
+            &MigrateMsg {
+                new_router: router.addr().clone(),
+                import: Some(first_page.clone()),
+            },
+            code_id,
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::ReservesNotHeld {
+            asset: usdc.addr().to_string(),
+            reserved: first_page.claimable[0].1.amount,
+            balance: Uint128::zero(),
+        }
+    );
+    usdc.transfer(&mut app, &owner, other.addr(), Uint128::from(100u64));
+
+    // each page adds its shares, importing one twice does not count it twice
+    for page in [&first_page, &first_page, &second_page] {
+        app.migrate_contract(
+            owner.clone(),
+            other.addr().clone(),
+            &MigrateMsg {
+                new_router: router.addr().clone(),
+                import: Some(page.clone()),
+            },
+            code_id,
+        )
+        .unwrap();
+    }
+
+    let imported = snapshot_page(&app, other.addr(), None, None);
+    assert_eq!(imported, snapshot);
+
+    // the imported shares stay reserved for their targets
+    let err = other
+        .distribute_token(&owner, &mut app, Uint128::from(1u64))
+        .unwrap_err();
+    assert_eq!(err, ContractError::ExceedContractBalance {});
+}
+
+#[test]
+fn test_distribute_normalized_pays_equal_value() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let alice = Addr::unchecked("alice");
+    let (mut app, treasury, _cw20, _ping_pong, router, usdc) = mock_app();
+    let atom_info = AssetInfo::NativeToken {
+        denom: "atom".to_string(),
+    };
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributeTarget {
+            distribute_targets: vec![
+                target(finance.clone(), 50),
+                DistributeTarget {
+                    preferred_asset: Some(atom_info.clone()),
+                    ..target(alice.clone(), 50)
+                },
+            ],
+        },
+        &[],
+    )
+    .unwrap();
+
+    // one usdc averaged two atom, the spot price of three is not used
+    router.set_rate(&mut app, &atom_info, Decimal::percent(300));
+    router.set_twap(&mut app, &atom_info, Decimal::percent(200));
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    app.send_tokens(owner.clone(), treasury.addr().clone(), &[coin(99, "atom")])
+        .unwrap();
+
+    let distribute_normalized = |app: &mut StargateAccpetingModuleApp, nonce: Option<u64>| {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeNormalized {
+                amount: Uint128::from(100u128),
+                nonce,
+            },
+            &[],
+        )
+    };
+
+    // alice's half needs 100 atom
+    let err: ContractError = distribute_normalized(&mut app, Some(0))
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::ExceedContractBalance {});
+
+    app.send_tokens(owner.clone(), treasury.addr().clone(), &[coin(1, "atom")])
+        .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateAggregateEvents {
+            aggregate_events: true,
+        },
+        &[],
+    )
+    .unwrap();
+    let res = distribute_normalized(&mut app, Some(0)).unwrap();
+    assert!(res.has_event(&Event::new("wasm-distribute-summary").add_attribute("targets", "2")));
+
+    assert_eq!(
+        usdc.query_balance(&app, &finance).balance,
+        Uint128::from(50u128)
+    );
+    assert_eq!(
+        app.wrap().query_balance(&alice, "atom").unwrap().amount,
+        Uint128::from(100u128)
+    );
+    // alice's half is paid from the atom held, the usdc stays
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        Uint128::from(50u128)
+    );
+
+    // it is a scheduled distribution like any other
+    let history: Vec<DistributionHistoryEntry> = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::DistributionHistory { limit: None },
+        )
+        .unwrap();
+    assert_eq!(history.len(), 1);
+    let err: ContractError = distribute_normalized(&mut app, Some(0))
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::NonceMismatch { expected: 1 });
+}
+
+#[test]
+fn test_pull_mode_credits_normalized_and_diversified() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let alice = Addr::unchecked("alice");
+    let (mut app, treasury, _cw20, _ping_pong, router, usdc) = mock_app();
+    let usdc_info = AssetInfo::Token {
+        contract_addr: usdc.addr().clone(),
+    };
+    let atom_info = AssetInfo::NativeToken {
+        denom: "atom".to_string(),
+    };
+
+    // one usdc averaged two atom
+    router.set_twap(&mut app, &atom_info, Decimal::percent(200));
+    for msg in [
+        ExecuteMsg::UpdateDistributionMode {
+            mode: DistributionMode::Pull,
+        },
+        ExecuteMsg::UpdateDistributeTarget {
+            distribute_targets: vec![
+                target(finance.clone(), 50),
+                DistributeTarget {
+                    preferred_asset: Some(atom_info.clone()),
+                    ..target(alice.clone(), 50)
+                },
+            ],
+        },
+    ] {
+        app.execute_contract(owner.clone(), treasury.addr().clone(), &msg, &[])
+            .unwrap();
+    }
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(200u64));
+    app.send_tokens(owner.clone(), treasury.addr().clone(), &[coin(100, "atom")])
+        .unwrap();
+    app.send_tokens(owner.clone(), router.addr().clone(), &[coin(1000, "atom")])
+        .unwrap();
+    let claimable = |app: &StargateAccpetingModuleApp, addr: &Addr| -> Vec<Asset> {
+        app.wrap()
+            .query_wasm_smart(treasury.addr(), &QueryMsg::Claimable { addr: addr.clone() })
+            .unwrap()
+    };
+
+    // alice's share is credited in the atom it would have been paid in
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::DistributeNormalized {
+            amount: Uint128::from(100u128),
+            nonce: None,
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        claimable(&app, &finance),
+        vec![Asset {
+            info: usdc_info.clone(),
+            amount: Uint128::from(50u128),
+        }]
+    );
+    assert_eq!(
+        claimable(&app, &alice),
+        vec![Asset {
+            info: atom_info.clone(),
+            amount: Uint128::from(100u128),
+        }]
+    );
+    assert!(app
+        .wrap()
+        .query_balance(&alice, "atom")
+        .unwrap()
+        .amount
+        .is_zero());
+    // and counted as received in that atom, where a reclaim takes it back from
+    let profile = app
+        .wrap()
+        .query_wasm_smart::<Option<TargetProfileResponse>>(
+            treasury.addr(),
+            &QueryMsg::TargetProfile {
+                addr: alice.clone(),
+                token: None,
+            },
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        profile.received,
+        vec![Asset {
+            info: atom_info.clone(),
+            amount: Uint128::from(100u128),
+        }]
+    );
+
+    // the swapped atom is credited at the quote
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::DistributeDiversified {
+            amount: Uint128::from(100u128),
+            allocation: vec![AllocationEntry {
+                asset: atom_info.clone(),
+                ratio: Decimal::one(),
+                swap_operations: None,
+            }],
+            nonce: None,
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        claimable(&app, &finance),
+        vec![
+            Asset {
+                info: atom_info.clone(),
+                amount: Uint128::from(50u128),
+            },
+            Asset {
+                info: usdc_info,
+                amount: Uint128::from(50u128),
+            },
+        ]
+    );
+
+    app.execute_contract(
+        alice.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Claim {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_balance(&alice, "atom").unwrap().amount,
+        Uint128::from(150u128)
+    );
+    assert_eq!(
+        app.wrap()
+            .query_balance(treasury.addr(), "atom")
+            .unwrap()
+            .amount,
+        Uint128::from(50u128)
+    );
+}
+
+#[test]
+fn test_collect_fees_cooldown_per_asset() {
+    let owner = Addr::unchecked("owner");
+    let not_owner = Addr::unchecked("not_owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+
+    // enough atom held for the two swaps of the approver balance
+    app.send_tokens(
+        owner.clone(),
+        treasury.addr().clone(),
+        &[coin(INITIAL_BALANCE / 2, "atom")],
+    )
+    .unwrap();
+    app.send_tokens(
+        not_owner,
+        treasury.addr().clone(),
+        &[coin(INITIAL_BALANCE / 2, "atom")],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateCollectCooldown {
+            collect_cooldown_seconds: 3600,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let collect = |app: &mut StargateAccpetingModuleApp| {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::CollectFees {
+                collect_fee_requirements: vec![CollectFeeRequirement {
+                    approver: owner.clone(),
+                    swap_operations: vec![SwapOperation::SwapV3 {
+                        pool_key: PoolKey {
+                            token_x: "atom".into(),
+                            token_y: usdc.addr().to_string(),
+                            fee_tier: FeeTier {
+                                fee: Percentage(3u64),
+                                tick_spacing: 100,
+                            },
+                        },
+                        x_to_y: true,
+                    }],
+                    minimum_receive: None,
+                    min_price: None,
+                }],
+            },
+            &[],
+        )
+        .unwrap()
+    };
+    let swapped = Uint128::from(INITIAL_BALANCE / 2);
+
+    collect(&mut app);
+    assert_eq!(usdc.query_balance(&app, treasury.addr()).balance, swapped);
+
+    let res = collect(&mut app);
+    assert!(
+        res.has_event(&Event::new("wasm-collect-cooldown").add_attribute("offer_asset", "atom"))
+    );
+    assert_eq!(usdc.query_balance(&app, treasury.addr()).balance, swapped);
+
+    app.update_block(|block| block.time = block.time.plus_seconds(3600));
+    collect(&mut app);
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        swapped + swapped
+    );
+}
+
+#[test]
+fn test_distribute_nonce_rejects_stale_keeper() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(200u64));
+
+    let distribute = |app: &mut StargateAccpetingModuleApp, nonce: u64| {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::Distribute {
+                amount_distribute: Uint128::from(100u128),
+                token: None,
+                nonce: Some(nonce),
+            },
+            &[],
+        )
+    };
+
+    distribute(&mut app, 0).unwrap();
+
+    // a second keeper still holding nonce 0
+    let err: ContractError = distribute(&mut app, 0).unwrap_err().downcast().unwrap();
+    assert_eq!(err, ContractError::NonceMismatch { expected: 1 });
+
+    distribute(&mut app, 1).unwrap();
+    let nonce: u64 = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::DistributionNonce {})
+        .unwrap();
+    assert_eq!(nonce, 2);
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn test_distribute_pays_usd_target_at_oracle_price() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let payroll = Addr::unchecked("payroll");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+
+    // one distributed token is worth half a dollar
+    let updated_at = app.block_info().time;
+    let oracle = MockOracle::instantiate(&mut app, &owner, Decimal::percent(50), updated_at);
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateOracle {
+            oracle: Some(OracleConfig {
+                oracle: oracle.addr().clone(),
+                max_oracle_staleness_seconds: 60,
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributeTarget {
+            distribute_targets: vec![
+                DistributeTarget {
+                    usd_target: Some(Uint128::from(20u128)),
+                    ..target(payroll.clone(), 0)
+                },
+                target(finance.clone(), 100),
+            ],
+        },
+        &[],
+    )
+    .unwrap();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+
+    // too little to cover the USD target, it takes everything
+    let plan: EffectiveDistributionResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::EffectiveDistribution {
+                amount: Uint128::from(30u128),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        plan.shares,
+        vec![
+            DistributionShare {
+                addr: payroll.clone(),
+                amount: Uint128::from(30u128),
+            },
+            DistributionShare {
+                addr: finance.clone(),
+                amount: Uint128::zero(),
+            },
+        ]
+    );
+
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+    assert_eq!(
+        usdc.query_balance(&app, &payroll).balance,
+        Uint128::from(40u128)
+    );
+    assert_eq!(
+        usdc.query_balance(&app, &finance).balance,
+        Uint128::from(60u128)
+    );
+
+    // a zero price can not value the USD target
+    oracle.set_price(&mut app, usdc.addr().as_str(), Decimal::zero());
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UsdTargetUnpriced {
+            denom: usdc.addr().to_string(),
+        }
+    );
+
+    // neither can a stale one
+    oracle.set_price(&mut app, usdc.addr().as_str(), Decimal::percent(50));
+    app.update_block(|block| block.time = block.time.plus_seconds(61));
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap_err();
+    assert_eq!(err, ContractError::StaleOracle { updated_at });
+}
+
+#[test]
+fn test_distribute_aggregates_target_events() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, ping_pong, _router, usdc) = mock_app();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(200u64));
+
+    // by default a distribution keeps to its action attributes
+    let res = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+    assert!(!res
+        .events
+        .iter()
+        .any(|event| event.ty == "wasm-distribute-summary"));
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateAggregateEvents {
+            aggregate_events: true,
+        },
+        &[],
+    )
+    .unwrap();
+    let res = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    let payouts = vec![
+        DistributionShare {
+            addr: ping_pong.addr().clone(),
+            amount: Uint128::from(40u128),
+        },
+        DistributionShare {
+            addr: Addr::unchecked("finance"),
+            amount: Uint128::from(60u128),
+        },
+    ];
+    assert!(res.has_event(
+        &Event::new("wasm-distribute-summary")
+            .add_attribute("targets", "2")
+            .add_attribute("payouts", to_json_string(&payouts).unwrap())
+    ));
+}
+
+#[test]
+fn test_distribution_history_prunes_entries_past_max_age() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(300u64));
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateHistoryMaxAge {
+            history_max_age_seconds: Some(100),
+        },
+        &[],
+    )
+    .unwrap();
+    let query_history = |app: &StargateAccpetingModuleApp| -> Vec<DistributionHistoryEntry> {
+        app.wrap()
+            .query_wasm_smart(
+                treasury.addr(),
+                &QueryMsg::DistributionHistory { limit: None },
+            )
+            .unwrap()
+    };
+
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(10));
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+    assert_eq!(query_history(&app).len(), 2);
+
+    app.update_block(|block| block.time = block.time.plus_seconds(101));
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+    assert_eq!(
+        query_history(&app),
+        vec![DistributionHistoryEntry {
+            token: usdc.addr().to_string(),
+            amount: Uint128::from(100u128),
+            distributed_at: app.block_info().time,
+        }]
+    );
+}
+
+#[test]
+fn test_one_distribution_per_epoch() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, usdc) = mock_app();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(300u64));
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateEpoch {
+            epoch: Some(EpochConfig {
+                epoch_length_seconds: 3600,
+                one_per_epoch: true,
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+    let current_epoch = |app: &StargateAccpetingModuleApp| -> CurrentEpochResponse {
+        app.wrap()
+            .query_wasm_smart::<Option<CurrentEpochResponse>>(
+                treasury.addr(),
+                &QueryMsg::CurrentEpoch {},
+            )
+            .unwrap()
+            .unwrap()
+    };
+
+    let now = app.block_info().time.seconds();
+    let epoch = current_epoch(&app);
+    assert_eq!(
+        epoch,
+        CurrentEpochResponse {
+            epoch: now / 3600,
+            seconds_remaining: 3600 - now % 3600,
+        }
+    );
+
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+    let err = treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::EpochAlreadyDistributed { epoch: epoch.epoch }
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(epoch.seconds_remaining));
+    assert_eq!(
+        current_epoch(&app),
+        CurrentEpochResponse {
+            epoch: epoch.epoch + 1,
+            seconds_remaining: 3600,
+        }
+    );
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+}
+
+#[test]
+fn test_distribute_as_stake_delegates_by_weight() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, _ping_pong, _router, _usdc) = mock_app();
+
+    let block = app.block_info();
+    app.init_modules(|router, api, storage| {
+        router
+            .staking
+            .setup(
+                storage,
+                StakingInfo {
+                    bonded_denom: "orai".to_string(),
+                    unbonding_time: 60,
+                    apr: Decimal::percent(10),
+                },
+            )
+            .unwrap();
+        for address in ["validator1", "validator2"] {
+            router
+                .staking
+                .add_validator(
+                    api,
+                    storage,
+                    &block,
+                    Validator {
+                        address: address.to_string(),
+                        commission: Decimal::percent(5),
+                        max_commission: Decimal::percent(10),
+                        max_change_rate: Decimal::percent(1),
+                    },
+                )
+                .unwrap();
+        }
+    });
+    app.send_tokens(
+        owner.clone(),
+        treasury.addr().clone(),
+        &[coin(1000, "orai")],
+    )
+    .unwrap();
+
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeAsStake {
+                amount: Uint128::from(1000u128),
+                validators: vec![ValidatorWeight {
+                    validator: "unknown".to_string(),
+                    weight: 1,
+                }],
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ContractError::UnknownValidator {
+            validator: "unknown".to_string()
+        }
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateAggregateEvents {
+            aggregate_events: true,
+        },
+        &[],
+    )
+    .unwrap();
+    let res = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeAsStake {
+                amount: Uint128::from(1000u128),
+                validators: vec![
+                    ValidatorWeight {
+                        validator: "validator1".to_string(),
+                        weight: 30,
+                    },
+                    ValidatorWeight {
+                        validator: "validator2".to_string(),
+                        weight: 70,
+                    },
+                ],
+            },
+            &[],
+        )
+        .unwrap();
+
+    for (validator, delegated) in [("validator1", 300u128), ("validator2", 700u128)] {
+        let delegation = app
+            .wrap()
+            .query_delegation(treasury.addr(), validator)
+            .unwrap()
+            .unwrap();
+        assert_eq!(delegation.amount, coin(delegated, "orai"));
+    }
+    // the summary lists the validators
+    assert!(res.has_event(&Event::new("wasm-distribute-summary").add_attribute("targets", "2")));
+    let history: Vec<DistributionHistoryEntry> = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::DistributionHistory { limit: None },
+        )
+        .unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|entry| (entry.token.clone(), entry.amount))
+            .collect::<Vec<(String, Uint128)>>(),
+        vec![("orai".to_string(), Uint128::from(1000u128))]
+    );
+}
+
+#[test]
+fn test_target_profile_of_ping_target() {
+    let owner = Addr::unchecked("owner");
+    let alice = Addr::unchecked("alice");
+    let (mut app, treasury, cw20, ping_pong, _router, usdc) = mock_app();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(200u64));
+
+    for msg in [
+        ExecuteMsg::UpdateDistributionMode {
+            mode: DistributionMode::Pull,
+        },
+        ExecuteMsg::UpdateMaxTargetShare {
+            max_target_share_bps: Some(6000),
+        },
+        ExecuteMsg::UpdateRemovalGrace {
+            removal_grace_seconds: 100,
+        },
+        ExecuteMsg::RemoveDistributeTarget {
+            addr: ping_pong.addr().clone(),
+        },
+    ] {
+        app.execute_contract(owner.clone(), treasury.addr().clone(), &msg, &[])
+            .unwrap();
+    }
+    // one share is claimed, the next one is still credited
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+    app.execute_contract(
+        ping_pong.addr().clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::Claim {},
+        &[],
+    )
+    .unwrap();
+    treasury
+        .distribute_token(&owner, &mut app, Uint128::from(100u64))
+        .unwrap();
+
+    let target_profile = |app: &StargateAccpetingModuleApp, addr: &Addr| {
+        app.wrap()
+            .query_wasm_smart::<Option<TargetProfileResponse>>(
+                treasury.addr(),
+                &QueryMsg::TargetProfile {
+                    addr: addr.clone(),
+                    token: None,
+                },
+            )
+            .unwrap()
+    };
+    let profile = target_profile(&app, ping_pong.addr());
+    assert_eq!(
+        profile,
+        Some(TargetProfileResponse {
+            target: DistributeTarget {
+                msg_hook: Some(to_json_binary(&Cw20Hook::Ping {}).unwrap()),
+                ..target(ping_pong.addr().clone(), 40)
+            },
+            share_bps: 4000,
+            enabled: true,
+            removal_effective_at: Some(app.block_info().time.plus_seconds(100)),
+            received: vec![Asset {
+                info: AssetInfo::Token {
+                    contract_addr: usdc.addr().clone(),
+                },
+                amount: Uint128::from(80u128),
+            }],
+            claimable: vec![Asset {
+                info: AssetInfo::Token {
+                    contract_addr: usdc.addr().clone(),
+                },
+                amount: Uint128::from(40u128),
+            }],
+            max_share_bps: Some(6000),
+            hook_type: Some("ping".to_string()),
+            token_list: None,
+        })
+    );
+
+    // targets of a per-token list are found there
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateTokenDistributeTargets {
+            token: AssetInfo::Token {
+                contract_addr: cw20.addr().clone(),
+            },
+            distribute_targets: vec![target(alice.clone(), 50), target("finance", 50)],
+        },
+        &[],
+    )
+    .unwrap();
+    let profile = target_profile(&app, &alice).unwrap();
+    assert_eq!(profile.token_list, Some(cw20.addr().to_string()));
+    assert_eq!(profile.share_bps, 5000);
+    assert!(profile.received.is_empty());
+}
+
+#[test]
+fn test_distribute_diversified_splits_each_swapped_asset() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let alice = Addr::unchecked("alice");
+    let (mut app, treasury, _cw20, _ping_pong, router, usdc) = mock_app();
+    let atom_info = AssetInfo::NativeToken {
+        denom: "atom".to_string(),
+    };
+    let orai_info = AssetInfo::NativeToken {
+        denom: "orai".to_string(),
+    };
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributeTarget {
+            distribute_targets: vec![target(finance.clone(), 40), target(alice.clone(), 60)],
+        },
+        &[],
+    )
+    .unwrap();
+    // one usdc is worth two atom
+    router.set_rate(&mut app, &atom_info, Decimal::percent(200));
+    app.send_tokens(
+        owner.clone(),
+        router.addr().clone(),
+        &[coin(1000, "atom"), coin(1000, "orai")],
+    )
+    .unwrap();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeDiversified {
+                amount: Uint128::from(100u128),
+                allocation: vec![AllocationEntry {
+                    asset: atom_info.clone(),
+                    ratio: Decimal::percent(50),
+                    swap_operations: None,
+                }],
+                nonce: None,
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::InvalidAllocation {});
+
+    // orai is routed through atom instead of the direct pair
+    let allocation = vec![
+        AllocationEntry {
+            asset: atom_info.clone(),
+            ratio: Decimal::percent(50),
+            swap_operations: None,
+        },
+        AllocationEntry {
+            asset: orai_info.clone(),
+            ratio: Decimal::percent(50),
+            swap_operations: Some(vec![
+                SwapOperation::OraiSwap {
+                    offer_asset_info: AssetInfo::Token {
+                        contract_addr: usdc.addr().clone(),
+                    },
+                    ask_asset_info: atom_info,
+                },
+                SwapOperation::OraiSwap {
+                    offer_asset_info: AssetInfo::NativeToken {
+                        denom: "atom".to_string(),
+                    },
+                    ask_asset_info: orai_info,
+                },
+            ]),
+        },
+    ];
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::DistributeDiversified {
+            amount: Uint128::from(100u128),
+            allocation: allocation.clone(),
+            nonce: Some(0),
+        },
+        &[],
+    )
+    .unwrap();
+
+    for (addr, atom, orai) in [(&finance, 40u128, 20u128), (&alice, 60u128, 30u128)] {
+        assert_eq!(
+            app.wrap().query_balance(addr, "atom").unwrap().amount,
+            Uint128::from(atom)
+        );
+        assert_eq!(
+            app.wrap().query_balance(addr, "orai").unwrap().amount,
+            Uint128::from(orai)
+        );
+    }
+    assert_eq!(
+        usdc.query_balance(&app, treasury.addr()).balance,
+        Uint128::zero()
+    );
+    let history: Vec<DistributionHistoryEntry> = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::DistributionHistory { limit: None },
+        )
+        .unwrap();
+    assert_eq!(history[0].amount, Uint128::from(100u128));
+
+    // a replayed nonce is rejected like any other distribution
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeDiversified {
+                amount: Uint128::from(100u128),
+                allocation,
+                nonce: Some(0),
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::NonceMismatch { expected: 1 });
+}
+
+#[test]
+fn test_distribute_diversified_caps_hooks_across_the_basket() {
+    let owner = Addr::unchecked("owner");
+    let finance = Addr::unchecked("finance");
+    let (mut app, treasury, cw20, ping_pong, router, usdc) = mock_app();
+    let usdc_info = AssetInfo::Token {
+        contract_addr: usdc.addr().clone(),
+    };
+    let cw20_info = AssetInfo::Token {
+        contract_addr: cw20.addr().clone(),
+    };
+
+    let hook_target = DistributeTarget {
+        msg_hook: Some(to_json_binary(&Cw20Hook::Ping {}).unwrap()),
+        ..target(ping_pong.addr().clone(), 40)
+    };
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateDistributeTarget {
+            distribute_targets: vec![
+                hook_target.clone(),
+                DistributeTarget {
+                    weight: 20,
+                    ..hook_target
+                },
+                target(finance.clone(), 40),
+            ],
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::UpdateMaxHooksPerDistribution { max_hooks: Some(1) },
+        &[],
+    )
+    .unwrap();
+    cw20.transfer(&mut app, &owner, router.addr(), Uint128::from(1000u64));
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+
+    let allocation = vec![
+        AllocationEntry {
+            asset: usdc_info,
+            ratio: Decimal::percent(50),
+            swap_operations: None,
+        },
+        AllocationEntry {
+            asset: cw20_info,
+            ratio: Decimal::percent(50),
+            swap_operations: None,
+        },
+    ];
+    let res = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeDiversified {
+                amount: Uint128::from(100u128),
+                allocation: allocation.clone(),
+                nonce: None,
+            },
+            &[],
+        )
+        .unwrap();
+    // one hook fires for the whole basket, the cw20 hooks wait behind the usdc one
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("action", "distribute_diversified")
+            .add_attribute("deferred_hooks", "3")
+    ));
+    assert_eq!(
+        usdc.query_balance(&app, ping_pong.addr()).balance,
+        Uint128::from(20u128)
+    );
+    assert_eq!(
+        cw20.query_balance(&app, ping_pong.addr()).balance,
+        Uint128::zero()
+    );
+    assert_eq!(
+        cw20.query_balance(&app, &finance).balance,
+        Uint128::from(20u128)
+    );
+    let pending: PendingDistribution = app
+        .wrap()
+        .query_wasm_smart::<Option<PendingDistribution>>(
+            treasury.addr(),
+            &QueryMsg::PendingDistribution {},
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(pending.distribute_token, usdc.addr().clone());
+    assert_eq!(pending.payouts.len(), 1);
+    assert_eq!(pending.queued.len(), 1);
+    assert_eq!(pending.queued[0].distribute_token, cw20.addr().clone());
+
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    let err: ContractError = app
+        .execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::DistributeDiversified {
+                amount: Uint128::from(100u128),
+                allocation,
+                nonce: None,
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, ContractError::DistributionInProgress {});
+
+    // the usdc hook, then one cw20 hook per call under the cap
+    for _ in 0..3 {
+        app.execute_contract(
+            owner.clone(),
+            treasury.addr().clone(),
+            &ExecuteMsg::ContinueDistribution {},
+            &[],
+        )
+        .unwrap();
+    }
+    let pending: Option<PendingDistribution> = app
+        .wrap()
+        .query_wasm_smart(treasury.addr(), &QueryMsg::PendingDistribution {})
+        .unwrap();
+    assert!(pending.is_none());
+    assert_eq!(
+        usdc.query_balance(&app, ping_pong.addr()).balance,
+        Uint128::from(30u128)
+    );
+    assert_eq!(
+        cw20.query_balance(&app, ping_pong.addr()).balance,
+        Uint128::from(30u128)
+    );
+    assert_eq!(
+        cw20.query_balance(&app, treasury.addr()).balance,
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn test_full_cycle_plan_matches_collect_and_distribute() {
+    let owner = Addr::unchecked("owner");
+    let (mut app, treasury, _cw20, ping_pong, _router, usdc) = mock_app();
+    let finance = Addr::unchecked("finance");
+
+    // atom held to fund the swap of the approver balance
+    app.send_tokens(
+        owner.clone(),
+        treasury.addr().clone(),
+        &[coin(INITIAL_BALANCE / 2, "atom")],
+    )
+    .unwrap();
+    usdc.transfer(&mut app, &owner, treasury.addr(), Uint128::from(100u64));
+    let collect_fee_requirements = vec![CollectFeeRequirement {
+        approver: owner.clone(),
+        swap_operations: vec![SwapOperation::SwapV3 {
+            pool_key: PoolKey {
+                token_x: "atom".into(),
+                token_y: usdc.addr().to_string(),
+                fee_tier: FeeTier {
+                    fee: Percentage(3u64),
+                    tick_spacing: 100,
+                },
+            },
+            x_to_y: true,
+        }],
+        minimum_receive: None,
+        min_price: None,
+    }];
+
+    let plan: FullCyclePlanResponse = app
+        .wrap()
+        .query_wasm_smart(
+            treasury.addr(),
+            &QueryMsg::FullCyclePlan {
+                collect_fee_requirements: collect_fee_requirements.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(plan.collects.len(), 1);
+    assert_eq!(plan.distributable, Uint128::from(INITIAL_BALANCE / 2 + 100));
+
+    app.execute_contract(
+        owner.clone(),
+        treasury.addr().clone(),
+        &ExecuteMsg::CollectFees {
+            collect_fee_requirements,
+        },
+        &[],
+    )
+    .unwrap();
+    treasury
+        .distribute_token(&owner, &mut app, plan.distributable)
+        .unwrap();
+
+    assert_eq!(
+        plan.shares,
+        vec![
+            DistributionShare {
+                addr: ping_pong.addr().clone(),
+                amount: usdc.query_balance(&app, ping_pong.addr()).balance,
+            },
+            DistributionShare {
+                addr: finance.clone(),
+                amount: usdc.query_balance(&app, &finance).balance,
+            },
+        ]
+    );
+}
@@ -1,12 +1,37 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Binary};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Timestamp, Uint128};
 use cw_storage_plus::{Item, Map};
+use oraiswap::asset::AssetInfo;
+
+use crate::msg::CollectFeeRequirement;
+use std::collections::BTreeMap;
 
 const CONFIG_KEY: &str = "config";
 const DISTRIBUTION_TARGET: &str = "distribution_target";
 
 #[cw_serde]
 pub struct Config {
+    pub distribute_token: Addr,
+    pub router: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct ConfigHistoryEntry {
+    pub config: Config,
+    pub updated_at: Timestamp,
+}
+
+#[cw_serde]
+pub struct DistributionHistoryEntry {
+    /// denom or cw20 address of the distributed token
+    pub token: String,
+    pub amount: Uint128,
+    pub distributed_at: Timestamp,
+}
+
+/// Config layout used before ownership moved to cw-ownable, only read on migrate
+#[cw_serde]
+pub struct LegacyConfig {
     pub owner: Addr,
     pub distribute_token: Addr,
     pub router: Option<Addr>,
@@ -17,8 +42,243 @@ pub struct DistributeTarget {
     pub addr: Addr,
     pub weight: u32, // total weight distribute target should be 100
     pub msg_hook: Option<Binary>,
+    /// asset the share is swapped into through the router before being sent
+    pub preferred_asset: Option<AssetInfo>,
+    /// USD value paid ahead of the weighted shares, priced through the oracle
+    pub usd_target: Option<Uint128>,
+    /// gas the hook call may use, uncapped when unset
+    pub hook_gas_limit: Option<u64>,
+    /// pays the share through an ICS-20 transfer instead of a bank send
+    pub ibc: Option<IbcTarget>,
+}
+
+#[cw_serde]
+pub struct IbcTarget {
+    pub channel_id: String,
+    /// address on the counterparty chain, the first hop of a forwarded transfer
+    pub receiver: String,
+    pub timeout_seconds: u64,
+    /// packet forward middleware route the transfer continues on
+    pub forward: Option<ForwardMemo>,
+}
+
+/// Serialized as the `forward` object of a packet forward middleware memo
+#[cw_serde]
+pub struct ForwardMemo {
+    pub receiver: String,
+    pub port: String,
+    pub channel: String,
+    /// go duration such as `10m`, left to the middleware default when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u8>,
+    /// memo handed to the next hop
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<Box<PacketMemo>>,
+}
+
+#[cw_serde]
+pub struct PacketMemo {
+    pub forward: ForwardMemo,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct PauseFlags {
+    pub distribute: bool,
+    pub collect: bool,
+    pub admin: bool,
+}
+
+/// Balances captured before the CollectFees swaps, consumed by the reply
+#[cw_serde]
+pub struct CollectSnapshot {
+    pub assets: Vec<AssetInfo>,
+    pub before: BTreeMap<String, Uint128>,
+    /// part of the collected distribute token burned before the rest is distributed,
+    /// unset for a plain collection
+    pub burn_ratio: Option<Decimal>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum TargetKindRestriction {
+    #[default]
+    Any,
+    ContractsOnly,
+    WalletsOnly,
+}
+
+/// Push sends the shares out when distributing, Pull credits them for `Claim`.
+/// Pull is the default, contracts deployed before it keep pushing after migration
+#[cw_serde]
+#[derive(Default)]
+pub enum DistributionMode {
+    Push,
+    #[default]
+    Pull,
+}
+
+/// Periods of `epoch_length_seconds` counted from the unix epoch
+#[cw_serde]
+pub struct EpochConfig {
+    pub epoch_length_seconds: u64,
+    /// allow a single `Distribute` per epoch
+    pub one_per_epoch: bool,
+}
+
+/// Target keeping its share until `effective_at`, after which it is skipped
+#[cw_serde]
+pub struct PendingRemoval {
+    pub addr: Addr,
+    pub effective_at: Timestamp,
+}
+
+/// Hook failures are caught and kept for `RetryHook` while this is set, a hook
+/// still failing after `max_retries` has its payout sent to `fallback`
+#[cw_serde]
+pub struct HookRetryConfig {
+    pub max_retries: u32,
+    /// blocks to wait before each retry, multiplied by the retry count
+    pub backoff_blocks: u64,
+    pub fallback: Addr,
+}
+
+/// Hook payout in flight, or waiting for `RetryHook` once it failed
+#[cw_serde]
+pub struct HookAttempt {
+    pub target: Addr,
+    pub token: Addr,
+    pub amount: Uint128,
+    pub msg_hook: Binary,
+    pub retries: u32,
+    pub retry_at_height: u64,
+    pub gas_limit: Option<u64>,
+}
+
+/// Price oracle distributions and collections are checked against
+#[cw_serde]
+pub struct OracleConfig {
+    pub oracle: Addr,
+    pub max_oracle_staleness_seconds: u64,
+}
+
+/// Authz grant a granter reported giving the treasury for the `MsgSend` of its
+/// native fees, as registered by the owner and not checked against the chain
+#[cw_serde]
+pub struct CollectGrant {
+    pub expiration: Option<Timestamp>,
+}
+
+/// Message kind `Execute` may dispatch to a single recipient or contract. Only the
+/// listed denoms can be sent, each up to its amount
+#[cw_serde]
+pub enum AllowedMsg {
+    BankSend {
+        to_address: Addr,
+        max_amount: Vec<Coin>,
+    },
+    WasmExecute {
+        contract_addr: Addr,
+        max_funds: Vec<Coin>,
+    },
+}
+
+/// Deposits of `denom` mint `receipt_token` shares redeemable for a pro-rata part
+/// of the `denom` deposited, the contract must be the receipt token minter
+#[cw_serde]
+pub struct VaultConfig {
+    pub receipt_token: Addr,
+    pub denom: String,
+}
+
+/// Instantiate parameters, kept untouched for provenance
+#[cw_serde]
+pub struct GenesisConfig {
+    pub owner: Addr,
+    pub distribute_token: Addr,
+    pub router: Option<Addr>,
+    pub init_distribution_targets: Vec<DistributeTarget>,
+}
+
+/// Payouts of a distribution left for `ContinueDistribution` once the hook cap was hit
+#[cw_serde]
+pub struct PendingDistribution {
+    pub distribute_token: Addr,
+    pub payouts: Vec<(Uint128, DistributeTarget)>,
+    /// deferred payouts of the other cw20 assets of a diversified distribution,
+    /// continued once these are paid. They carry no queue of their own
+    #[serde(default)]
+    pub queued: Vec<PendingDistribution>,
 }
 
 pub const CONFIG: Item<Config> = Item::new(CONFIG_KEY);
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new(CONFIG_KEY);
 pub const DISTRIBUTION_TARGETS: Item<Vec<DistributeTarget>> = Item::new(DISTRIBUTION_TARGET);
 pub const EXECUTORS: Map<&Addr, bool> = Map::new("executors");
+pub const PAUSE_FLAGS: Item<PauseFlags> = Item::new("pause_flags");
+pub const TARGET_KIND_RESTRICTION: Item<TargetKindRestriction> =
+    Item::new("target_kind_restriction");
+pub const WEIGHT_REGISTRY: Item<Addr> = Item::new("weight_registry");
+pub const COLLECT_SNAPSHOT: Item<CollectSnapshot> = Item::new("collect_snapshot");
+pub const MAX_HOOKS_PER_DISTRIBUTION: Item<u32> = Item::new("max_hooks_per_distribution");
+pub const PENDING_DISTRIBUTION: Item<PendingDistribution> = Item::new("pending_distribution");
+pub const GENESIS_CONFIG: Item<GenesisConfig> = Item::new("genesis_config");
+pub const REMOVAL_GRACE_SECONDS: Item<u64> = Item::new("removal_grace_seconds");
+pub const PENDING_REMOVALS: Map<&Addr, Timestamp> = Map::new("pending_removals");
+pub const REMAINDER_TARGET: Item<Addr> = Item::new("remainder_target");
+/// Config updates, oldest first
+pub const CONFIG_HISTORY: Item<Vec<ConfigHistoryEntry>> = Item::new("config_history");
+/// Distributions, oldest first
+pub const DISTRIBUTION_HISTORY: Item<Vec<DistributionHistoryEntry>> =
+    Item::new("distribution_history");
+pub const HISTORY_MAX_AGE_SECONDS: Item<u64> = Item::new("history_max_age_seconds");
+/// Target lists of tokens distributed to other recipients than the default list,
+/// keyed by denom or cw20 address
+pub const TOKEN_DISTRIBUTION_TARGETS: Map<&str, Vec<DistributeTarget>> =
+    Map::new("token_distribution_targets");
+pub const HOOK_RETRY: Item<HookRetryConfig> = Item::new("hook_retry");
+pub const HOOK_ATTEMPTS: Map<u64, HookAttempt> = Map::new("hook_attempts");
+/// Failed hook payouts keyed by target and cw20 token, failures of one target in
+/// different tokens are kept apart
+pub const FAILED_HOOKS: Map<(&Addr, &Addr), HookAttempt> = Map::new("failed_hooks");
+pub const DISTRIBUTION_INTERVAL: Item<u64> = Item::new("distribution_interval");
+pub const LAST_DISTRIBUTION: Item<Timestamp> = Item::new("last_distribution");
+pub const EPOCH: Item<EpochConfig> = Item::new("epoch");
+/// Incremented by every `Distribute`, lets concurrent keepers detect they raced
+pub const DISTRIBUTION_NONCE: Item<u64> = Item::new("distribution_nonce");
+/// Whether `Distribute` emits one summary event of its payouts
+pub const AGGREGATE_EVENTS: Item<bool> = Item::new("aggregate_events");
+pub const MAX_ASSETS_PER_COLLECT: Item<u32> = Item::new("max_assets_per_collect");
+/// Requirements left for `ContinueCollect` once the per call cap was hit
+pub const PENDING_COLLECT: Item<Vec<CollectFeeRequirement>> = Item::new("pending_collect");
+/// `max_total_offer` guard of the guarded collection the pending requirements belong to
+pub const PENDING_COLLECT_GUARD: Item<Uint128> = Item::new("pending_collect_guard");
+pub const ORACLE: Item<OracleConfig> = Item::new("oracle");
+pub const COLLECT_GRANTS: Map<&Addr, CollectGrant> = Map::new("collect_grants");
+pub const COLLECT_COOLDOWN_SECONDS: Item<u64> = Item::new("collect_cooldown_seconds");
+/// Last time each offer asset was swapped by a collection, keyed by denom or cw20 address
+pub const LAST_COLLECTED: Map<&str, Timestamp> = Map::new("last_collected");
+pub const EXECUTE_ALLOWLIST: Item<Vec<AllowedMsg>> = Item::new("execute_allowlist");
+pub const VAULT: Item<VaultConfig> = Item::new("vault");
+/// Vault denom deposited and not redeemed yet, the rest of the balance is not the vault's
+pub const VAULT_HELD: Item<Uint128> = Item::new("vault_held");
+pub const DISTRIBUTION_MODE: Item<DistributionMode> = Item::new("distribution_mode");
+/// Credited shares of the Pull mode, keyed by target then denom or cw20 address
+pub const CLAIMABLE: Map<(&Addr, &str), Uint128> = Map::new("claimable");
+/// Sum of the credited shares per denom or cw20 address, kept out of distributions
+pub const TOTAL_CLAIMABLE: Map<&str, Uint128> = Map::new("total_claimable");
+/// Time each target was last credited a share in Pull mode
+pub const LAST_CREDITED: Map<&Addr, Timestamp> = Map::new("last_credited");
+/// Seconds after its last credit a target's unclaimed shares may be reclaimed
+pub const CLAIM_EXPIRY_SECONDS: Item<u64> = Item::new("claim_expiry_seconds");
+/// Shares paid out or credited to each target since it was first paid, keyed by
+/// target then denom or cw20 address of the distributed token
+pub const TARGET_RECEIVED: Map<(&Addr, &str), Uint128> = Map::new("target_received");
+pub const SUNSET_RECIPIENT: Item<Addr> = Item::new("sunset_recipient");
+/// Time the contract was sunset at, every operation but `EmergencyWithdraw` stays
+/// paused from then on
+pub const SUNSET: Item<Timestamp> = Item::new("sunset");
+/// Largest part of the total weight a single target may hold, in basis points
+pub const MAX_TARGET_SHARE_BPS: Item<u32> = Item::new("max_target_share_bps");